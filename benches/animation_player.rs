@@ -0,0 +1,139 @@
+//! Measures the cost of `animation_player` for many skeletons, with bone bindings kept
+//! between frames, against looking every bone up by name every frame as it used to. Also
+//! measures bindings kept while other hierarchies change, and poses shared through the
+//! `AnimationPoseCache`.
+
+use bevy::{asset::AssetPlugin, ecs::world::EntityMut, prelude::*};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use motion_warp::*;
+
+/// About as many bones as the fox
+const BONES: usize = 24;
+
+fn bone_name(bone: usize) -> Name {
+    Name::new(format!("bone{bone}"))
+}
+
+fn skeleton_clip() -> AnimationClip {
+    let mut clip = AnimationClip::default();
+    let mut path = EntityPath { parts: vec![Name::new("root")] };
+    for bone in 0..BONES {
+        path.parts.push(bone_name(bone));
+        clip.add_curve_to_path(
+            path.clone(),
+            VariableCurve {
+                keyframe_timestamps: (0..30).map(|i| i as f32 / 30.0).collect(),
+                keyframes: Keyframes::Rotation((0..30).map(|i| Quat::from_rotation_y(i as f32 * 0.1)).collect()),
+            },
+        );
+    }
+    clip
+}
+
+/// A player looking up the entity of every path of its clip each frame, walking the hierarchy
+/// by name from its root, like `animation_player` did before bindings were kept.
+#[derive(Component)]
+struct FindBonePlayer(Handle<AnimationClip>);
+
+fn find_bone_player(
+    time: Res<Time>,
+    animations: Res<Assets<AnimationClip>>,
+    players: Query<(Entity, &FindBonePlayer)>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for (root, player) in &players {
+        let Some(clip) = animations.get(&player.0) else { continue };
+        let elapsed = time.elapsed_seconds().rem_euclid(clip.duration());
+        for (path, bone_id) in clip.paths() {
+            let mut bone = Some(root);
+            for part in path.parts.iter().skip(1) {
+                bone = bone.and_then(|bone| children.get(bone).ok()).and_then(|children| {
+                    children.iter().find(|child| names.get(**child).is_ok_and(|name| name == part)).copied()
+                });
+            }
+            let Some(mut transform) = bone.and_then(|bone| transforms.get_mut(bone).ok()) else { continue };
+            if let Some(joint) = clip.sample_joint(*bone_id, elapsed) {
+                joint.apply(&mut transform, 1.0);
+            }
+        }
+    }
+}
+
+fn setup(skeletons: usize) -> App {
+    setup_with(skeletons, |player, clip| {
+        player.insert(AnimationPlayer::default()).get_mut::<AnimationPlayer>().unwrap().play(clip).repeat();
+    })
+}
+
+fn setup_with(skeletons: usize, add_player: impl Fn(&mut EntityMut, Handle<AnimationClip>)) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin::default())
+        .add_plugin(AnimationPlugin::default())
+        .add_system(find_bone_player);
+
+    let clip = app.world.resource_mut::<Assets<AnimationClip>>().add(skeleton_clip());
+    for _ in 0..skeletons {
+        let mut root = app.world.spawn((TransformBundle::default(), Name::new("root")));
+        add_player(&mut root, clip.clone());
+        let mut parent = root.id();
+        // A chain of bones, each with a sibling that isn't animated
+        for bone in 0..BONES {
+            let child = app.world.spawn((TransformBundle::default(), bone_name(bone))).id();
+            let sibling = app.world.spawn((TransformBundle::default(), Name::new("sibling"))).id();
+            app.world.entity_mut(parent).push_children(&[sibling, child]);
+            parent = child;
+        }
+    }
+    app.update();
+    app
+}
+
+fn animation_player(c: &mut Criterion) {
+    let mut group = c.benchmark_group("animation_player");
+    for skeletons in [100, 1000] {
+        let mut app = setup(skeletons);
+        group.bench_with_input(BenchmarkId::new("bound", skeletons), &skeletons, |b, _| {
+            b.iter(|| app.update());
+        });
+
+        let mut app = setup_with(skeletons, |player, clip| {
+            player.insert(FindBonePlayer(clip));
+        });
+        group.bench_with_input(BenchmarkId::new("find_bone", skeletons), &skeletons, |b, _| {
+            b.iter(|| app.update());
+        });
+
+        let mut app = setup(skeletons);
+        let mut spawned = None;
+        group.bench_with_input(BenchmarkId::new("hierarchy_churn", skeletons), &skeletons, |b, _| {
+            b.iter(|| {
+                // Named entities coming and going elsewhere, such as projectiles, keep the
+                // bindings of the skeletons
+                if let Some(spawned) = spawned.take() {
+                    app.world.entity_mut(spawned).despawn_recursive();
+                }
+                let child = app.world.spawn((TransformBundle::default(), Name::new("trail"))).id();
+                spawned = Some(
+                    app.world
+                        .spawn((TransformBundle::default(), Name::new("projectile")))
+                        .push_children(&[child])
+                        .id(),
+                );
+                app.update();
+            });
+        });
+
+        let mut app = setup(skeletons);
+        app.insert_resource(AnimationPoseCache::default());
+        group.bench_with_input(BenchmarkId::new("pose_cache", skeletons), &skeletons, |b, _| {
+            b.iter(|| app.update());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, animation_player);
+criterion_main!(benches);
@@ -1,5 +1,7 @@
 //! Loads animations from a skinned glTF, spawns many of them, and plays the
-//! animation to stress test skinned meshes.
+//! animation to stress test skinned meshes. Pass `--lod` to throttle far away and
+//! off-screen foxes with `AnimationLod`, and `--pose-cache` to share sampled poses
+//! between foxes with `AnimationPoseCache`, to compare frame times.
 
 use std::f32::consts::PI;
 use std::time::Duration;
@@ -15,12 +17,15 @@ use motion_warp::*;
 #[derive(Resource)]
 struct Foxes {
     count: usize,
+    /// Throttle far away and hidden foxes with `AnimationLod`
+    lod: bool,
     speed: f32,
     moving: bool,
 }
 
 fn main() {
-    App::new()
+    let mut app = App::new();
+    app
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "🦊🦊🦊 Many Foxes! 🦊🦊🦊".into(),
@@ -34,8 +39,10 @@ fn main() {
         .add_plugin(LogDiagnosticsPlugin::default())
         .insert_resource(Foxes {
             count: std::env::args()
-                .nth(1)
+                .skip(1)
+                .find(|arg| !arg.starts_with("--"))
                 .map_or(1000, |s| s.parse::<usize>().unwrap()),
+            lod: std::env::args().any(|arg| arg == "--lod"),
             speed: 2.0,
             moving: true,
         })
@@ -46,8 +53,11 @@ fn main() {
         .add_startup_system(setup)
         .add_system(setup_scene_once_loaded)
         .add_system(keyboard_animation_control)
-        .add_system(update_fox_rings.after(keyboard_animation_control))
-        .run();
+        .add_system(update_fox_rings.after(keyboard_animation_control));
+    if std::env::args().any(|arg| arg == "--pose-cache") {
+        app.insert_resource(AnimationPoseCache::default());
+    }
+    app.run();
 }
 
 #[derive(Resource)]
@@ -192,14 +202,18 @@ fn setup(
 
 // Once the scene is loaded, start the animation
 fn setup_scene_once_loaded(
+    mut commands: Commands,
     animations: Res<Animations>,
     foxes: Res<Foxes>,
-    mut player: Query<&mut AnimationPlayer>,
+    mut player: Query<(Entity, &mut AnimationPlayer)>,
     mut done: Local<bool>,
 ) {
     if !*done && player.iter().len() == foxes.count {
-        for mut player in &mut player {
+        for (entity, mut player) in &mut player {
             player.play(animations.0[0].clone_weak()).repeat();
+            if foxes.lod {
+                commands.entity(entity).insert(AnimationLod::default());
+            }
         }
         *done = true;
     }
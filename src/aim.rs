@@ -0,0 +1,222 @@
+//! Aim constraints, turning heads and eyes towards targets on top of sampled animations.
+
+use bevy::core::Name;
+use bevy::ecs::prelude::*;
+use bevy::hierarchy::{Children, Parent};
+use bevy::math::{Quat, Vec3};
+use bevy::reflect::{FromReflect, Reflect};
+use bevy::time::Time;
+use bevy::transform::components::Transform;
+
+use crate::ik::{global_transform, ChainPose, JointBindings};
+use crate::{EntityPath, JointLimit};
+
+/// A bone turned by an [`AimConstraint`].
+#[derive(Clone, Debug, Reflect, FromReflect)]
+pub struct AimBone {
+    /// Path to the bone.
+    pub path: EntityPath,
+    /// Share of the rotation taken by this bone, relative to the other bones.
+    pub weight: f32,
+    /// Limits of the bone, relative to its animated rotation.
+    pub limit: Option<JointLimit>,
+}
+
+/// Points an axis of a bone, such as a head or an eye, at a target, by turning it and the bones
+/// above it, such as the spine and the neck.
+///
+/// Paths start with the name of this entity, like the paths of an [`crate::AnimationClip`]
+/// played on it, and are resolved like those of [`crate::TwoBoneIk`]. The constraint is evaluated
+/// by [`aim_constraints`] after the animation is applied.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct AimConstraint {
+    /// Bones from the top of the spine to the aiming bone, each below the previous one. The last
+    /// bone is the one pointed at the target.
+    pub bones: Vec<AimBone>,
+    /// Axis of the last bone pointed at the target, in its local space.
+    pub axis: Vec3,
+    /// Entity whose position is aimed at. Nothing is aimed at while it has no transform.
+    pub target: Entity,
+    /// Is the constraint blending on, or off?
+    pub active: bool,
+    /// Seconds taken to blend the constraint fully on or off.
+    pub blend_time: f32,
+    #[reflect(ignore)]
+    weight: f32,
+    #[reflect(ignore)]
+    bindings: JointBindings,
+}
+
+impl Default for AimConstraint {
+    fn default() -> Self {
+        AimConstraint {
+            bones: Vec::new(),
+            axis: Vec3::Z,
+            target: Entity::PLACEHOLDER,
+            active: true,
+            blend_time: 0.3,
+            weight: 0.0,
+            bindings: JointBindings::default(),
+        }
+    }
+}
+
+impl AimConstraint {
+    /// A constraint aiming the `axis` of the last of `bones` at `target`, with its rotation spread
+    /// evenly over the bones.
+    pub fn new(bones: impl IntoIterator<Item = EntityPath>, axis: Vec3, target: Entity) -> Self {
+        AimConstraint {
+            bones: bones.into_iter().map(|path| AimBone { path, weight: 1.0, limit: None }).collect(),
+            axis,
+            target,
+            ..Default::default()
+        }
+    }
+
+    /// How much of the constraint is applied, from 0 (the animated pose) to 1.
+    pub fn weight(&self) -> f32 {
+        // Ease in and out of the blend
+        self.weight * self.weight * (3.0 - 2.0 * self.weight)
+    }
+
+    /// Resolves the bones again the next time the constraint is evaluated, such as after their
+    /// paths changed.
+    pub fn rebind(&mut self) {
+        self.bindings.reset();
+    }
+
+    /// Blends the constraint on or off by `delta` seconds.
+    fn advance(&mut self, delta: f32) {
+        let goal: f32 = if self.active { 1.0 } else { 0.0 };
+        self.weight = if self.blend_time > 0.0 {
+            let step = delta / self.blend_time;
+            goal.clamp(self.weight - step, self.weight + step)
+        } else {
+            goal
+        };
+    }
+}
+
+/// System that blends [`AimConstraint`]s on and off and turns their bones towards their targets.
+///
+/// It runs after the IK solvers and before transforms are propagated.
+pub fn aim_constraints(
+    time: Res<Time>,
+    mut constraints: Query<(Entity, &mut AimConstraint)>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+    parents: Query<&Parent>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for (root, mut constraint) in &mut constraints {
+        let constraint = constraint.bypass_change_detection();
+        constraint.advance(time.delta_seconds());
+        let weight = constraint.weight();
+        if weight <= 0.0 || constraint.bones.is_empty() || !transforms.contains(constraint.target) {
+            continue;
+        }
+        let paths = constraint.bones.iter().map(|bone| &bone.path);
+        let Some(bones) = constraint.bindings.get_or_bind(root, paths, &children, &names) else { continue };
+
+        let target = global_transform(constraint.target, &parents, &transforms).translation();
+        let limits = constraint.bones.iter().map(|bone| bone.limit).collect();
+        let mut pose = ChainPose::new(bones, limits, &parents, &transforms);
+        let weights: Vec<f32> = constraint.bones.iter().map(|bone| bone.weight.max(0.0)).collect();
+        aim(&mut pose, &weights, constraint.axis, target);
+
+        for (bone, (local, reference)) in bones.iter().zip(pose.locals.iter().zip(&pose.reference)) {
+            if let Ok(mut transform) = transforms.get_mut(*bone) {
+                transform.rotation = reference.slerp(local.rotation, weight);
+            }
+        }
+    }
+}
+
+/// Turns the bones of `pose` so that `axis` of the last bone points at `target`, each bone taking
+/// its share of the remaining rotation.
+fn aim(pose: &mut ChainPose, weights: &[f32], axis: Vec3, target: Vec3) {
+    let last = pose.locals.len() - 1;
+    for index in 0..=last {
+        let remaining: f32 = weights[index..].iter().sum();
+        if remaining <= 0.0 {
+            break;
+        }
+        let (_, parent, _) = pose.parents()[last].to_scale_rotation_translation();
+        let position = pose.positions()[last];
+        let current = parent * pose.locals[last].rotation * axis;
+        if let (Some(from), Some(to)) = (current.try_normalize(), (target - position).try_normalize()) {
+            let rotation = Quat::IDENTITY.slerp(Quat::from_rotation_arc(from, to), weights[index] / remaining);
+            pose.rotate(index, rotation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::App;
+    use bevy::hierarchy::BuildWorldChildren;
+    use bevy::transform::{components::GlobalTransform, TransformBundle, TransformPlugin};
+    use bevy::MinimalPlugins;
+
+    use super::*;
+
+    /// A spine of three bones along Y, looking along Z.
+    fn spine(limit: Option<JointLimit>) -> ChainPose {
+        ChainPose::from_locals(
+            vec![Transform::IDENTITY, Transform::from_xyz(0.0, 1.0, 0.0), Transform::from_xyz(0.0, 1.0, 0.0)],
+            vec![limit; 3],
+        )
+    }
+
+    fn head_direction(pose: &ChainPose) -> Vec3 {
+        let (_, parent, _) = pose.parents()[2].to_scale_rotation_translation();
+        parent * pose.locals[2].rotation * Vec3::Z
+    }
+
+    #[test]
+    fn aims_at_target() {
+        let target = Vec3::new(5.0, 2.0, 0.0);
+        let mut pose = spine(None);
+        aim(&mut pose, &[1.0, 1.0, 2.0], Vec3::Z, target);
+        assert!(head_direction(&pose).abs_diff_eq((target - pose.positions()[2]).normalize(), 1e-4));
+        // Every bone takes part of the rotation
+        for local in &pose.locals {
+            assert!(local.rotation.angle_between(Quat::IDENTITY) > 0.1);
+        }
+
+        let limit = JointLimit { max_swing: 0.2, ..Default::default() };
+        let mut pose = spine(Some(limit));
+        aim(&mut pose, &[1.0, 1.0, 1.0], Vec3::Z, Vec3::new(0.0, 2.0, -5.0));
+        assert!(pose.locals.iter().all(|local| limit.contains(local.rotation)));
+    }
+
+    #[test]
+    fn blends_smoothly() {
+        let mut constraint = AimConstraint { blend_time: 0.5, ..Default::default() };
+        constraint.advance(0.25);
+        assert_eq!(constraint.weight(), 0.5);
+        constraint.advance(1.0);
+        assert_eq!(constraint.weight(), 1.0);
+        constraint.active = false;
+        constraint.advance(0.1);
+        assert!(constraint.weight() > 0.8 && constraint.weight() < 1.0);
+        constraint.advance(0.5);
+        assert_eq!(constraint.weight(), 0.0);
+    }
+
+    #[test]
+    fn aims_at_target_entity() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugin(TransformPlugin).add_system(aim_constraints);
+        let target = app.world.spawn(TransformBundle::from(Transform::from_xyz(5.0, 1.0, 0.0))).id();
+        let head = app.world.spawn((TransformBundle::from(Transform::from_xyz(0.0, 1.0, 0.0)), Name::new("head"))).id();
+        let path = EntityPath { parts: vec![Name::new("root"), Name::new("head")] };
+        let constraint = AimConstraint { blend_time: 0.0, ..AimConstraint::new([path], Vec3::Z, target) };
+        app.world.spawn((TransformBundle::default(), Name::new("root"), constraint)).push_children(&[head]);
+        app.update();
+
+        let forward = app.world.get::<GlobalTransform>(head).unwrap().affine().transform_vector3(Vec3::Z);
+        assert!(forward.abs_diff_eq(Vec3::X, 1e-4), "{forward}");
+    }
+}
@@ -1,20 +1,29 @@
 //! Animation for the game engine Bevy
 #![warn(missing_docs)]
 
+use std::any::TypeId;
 use std::ops::Deref;
+use std::sync::Arc;
 use std::time::Duration;
 
-use bevy::asset::{Assets, Handle};
+use bevy::app::AppTypeRegistry;
+use bevy::asset::{AssetEvent, Assets, Handle, HandleId, HandleUntyped, ReflectAsset};
 use bevy::ecs::prelude::*;
+use bevy::ecs::system::SystemParam;
 use bevy::core::Name;
-use bevy::hierarchy::{Children, Parent};
+use bevy::hierarchy::{Children, HierarchyQueryExt, Parent};
 use bevy::math::{Quat, Vec3};
-use bevy::reflect::{FromReflect, Reflect, TypeUuid};
+use bevy::reflect::{FromReflect, GetPath, Reflect, TypeRegistryInternal, TypeUuid};
 use bevy::time::Time;
-use bevy::transform::{prelude::Transform};
-use bevy::utils::{tracing::warn, HashMap};
+use bevy::render::{camera::Camera, mesh::Mesh, view::ComputedVisibility};
+use bevy::transform::{prelude::{GlobalTransform, Transform}};
+use bevy::utils::{tracing::warn, HashMap, HashSet};
+use serde::{Deserialize, Serialize};
 
-use crate::MotionWarpClip;
+use crate::{
+    lod::Throttle, AnimationLod, AnimationPoseCache, CompressedRotations, JointPose, MotionWarpClip, PropertyCurve,
+    PropertyValue, QuantizedVec3s,
+};
 
 #[allow(missing_docs)]
 pub mod prelude {
@@ -34,6 +43,150 @@ pub enum Keyframes {
     Translation(Vec<Vec3>),
     /// Keyframes for scale.
     Scale(Vec<Vec3>),
+    /// Quantized keyframes for rotation, see [`AnimationClip::compress`].
+    CompressedRotation(CompressedRotations),
+    /// Quantized keyframes for translation, see [`AnimationClip::compress`].
+    CompressedTranslation(QuantizedVec3s),
+    /// Quantized keyframes for scale, see [`AnimationClip::compress`].
+    CompressedScale(QuantizedVec3s),
+}
+
+impl Keyframes {
+    /// Number of keyframes.
+    pub fn len(&self) -> usize {
+        match self {
+            Keyframes::Rotation(keyframes) => keyframes.len(),
+            Keyframes::Translation(keyframes) | Keyframes::Scale(keyframes) => keyframes.len(),
+            Keyframes::CompressedRotation(keyframes) => keyframes.len(),
+            Keyframes::CompressedTranslation(keyframes) | Keyframes::CompressedScale(keyframes) => keyframes.len(),
+        }
+    }
+
+    /// Are there no keyframes?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Keyframe `index` of rotation keyframes.
+    ///
+    /// Returns `None` if these aren't rotation keyframes.
+    #[inline]
+    pub fn rotation(&self, index: usize) -> Option<Quat> {
+        match self {
+            Keyframes::Rotation(keyframes) => Some(keyframes[index]),
+            Keyframes::CompressedRotation(keyframes) => Some(keyframes.get(index)),
+            _ => None,
+        }
+    }
+
+    /// Keyframe `index` of translation keyframes.
+    ///
+    /// Returns `None` if these aren't translation keyframes.
+    #[inline]
+    pub fn translation(&self, index: usize) -> Option<Vec3> {
+        match self {
+            Keyframes::Translation(keyframes) => Some(keyframes[index]),
+            Keyframes::CompressedTranslation(keyframes) => Some(keyframes.get(index)),
+            _ => None,
+        }
+    }
+
+    /// Keyframe `index` of scale keyframes.
+    ///
+    /// Returns `None` if these aren't scale keyframes.
+    #[inline]
+    pub fn scale(&self, index: usize) -> Option<Vec3> {
+        match self {
+            Keyframes::Scale(keyframes) => Some(keyframes[index]),
+            Keyframes::CompressedScale(keyframes) => Some(keyframes.get(index)),
+            _ => None,
+        }
+    }
+
+    /// These keyframes, with quantized keyframes decoded.
+    pub fn decompressed(&self) -> Keyframes {
+        match self {
+            Keyframes::CompressedRotation(keyframes) => Keyframes::Rotation(keyframes.iter().collect()),
+            Keyframes::CompressedTranslation(keyframes) => Keyframes::Translation(keyframes.iter().collect()),
+            Keyframes::CompressedScale(keyframes) => Keyframes::Scale(keyframes.iter().collect()),
+            keyframes => keyframes.clone(),
+        }
+    }
+
+    /// Sets the property animated by these keyframes on `joint` to keyframe `index`.
+    #[inline]
+    fn sample_keyframe(&self, index: usize, joint: &mut JointPose) {
+        match self {
+            Keyframes::Rotation(_) | Keyframes::CompressedRotation(_) => joint.rotation = self.rotation(index),
+            Keyframes::Translation(_) | Keyframes::CompressedTranslation(_) => {
+                joint.translation = self.translation(index);
+            }
+            Keyframes::Scale(_) | Keyframes::CompressedScale(_) => joint.scale = self.scale(index),
+        }
+    }
+
+    /// Sets the property animated by these keyframes on `joint`, interpolated by `lerp` from
+    /// keyframe `index` to the next one.
+    #[inline]
+    fn sample_step(&self, index: usize, lerp: f32, joint: &mut JointPose) {
+        match self {
+            Keyframes::Rotation(_) | Keyframes::CompressedRotation(_) => {
+                let (start, end) = (self.rotation(index).unwrap(), self.rotation(index + 1).unwrap());
+                joint.rotation = Some(slerp_keyframes(start, end, lerp));
+            }
+            Keyframes::Translation(_) | Keyframes::CompressedTranslation(_) => {
+                let (start, end) = (self.translation(index).unwrap(), self.translation(index + 1).unwrap());
+                joint.translation = Some(start.lerp(end, lerp));
+            }
+            Keyframes::Scale(_) | Keyframes::CompressedScale(_) => {
+                let (start, end) = (self.scale(index).unwrap(), self.scale(index + 1).unwrap());
+                joint.scale = Some(start.lerp(end, lerp));
+            }
+        }
+    }
+}
+
+/// Interpolates between rotation keyframes the way [`animation_player`] does.
+#[inline]
+pub(crate) fn slerp_keyframes(start: Quat, mut end: Quat, lerp: f32) -> Quat {
+    // Choose the smallest angle for the rotation
+    if end.dot(start) < 0.0 {
+        end = -end;
+    }
+    // Rotations are using a spherical linear interpolation
+    start.normalize().slerp(end.normalize(), lerp)
+}
+
+/// Index of the keyframe starting the step of `timestamps` that contains `time`, searching from
+/// `cursor`.
+///
+/// Returns `None` if the curve isn't started yet or is finished at `time`, or if `time` is NaN.
+/// `cursor` is set to the step found.
+pub(crate) fn keyframe_step(timestamps: &[f32], time: f32, cursor: &mut usize) -> Option<usize> {
+    /// Steps walked from the cursor before falling back to a binary search
+    const MAX_WALK: usize = 4;
+
+    let last = timestamps.len().checked_sub(1)?;
+    if time.is_nan() || time < timestamps[0] || time >= timestamps[last] {
+        return None;
+    }
+
+    let mut step = (*cursor).min(last - 1);
+    let mut walked = 0;
+    while timestamps[step] > time && walked < MAX_WALK {
+        step -= 1;
+        walked += 1;
+    }
+    while timestamps[step + 1] <= time && walked < MAX_WALK {
+        step += 1;
+        walked += 1;
+    }
+    if timestamps[step] > time || time >= timestamps[step + 1] {
+        // A seek, or a jump over many keyframes
+        step = timestamps.partition_point(|probe| *probe <= time).saturating_sub(1).min(last - 1);
+    }
+    *cursor = step;
+    Some(step)
 }
 
 /// Describes how an attribute of a [`Transform`] should be animated.
@@ -47,20 +200,85 @@ pub struct VariableCurve {
     pub keyframes: Keyframes,
 }
 
+impl VariableCurve {
+    /// The rotation of a rotation curve at a time, clamped to the first and last keyframes.
+    ///
+    /// Returns `None` if this isn't a rotation curve.
+    pub fn rotation_at(&self, time: f32) -> Option<Quat> {
+        let mut joint = JointPose::default();
+        self.sample_clamped(time, &mut joint);
+        joint.rotation
+    }
+
+    /// The translation of a translation curve at a time, clamped to the first and last keyframes.
+    ///
+    /// Returns `None` if this isn't a translation curve.
+    pub fn translation_at(&self, time: f32) -> Option<Vec3> {
+        let mut joint = JointPose::default();
+        self.sample_clamped(time, &mut joint);
+        joint.translation
+    }
+
+    /// The scale of a scale curve at a time, clamped to the first and last keyframes.
+    ///
+    /// Returns `None` if this isn't a scale curve.
+    pub fn scale_at(&self, time: f32) -> Option<Vec3> {
+        let mut joint = JointPose::default();
+        self.sample_clamped(time, &mut joint);
+        joint.scale
+    }
+
+    /// Sets the property animated by this curve on `joint` at an elapsed time, starting the
+    /// keyframe search from `cursor`.
+    ///
+    /// Like in [`animation_player`], curves that aren't started yet or are finished leave their
+    /// property unset, unless they have a single keyframe.
+    fn sample(&self, elapsed: f32, cursor: &mut usize, joint: &mut JointPose) {
+        // Some curves have only one keyframe used to set a transform
+        if self.keyframe_timestamps.len() == 1 {
+            self.keyframes.sample_keyframe(0, joint);
+            return;
+        }
+
+        // Find the current keyframe
+        let Some(step_start) = keyframe_step(&self.keyframe_timestamps, elapsed, cursor) else { return };
+        let ts_start = self.keyframe_timestamps[step_start];
+        let ts_end = self.keyframe_timestamps[step_start + 1];
+        let lerp = (elapsed - ts_start) / (ts_end - ts_start);
+
+        self.keyframes.sample_step(step_start, lerp, joint);
+    }
+
+    /// Like [`VariableCurve::sample`], with the time clamped to the first and last keyframes.
+    fn sample_clamped(&self, time: f32, joint: &mut JointPose) {
+        let Some(last) = self.keyframe_timestamps.len().checked_sub(1) else { return };
+        let time = time.clamp(self.keyframe_timestamps[0], self.keyframe_timestamps[last]);
+        if time >= self.keyframe_timestamps[last] {
+            self.keyframes.sample_keyframe(last, joint);
+        } else {
+            self.sample(time, &mut 0, joint);
+        }
+    }
+}
+
 /// Path to an entity, with [`Name`]s. Each entity in a path must have a name.
-#[derive(Reflect, FromReflect, Clone, Debug, Hash, PartialEq, Eq, Default)]
+#[derive(Reflect, FromReflect, Clone, Debug, Hash, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct EntityPath {
     /// Parts of the path
     pub parts: Vec<Name>,
 }
 
 /// A list of [`VariableCurve`], and the [`EntityPath`] to which they apply.
+///
+/// Fields of other components are animated by [`PropertyCurve`]s, with their own paths.
 #[derive(Reflect, FromReflect, Clone, TypeUuid, Debug, Default)]
 #[uuid = "d81b7179-0448-4eb0-89fe-c067222725bf"]
 pub struct AnimationClip {
-    curves: Vec<Vec<VariableCurve>>,
-    paths: HashMap<EntityPath, usize>,
-    duration: f32,
+    pub(crate) curves: Vec<Vec<VariableCurve>>,
+    pub(crate) paths: HashMap<EntityPath, usize>,
+    pub(crate) property_curves: Vec<Vec<PropertyCurve>>,
+    pub(crate) property_paths: HashMap<EntityPath, usize>,
+    pub(crate) duration: f32,
 }
 
 impl AnimationClip {
@@ -86,6 +304,12 @@ impl AnimationClip {
         self.paths.get(path).and_then(|id| self.curves.get(*id))
     }
 
+    /// The [`EntityPath`] of each bone, mapped to its bone ID.
+    #[inline]
+    pub fn paths(&self) -> &HashMap<EntityPath, usize> {
+        &self.paths
+    }
+
     /// Duration of the clip, represented in seconds
     #[inline]
     pub fn duration(&self) -> f32 {
@@ -109,48 +333,53 @@ impl AnimationClip {
 
     /// The rotation at an entity path at an elapsed time, assuming no transitions are occuring.
     /// 
-    /// Calculates rotation in the same way as [apply_animation].
+    /// Calculates rotation in the same way as [animation_player].
     pub fn get_joint_rotation_at(&self, path: &EntityPath, elapsed: f32) -> Quat {
         let Some(bone_id) = self.paths.get(path) else {
             warn!("Couldn't find bone id for {:?}. Returning identity quat.", path);
             return Quat::IDENTITY;
         };
-        let curves = self.get_curves(*bone_id).unwrap();
+        self.sample_joint(*bone_id, elapsed).unwrap().rotation.unwrap_or(Quat::IDENTITY)
+    }
 
-        let mut rot = Quat::IDENTITY;
+    /// Samples the curves of a bone at an elapsed time.
+    ///
+    /// Curves that aren't started yet or are finished leave their property unset.
+    /// Returns `None` if the bone is invalid.
+    pub fn sample_joint(&self, bone_id: usize, elapsed: f32) -> Option<JointPose> {
+        self.sample_joint_with_cursors(bone_id, elapsed, &mut Vec::new())
+    }
 
-        for curve in curves {
-            if curve.keyframe_timestamps.len() == 1 {
-                if let Keyframes::Rotation(keyframes) = &curve.keyframes {
-                    rot = keyframes[0];
-                } 
-                continue; 
-            }
+    /// Like [`AnimationClip::sample_joint`], starting the keyframe search of each curve of the
+    /// bone from `cursors`, which are updated to the keyframes found.
+    ///
+    /// Sampling at steadily increasing or decreasing times is amortised O(1) per curve.
+    pub fn sample_joint_with_cursors(
+        &self,
+        bone_id: usize,
+        elapsed: f32,
+        cursors: &mut Vec<usize>,
+    ) -> Option<JointPose> {
+        let curves = self.get_curves(bone_id)?;
+        cursors.resize(curves.len(), 0);
 
-            let step_start = match curve
-                .keyframe_timestamps
-                .binary_search_by(|probe| probe.partial_cmp(&elapsed).unwrap())
-            {
-                Ok(n) if n >= curve.keyframe_timestamps.len() - 1 => continue, // this curve is finished
-                Ok(i) => i,
-                Err(0) => continue, // this curve isn't started yet
-                Err(n) if n > curve.keyframe_timestamps.len() - 1 => continue, // this curve is finished
-                Err(i) => i - 1,
-            };
-            let ts_start = curve.keyframe_timestamps[step_start];
-            let ts_end = curve.keyframe_timestamps[step_start + 1];
-            let lerp = (elapsed - ts_start) / (ts_end - ts_start);
-
-            if let Keyframes::Rotation(keyframes) = &curve.keyframes {
-                let rot_start = keyframes[step_start];
-                let mut rot_end = keyframes[step_start + 1];
-                if rot_end.dot(rot_start) < 0.0 {
-                    rot_end = -rot_end;
-                }
-                rot = rot_start.normalize().slerp(rot_end.normalize(), lerp);
-            }
+        let mut joint = JointPose::default();
+        for (curve, cursor) in curves.iter().zip(cursors) {
+            curve.sample(elapsed, cursor, &mut joint);
+        }
+        Some(joint)
+    }
+
+    /// Samples the curves of a bone at a time, clamped to the first and last keyframes of each
+    /// curve, for tools that sample a whole clip, including its last keyframes.
+    ///
+    /// Returns `None` if the bone is invalid.
+    pub fn sample_joint_clamped(&self, bone_id: usize, time: f32) -> Option<JointPose> {
+        let mut joint = JointPose::default();
+        for curve in self.get_curves(bone_id)? {
+            curve.sample_clamped(time, &mut joint);
         }
-        rot
+        Some(joint)
     }
 }
 
@@ -160,7 +389,19 @@ struct PlayingAnimation {
     speed: f32,
     elapsed: f32,
     animation_clip: Handle<AnimationClip>,
-    path_cache: Vec<Vec<Option<Entity>>>,
+    /// The entity targeted by each bone of the clip, indexed by bone ID.
+    /// `None` until resolved, and reset when the hierarchy or the clip changes.
+    bindings: Option<Vec<Option<Entity>>>,
+    /// The keyframe last sampled in each curve of the clip, indexed by bone ID.
+    cursors: Vec<Vec<usize>>,
+    /// The entity targeted by each property path of the clip, indexed by property ID.
+    /// Resolved and reset along with `bindings`.
+    property_bindings: Option<Vec<Option<Entity>>>,
+    /// The keyframe last sampled in each property curve of the clip, indexed by property ID.
+    property_cursors: Vec<Vec<usize>>,
+    /// The index of the field of the player animated by each property curve of the clip, indexed
+    /// by property ID and then by curve. Resolved along with `property_bindings`.
+    property_fields: Vec<Vec<Option<usize>>>,
     #[reflect(ignore)]
     warp_clip: Option<Handle<MotionWarpClip>>
 }
@@ -172,7 +413,11 @@ impl Default for PlayingAnimation {
             speed: 1.0,
             elapsed: 0.0,
             animation_clip: Default::default(),
-            path_cache: Vec::new(),
+            bindings: None,
+            cursors: Vec::new(),
+            property_bindings: None,
+            property_cursors: Vec::new(),
+            property_fields: Vec::new(),
             warp_clip: None,
         }
     }
@@ -203,6 +448,60 @@ pub struct AnimationPlayer {
     // Once a transition is finished, it will be automatically removed from the list
     #[reflect(ignore)]
     transitions: Vec<AnimationTransition>,
+
+    /// The joints sampled this frame, waiting to be written by [`apply_animation_poses`].
+    #[reflect(ignore)]
+    pose: Vec<SampledJoint>,
+    /// The properties sampled this frame, and the fields they're written to.
+    #[reflect(ignore)]
+    properties: SampledProperties,
+    /// Number of ancestors of the player, so nested players are applied last.
+    #[reflect(ignore)]
+    depth: usize,
+}
+
+/// A joint sampled by [`animation_player`], to be blended into its target's [`Transform`].
+struct SampledJoint {
+    target: Entity,
+    joint: JointPose,
+    weight: f32,
+}
+
+/// A property sampled by [`animation_player`], to be blended into a field of its target.
+struct SampledProperty {
+    target: Entity,
+    /// Index of the field in [`SampledProperties::fields`]
+    field: usize,
+    value: PropertyValue,
+    weight: f32,
+}
+
+/// A field of a reflected component or asset, resolved once from the [`PropertyCurve`]s animating it.
+#[derive(Clone)]
+struct PropertyField {
+    type_id: TypeId,
+    owner: FieldOwner,
+    type_name: &'static str,
+    /// Reflection path to the field in the component or asset
+    path: String,
+}
+
+/// What an animated field belongs to.
+#[derive(Clone)]
+enum FieldOwner {
+    /// A component of the target entity
+    Component(ReflectComponent),
+    /// An asset, such as a material, found through a `Handle` component of the target entity
+    Asset { handle: ReflectComponent, asset: ReflectAsset },
+}
+
+/// The properties sampled by a player, with the fields they're blended into.
+#[derive(Default)]
+struct SampledProperties {
+    /// Fields animated by the clips of the player, shared with [`apply_animation_properties`].
+    fields: Arc<Vec<PropertyField>>,
+    /// The properties sampled this frame, waiting to be written by [`apply_animation_properties`].
+    samples: Vec<SampledProperty>,
 }
 
 impl AnimationPlayer {
@@ -281,6 +580,11 @@ impl AnimationPlayer {
         self
     }
 
+    /// Is the animation repeating
+    pub fn is_repeating(&self) -> bool {
+        self.animation.repeat
+    }
+
     /// Pause the animation
     pub fn pause(&mut self) {
         self.paused = true;
@@ -341,193 +645,357 @@ impl AnimationPlayer {
     }
 }
 
-fn find_bone(
+pub(crate) fn find_bone(
     root: Entity,
     path: &EntityPath,
     children: &Query<&Children>,
     names: &Query<&Name>,
-    path_cache: &mut Vec<Option<Entity>>,
 ) -> Option<Entity> {
-    // PERF: finding the target entity can be optimised
     let mut current_entity = root;
-    path_cache.resize(path.parts.len(), None);
     // Ignore the first name, it is the root node which we already have
-    for (idx, part) in path.parts.iter().enumerate().skip(1) {
-        let mut found = false;
+    for part in path.parts.iter().skip(1) {
         let children = children.get(current_entity).ok()?;
-        if let Some(cached) = path_cache[idx] {
-            if children.contains(&cached) {
-                if let Ok(name) = names.get(cached) {
-                    if name == part {
-                        current_entity = cached;
-                        found = true;
-                    }
-                }
-            }
-        }
-        if !found {
-            for child in children.deref() {
-                if let Ok(name) = names.get(*child) {
-                    if name == part {
-                        // Found a children with the right name, continue to the next part
-                        current_entity = *child;
-                        path_cache[idx] = Some(*child);
-                        found = true;
+        let Some(child) = children
+            .deref()
+            .iter()
+            .find(|child| names.get(**child).is_ok_and(|name| name == part))
+        else {
+            warn!("Entity not found for path {:?} on part {:?}", path, part);
+            return None;
+        };
+        // Found a children with the right name, continue to the next part
+        current_entity = *child;
+    }
+    Some(current_entity)
+}
+
+/// Resolves the entity targeted by each path of `paths`, indexed by the ID it maps to.
+fn bind_bones(
+    root: Entity,
+    paths: &HashMap<EntityPath, usize>,
+    children: &Query<&Children>,
+    names: &Query<&Name>,
+) -> Vec<Option<Entity>> {
+    let mut bindings = vec![None; paths.len()];
+    for (path, bone_id) in paths {
+        bindings[*bone_id] = find_bone(root, path, children, names);
+    }
+    bindings
+}
+
+type HierarchyChanged = Or<(Changed<Children>, Changed<Parent>, Changed<Name>)>;
+
+/// The hierarchy changes of a frame, to find the roots whose paths may resolve to other entities.
+#[derive(SystemParam)]
+pub struct HierarchyChanges<'w, 's> {
+    changed: Query<'w, 's, Entity, HierarchyChanged>,
+    removed_children: RemovedComponents<'w, 's, Children>,
+    removed_names: RemovedComponents<'w, 's, Name>,
+    parents: Query<'w, 's, &'static Parent>,
+}
+
+impl HierarchyChanges<'_, '_> {
+    /// Every entity that is changed, or is an ancestor of a changed entity.
+    ///
+    /// Despawned entities have no ancestors left, but despawning them recursively changes the
+    /// [`Children`] of their parent. Entities despawned without updating their parent keep
+    /// resolving to nothing, whether their paths are resolved again or not.
+    pub(crate) fn changed_roots(&mut self) -> HashSet<Entity> {
+        let changed: Vec<Entity> = self
+            .changed
+            .iter()
+            .chain(self.removed_children.iter())
+            .chain(self.removed_names.iter())
+            .collect();
+        let mut roots = HashSet::new();
+        for entity in changed {
+            // The ancestors of entities already seen have been added too
+            if roots.insert(entity) {
+                for ancestor in self.parents.iter_ancestors(entity) {
+                    if !roots.insert(ancestor) {
                         break;
                     }
                 }
             }
         }
-        if !found {
-            warn!("Entity not found for path {:?} on part {:?}", path, part);
-            return None;
-        }
+        roots
     }
-    Some(current_entity)
 }
 
-/// Verify that there are no ancestors of a given entity that have an `AnimationPlayer`.
-fn verify_no_ancestor_player(
-    player_parent: Option<&Parent>,
-    parents: &Query<(Option<With<AnimationPlayer>>, Option<&Parent>)>,
-) -> bool {
-    let Some(mut current) = player_parent.map(Parent::get) else { return true };
-    loop {
-        let Ok((maybe_player, parent)) = parents.get(current) else { return true };
-        if maybe_player.is_some() {
-            return false;
+/// System that resets the bone bindings of [`AnimationPlayer`]s when the hierarchy or names
+/// below them change, or when a playing [`AnimationClip`] is modified.
+///
+/// Bindings are resolved again by [`animation_player`] the next time the animation is applied.
+pub fn invalidate_animation_bindings(
+    mut hierarchy_changes: HierarchyChanges,
+    mut clip_events: EventReader<AssetEvent<AnimationClip>>,
+    mut animation_players: Query<(Entity, &mut AnimationPlayer, Option<&mut AnimationLod>)>,
+) {
+    let changed_roots = hierarchy_changes.changed_roots();
+    let modified_clips: Vec<&Handle<AnimationClip>> = clip_events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => Some(handle),
+            AssetEvent::Created { .. } => None,
+        })
+        .collect();
+    if changed_roots.is_empty() && modified_clips.is_empty() {
+        return;
+    }
+
+    for (entity, mut player, lod) in &mut animation_players {
+        let hierarchy_changed = changed_roots.contains(&entity);
+        if let Some(mut lod) = lod.filter(|_| hierarchy_changed) {
+            lod.bypass_change_detection().visibility_targets = None;
         }
-        if let Some(parent) = parent {
-            current = parent.get();
-        } else {
-            return true;
+        // Don't trigger change detection, which would re-apply paused animations
+        let player = player.bypass_change_detection();
+        let animations = std::iter::once(&mut player.animation)
+            .chain(player.transitions.iter_mut().map(|transition| &mut transition.animation));
+        for animation in animations {
+            if hierarchy_changed || modified_clips.contains(&&animation.animation_clip) {
+                animation.bindings = None;
+                animation.property_bindings = None;
+            }
         }
     }
 }
 
 /// System that will play all animations, using any entity with a [`AnimationPlayer`]
 /// and a [`Handle<AnimationClip>`] as an animation root
+///
+/// Players are sampled in parallel into their pose buffer, which [`apply_animation_poses`]
+/// then writes to the animated [`Transform`]s. Poses are shared between players through the
+/// [`AnimationPoseCache`] if that resource exists.
+#[allow(clippy::too_many_arguments)]
 pub fn animation_player(
     time: Res<Time>,
     animations: Res<Assets<AnimationClip>>,
     motion_warps: Res<Assets<MotionWarpClip>>,
+    pose_cache: Option<Res<AnimationPoseCache>>,
+    type_registry: Res<AppTypeRegistry>,
     children: Query<&Children>,
     names: Query<&Name>,
-    transforms: Query<&mut Transform>,
-    parents: Query<(Option<With<AnimationPlayer>>, Option<&Parent>)>,
-    mut animation_players: Query<(Entity, Option<&Parent>, &mut AnimationPlayer)>,
+    parents: Query<&Parent>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    global_transforms: Query<&GlobalTransform>,
+    visibilities: Query<&ComputedVisibility>,
+    meshes: Query<(), With<Handle<Mesh>>>,
+    mut animation_players: Query<(Entity, &mut AnimationPlayer, Option<&mut AnimationLod>)>,
 ) {
+    let camera_positions: Vec<Vec3> = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, transform)| transform.translation())
+        .collect();
+    let type_registry = type_registry.read();
+
     animation_players
         .par_iter_mut()
-        .for_each_mut(|(root, maybe_parent, mut player)| {
+        .for_each_mut(|(root, mut player, lod)| {
+            let throttle = lod.map_or(Throttle::FULL, |mut lod| {
+                lod.bypass_change_detection().throttle(
+                    root,
+                    &camera_positions,
+                    &global_transforms,
+                    &children,
+                    &visibilities,
+                    &meshes,
+                )
+            });
             update_transitions(&mut player, &time);
             run_animation_player(
                 root,
                 player,
+                throttle,
                 &time,
                 &animations,
                 &motion_warps,
+                pose_cache.as_deref(),
+                &type_registry,
                 &names,
-                &transforms,
-                maybe_parent,
                 &parents,
                 &children,
             );
         });
 }
 
+/// System that writes the poses sampled by [`animation_player`] to [`Transform`]s.
+///
+/// Nested players are applied after their ancestors, so a player wins over its ancestors
+/// for the bones they both animate.
+pub fn apply_animation_poses(
+    animation_players: Query<&AnimationPlayer>,
+    mut transforms: Query<&mut Transform>,
+) {
+    let mut players: Vec<&AnimationPlayer> = animation_players
+        .iter()
+        .filter(|player| !player.pose.is_empty())
+        .collect();
+    players.sort_by_key(|player| player.depth);
+
+    for player in players {
+        for SampledJoint { target, joint, weight } in &player.pose {
+            let Ok(mut transform) = transforms.get_mut(*target) else { continue };
+            joint.apply(&mut transform, *weight);
+        }
+    }
+}
+
+/// System that writes the properties sampled by [`animation_player`] to the fields of reflected
+/// components, and of assets found through the `Handle` components of animated entities.
+///
+/// It runs after [`apply_animation_poses`] and before transforms are propagated, so properties
+/// follow the same timing as poses. It needs exclusive access to the world to reflect any
+/// component, but returns right away when no player sampled properties. Like poses, nested
+/// players are applied after their ancestors.
+pub fn apply_animation_properties(world: &mut World, animation_players: &mut QueryState<&mut AnimationPlayer>) {
+    let mut players: Vec<(usize, Arc<Vec<PropertyField>>, Vec<SampledProperty>)> = animation_players
+        .iter_mut(world)
+        .filter(|player| !player.properties.samples.is_empty())
+        .map(|mut player| {
+            let player = player.bypass_change_detection();
+            (player.depth, player.properties.fields.clone(), std::mem::take(&mut player.properties.samples))
+        })
+        .collect();
+    players.sort_by_key(|(depth, ..)| *depth);
+
+    for (_, fields, samples) in players {
+        for SampledProperty { target, field, value, weight } in samples {
+            let field = &fields[field];
+            match &field.owner {
+                FieldOwner::Component(component) => {
+                    let Some(mut entity) = world.get_entity_mut(target) else { continue };
+                    let Some(mut reflected) = component.reflect_mut(&mut entity) else { continue };
+                    blend_field(&mut *reflected, field, value, weight);
+                }
+                FieldOwner::Asset { handle, asset } => {
+                    let Some(entity) = world.get_entity(target) else { continue };
+                    let id = handle.reflect(entity).and_then(|handle| handle.reflect_path("id").ok());
+                    let Some(&id) = id.and_then(|id| id.downcast_ref::<HandleId>()) else { continue };
+                    let Some(reflected) = asset.get_mut(world, HandleUntyped::weak(id)) else { continue };
+                    blend_field(reflected, field, value, weight);
+                }
+            }
+        }
+    }
+}
+
+fn blend_field(reflected: &mut dyn Reflect, field: &PropertyField, value: PropertyValue, weight: f32) {
+    let PropertyField { type_name, path, .. } = field;
+    let Ok(reflected_field) = reflected.reflect_path_mut(path) else {
+        warn!("{} has no field {}", type_name, path);
+        return;
+    };
+    if !value.blend_into(reflected_field, weight) {
+        warn!("Field {} of {} can't be animated with {:?}", path, type_name, value);
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn run_animation_player(
     root: Entity,
     mut player: Mut<AnimationPlayer>,
+    throttle: Throttle,
     time: &Time,
     animations: &Assets<AnimationClip>,
     motion_warps: &Assets<MotionWarpClip>,
+    pose_cache: Option<&AnimationPoseCache>,
+    type_registry: &TypeRegistryInternal,
     names: &Query<&Name>,
-    transforms: &Query<&mut Transform>,
-    maybe_parent: Option<&Parent>,
-    parents: &Query<(Option<With<AnimationPlayer>>, Option<&Parent>)>,
+    parents: &Query<&Parent>,
     children: &Query<&Children>,
 ) {
     let paused = player.paused;
     // Continue if paused unless the `AnimationPlayer` was changed
     // This allow the animation to still be updated if the player.elapsed field was manually updated in pause
     if paused && !player.is_changed() {
+        let player = player.bypass_change_detection();
+        player.pose.clear();
+        player.properties.samples.clear();
         return;
     }
 
-    let warp = 
-        if let Some(handle) = &player.animation.warp_clip {
-            if let Some(warp_clip) = motion_warps.get(handle) {
-                Some(warp_clip)
-            }
-            else {
-                None
-            }
-        }
-        else { None };
+    let player = player.as_mut();
+    player.pose.clear();
+    player.properties.samples.clear();
+    player.depth = parents.iter_ancestors(root).count();
+
+    let warp = player
+        .animation
+        .warp_clip
+        .as_ref()
+        .and_then(|handle| Some((handle.id(), motion_warps.get(handle)?)));
 
-    // Apply the main animation
-    apply_animation(
+    // Sample the main animation
+    sample_animation(
         1.0,
         &mut player.animation,
         paused,
+        throttle,
         root,
         time,
         animations,
         warp,
+        pose_cache,
+        type_registry,
         names,
-        transforms,
-        maybe_parent,
-        parents,
         children,
+        &mut player.pose,
+        &mut player.properties,
     );
-    
-    
 
-    // Apply any potential fade-out transitions from previous animations
+    // Sample any potential fade-out transitions from previous animations
     for AnimationTransition {
         current_weight,
         animation,
         ..
     } in &mut player.transitions
     {
-        apply_animation(
+        sample_animation(
             *current_weight,
             animation,
             paused,
+            throttle,
             root,
             time,
             animations,
             None,
+            pose_cache,
+            type_registry,
             names,
-            transforms,
-            maybe_parent,
-            parents,
             children,
+            &mut player.pose,
+            &mut player.properties,
         );
     }
 }
 
 #[allow(clippy::too_many_arguments)]
-fn apply_animation(
+fn sample_animation(
     weight: f32,
     animation: &mut PlayingAnimation,
     paused: bool,
+    throttle: Throttle,
     root: Entity,
     time: &Time,
     animations: &Assets<AnimationClip>,
-    motion_warp: Option<&MotionWarpClip>,
+    motion_warp: Option<(HandleId, &MotionWarpClip)>,
+    pose_cache: Option<&AnimationPoseCache>,
+    type_registry: &TypeRegistryInternal,
     names: &Query<&Name>,
-    transforms: &Query<&mut Transform>,
-    maybe_parent: Option<&Parent>,
-    parents: &Query<(Option<With<AnimationPlayer>>, Option<&Parent>)>,
     children: &Query<&Children>,
+    pose: &mut Vec<SampledJoint>,
+    properties: &mut SampledProperties,
 ) {
     if let Some(animation_clip) = animations.get(&animation.animation_clip) {
         if !paused {
             animation.elapsed += time.delta_seconds() * animation.speed;
         }
+        // Throttled animations keep their elapsed time up to date, to resume in sync
+        if !throttle.sample {
+            return;
+        }
         let mut elapsed = animation.elapsed;
         // TODO: warp time HERE
         if animation.repeat {
@@ -536,118 +1004,106 @@ fn apply_animation(
         if elapsed < 0.0 {
             elapsed += animation_clip.duration;
         }
-        if animation.path_cache.len() != animation_clip.paths.len() {
-            animation.path_cache = vec![Vec::new(); animation_clip.paths.len()];
+        // The clip may have been replaced before its bindings were invalidated
+        if animation.bindings.as_ref().map_or(true, |bindings| bindings.len() != animation_clip.paths.len())
+            || animation
+                .property_bindings
+                .as_ref()
+                .map_or(true, |bindings| bindings.len() != animation_clip.property_paths.len())
+        {
+            animation.bindings = Some(bind_bones(root, &animation_clip.paths, children, names));
+            animation.property_bindings = Some(bind_bones(root, &animation_clip.property_paths, children, names));
+            animation.property_fields = bind_property_fields(animation_clip, type_registry, &mut properties.fields);
         }
-        if !verify_no_ancestor_player(maybe_parent, parents) {
-            warn!("Animation player on {:?} has a conflicting animation player on an ancestor. Cannot safely animate.", root);
+        sample_properties(animation, animation_clip, elapsed, weight, properties);
+        let bindings = animation.bindings.as_ref().unwrap();
+        let skipped = |path: &EntityPath| throttle.max_bone_depth.is_some_and(|depth| path.parts.len() > depth + 1);
+
+        if let Some(pose_cache) = pose_cache {
+            let clip_id = animation.animation_clip.id();
+            let shared = pose_cache.sample(clip_id, animation_clip, motion_warp, elapsed, animation.repeat);
+            for (path, bone_id) in &animation_clip.paths {
+                let Some(target) = bindings[*bone_id].filter(|_| !skipped(path)) else { continue };
+                pose.push(SampledJoint { target, joint: shared.joint(*bone_id), weight });
+            }
             return;
         }
+
+        animation.cursors.resize(animation_clip.paths.len(), Vec::new());
         for (path, bone_id) in &animation_clip.paths {
-            let cached_path = &mut animation.path_cache[*bone_id];
-            let curves = animation_clip.get_curves(*bone_id).unwrap();
-            let Some(target) = find_bone(root, path, children, names, cached_path) else { continue };
-            // SAFETY: The verify_no_ancestor_player check above ensures that two animation players cannot alias
-            // any of their descendant Transforms.
-            //
-            // The system scheduler prevents any other system from mutating Transforms at the same time,
-            // so the only way this fetch can alias is if two AnimationPlayers are targeting the same bone.
-            // This can only happen if there are two or more AnimationPlayers are ancestors to the same
-            // entities. By verifying that there is no other AnimationPlayer in the ancestors of a
-            // running AnimationPlayer before animating any entity, this fetch cannot alias.
-            //
-            // This means only the AnimationPlayers closest to the root of the hierarchy will be able
-            // to run their animation. Any players in the children or descendants will log a warning
-            // and do nothing.
-            let Ok(mut transform) = (unsafe { transforms.get_unchecked(target) }) else { continue };
-            for curve in curves {
-                // Some curves have only one keyframe used to set a transform
-                if curve.keyframe_timestamps.len() == 1 {
-                    match &curve.keyframes {
-                        Keyframes::Rotation(keyframes) => {
-                            
-                            let theta = keyframes[0];
-                            if let Some(warp_clip) = motion_warp {
-                                if warp_clip.start_time <= elapsed && elapsed <= warp_clip.end_time {
-                                    if let Some(warp_id) = warp_clip.paths.get(path) {
-                                        if let Some(warp_curve) = warp_clip.curves.get(*warp_id) {
-                                            transform.rotation = warp_clip.theta_blend(warp_curve, elapsed, theta);
-                                            continue;
-                                        };
-                                    }
-                                }
-                            }
-                            
-                            transform.rotation = transform.rotation.slerp(theta, weight);
-                            
-                        }
-                        Keyframes::Translation(keyframes) => {
-                            transform.translation =
-                                transform.translation.lerp(keyframes[0], weight);
-                        }
-                        Keyframes::Scale(keyframes) => {
-                            transform.scale = transform.scale.lerp(keyframes[0], weight);
-                        }
-                    }
-                    continue;
-                }
+            let Some(target) = bindings[*bone_id].filter(|_| !skipped(path)) else { continue };
+            let cursors = &mut animation.cursors[*bone_id];
+            let mut joint = animation_clip.sample_joint_with_cursors(*bone_id, elapsed, cursors).unwrap();
+            if let Some((_, warp_clip)) = motion_warp {
+                warp_clip.warp_joint(path, elapsed, &mut joint);
+            }
+            pose.push(SampledJoint { target, joint, weight });
+        }
+    }
+}
 
-                // Find the current keyframe
-                // PERF: finding the current keyframe can be optimised
-                let step_start = match curve
-                    .keyframe_timestamps
-                    .binary_search_by(|probe| probe.partial_cmp(&elapsed).unwrap())
-                {
-                    Ok(n) if n >= curve.keyframe_timestamps.len() - 1 => continue, // this curve is finished
-                    Ok(i) => i,
-                    Err(0) => continue, // this curve isn't started yet
-                    Err(n) if n > curve.keyframe_timestamps.len() - 1 => continue, // this curve is finished
-                    Err(i) => i - 1,
-                };
-                let ts_start = curve.keyframe_timestamps[step_start];
-                let ts_end = curve.keyframe_timestamps[step_start + 1];
-                let lerp = (elapsed - ts_start) / (ts_end - ts_start);
-
-                // Apply the keyframe
-                match &curve.keyframes {
-                    Keyframes::Rotation(keyframes) => {
-                        let rot_start = keyframes[step_start];
-                        let mut rot_end = keyframes[step_start + 1];
-                        // Choose the smallest angle for the rotation
-                        if rot_end.dot(rot_start) < 0.0 {
-                            rot_end = -rot_end;
-                        }
-                        // Rotations are using a spherical linear interpolation
-                        let theta = rot_start.normalize().slerp(rot_end.normalize(), lerp);
-
-                        if let Some(warp_clip) = motion_warp {
-                            if warp_clip.start_time <= elapsed && elapsed <= warp_clip.end_time {
-                                if let Some(warp_id) = warp_clip.paths.get(path) {
-                                    if let Some(warp_curve) = warp_clip.curves.get(*warp_id) {
-                                        transform.rotation = warp_clip.theta_blend(warp_curve, elapsed, theta);
-                                        continue;
-                                    };
-                                }
-                            }   
-                        }
-                        
-                        transform.rotation = transform.rotation.slerp(theta, weight);
-                        
-                    }
-                    Keyframes::Translation(keyframes) => {
-                        let translation_start = keyframes[step_start];
-                        let translation_end = keyframes[step_start + 1];
-                        let result = translation_start.lerp(translation_end, lerp);
-                        transform.translation = transform.translation.lerp(result, weight);
-                    }
-                    Keyframes::Scale(keyframes) => {
-                        let scale_start = keyframes[step_start];
-                        let scale_end = keyframes[step_start + 1];
-                        let result = scale_start.lerp(scale_end, lerp);
-                        transform.scale = transform.scale.lerp(result, weight);
-                    }
-                }
+/// Resolves the field animated by each property curve of `animation_clip`, indexed by property ID
+/// and then by curve, adding the fields that aren't in `fields` yet.
+///
+/// Types are found by name in `type_registry`, and must reflect `Component`, or be assets whose
+/// `Handle` reflects `Component`, like assets registered with `register_asset_reflect`.
+fn bind_property_fields(
+    animation_clip: &AnimationClip,
+    type_registry: &TypeRegistryInternal,
+    fields: &mut Arc<Vec<PropertyField>>,
+) -> Vec<Vec<Option<usize>>> {
+    let mut bind_field = |curve: &PropertyCurve| {
+        let owner = type_registry.get_with_name(&curve.component).and_then(|registration| {
+            if let Some(component) = registration.data::<ReflectComponent>() {
+                return Some((registration, FieldOwner::Component(component.clone())));
             }
+            let asset = registration.data::<ReflectAsset>()?;
+            let handle = type_registry.get_type_data::<ReflectComponent>(asset.handle_type_id())?;
+            Some((registration, FieldOwner::Asset { handle: handle.clone(), asset: asset.clone() }))
+        });
+        let Some((registration, owner)) = owner else {
+            warn!("{} isn't a reflected component or asset", curve.component);
+            return None;
+        };
+        let type_id = registration.type_id();
+        let bound = fields.iter().position(|field| field.type_id == type_id && field.path == curve.field);
+        Some(bound.unwrap_or_else(|| {
+            let fields = Arc::make_mut(fields);
+            fields.push(PropertyField {
+                type_id,
+                owner,
+                type_name: registration.type_name(),
+                path: curve.field.clone(),
+            });
+            fields.len() - 1
+        }))
+    };
+    animation_clip.property_curves.iter().map(|curves| curves.iter().map(&mut bind_field).collect()).collect()
+}
+
+/// Samples the property curves of `animation_clip` at `elapsed`, the time its joints are sampled at.
+fn sample_properties(
+    animation: &mut PlayingAnimation,
+    animation_clip: &AnimationClip,
+    elapsed: f32,
+    weight: f32,
+    properties: &mut SampledProperties,
+) {
+    if animation_clip.property_paths.is_empty() {
+        return;
+    }
+    let bindings = animation.property_bindings.as_ref().unwrap();
+    animation.property_cursors.resize(animation_clip.property_paths.len(), Vec::new());
+    for property_id in animation_clip.property_paths.values() {
+        let Some(target) = bindings[*property_id] else { continue };
+        let curves = &animation_clip.property_curves[*property_id];
+        let fields = &animation.property_fields[*property_id];
+        let cursors = &mut animation.property_cursors[*property_id];
+        cursors.resize(curves.len(), 0);
+        for ((curve, field), cursor) in curves.iter().zip(fields).zip(cursors) {
+            let Some(field) = *field else { continue };
+            let Some(value) = curve.sample_with_cursor(elapsed, cursor) else { continue };
+            properties.samples.push(SampledProperty { target, field, value, weight });
         }
     }
 }
@@ -659,3 +1115,291 @@ fn update_transitions(player: &mut AnimationPlayer, time: &Time) {
     });
 }
 
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::App;
+    use bevy::asset::{AddAsset, AssetPlugin};
+    use bevy::hierarchy::BuildWorldChildren;
+    use bevy::render::color::Color;
+    use bevy::time::TimeUpdateStrategy;
+    use bevy::transform::{TransformBundle, TransformPlugin};
+    use bevy::reflect::{FromReflect, TypeUuid};
+    use bevy::MinimalPlugins;
+
+    use super::*;
+    use crate::{AnimationPlugin, PropertyKeyframes};
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(AnimationPlugin::default());
+        app
+    }
+
+    /// A player that moves the bone at `root/bone` to `translation`.
+    fn translation_player(app: &mut App, root: &str, translation: Vec3) -> AnimationPlayer {
+        let mut clip = AnimationClip::default();
+        clip.add_curve_to_path(
+            EntityPath { parts: vec![Name::new(root.to_string()), Name::new("bone")] },
+            VariableCurve {
+                keyframe_timestamps: vec![0.0],
+                keyframes: Keyframes::Translation(vec![translation]),
+            },
+        );
+        let clip = app.world.resource_mut::<Assets<AnimationClip>>().add(clip);
+
+        let mut player = AnimationPlayer::default();
+        player.play(clip);
+        player
+    }
+
+    fn setup() -> (App, Entity) {
+        let mut app = test_app();
+        let player = translation_player(&mut app, "root", Vec3::X);
+        let bone = app.world.spawn((Transform::default(), Name::new("bone"))).id();
+        app.world
+            .spawn((Transform::default(), Name::new("root"), player))
+            .push_children(&[bone]);
+        (app, bone)
+    }
+
+    #[test]
+    fn bindings_follow_hierarchy() {
+        let (mut app, bone) = setup();
+        app.update();
+        assert_eq!(app.world.get::<Transform>(bone).unwrap().translation, Vec3::X);
+
+        // A renamed bone is no longer animated
+        *app.world.get_mut::<Name>(bone).unwrap() = Name::new("renamed");
+        app.world.get_mut::<Transform>(bone).unwrap().translation = Vec3::ZERO;
+        app.update();
+        assert_eq!(app.world.get::<Transform>(bone).unwrap().translation, Vec3::ZERO);
+
+        // A bone that now matches the path is bound again
+        let other = app.world.spawn((Transform::default(), Name::new("bone"))).id();
+        let root = app.world.get::<Parent>(bone).unwrap().get();
+        app.world.entity_mut(root).push_children(&[other]);
+        app.update();
+        assert_eq!(app.world.get::<Transform>(other).unwrap().translation, Vec3::X);
+        assert_eq!(app.world.get::<Transform>(bone).unwrap().translation, Vec3::ZERO);
+    }
+
+    #[test]
+    fn bindings_follow_own_hierarchy() {
+        let (mut app, bone) = setup();
+        let root = app.world.get::<Parent>(bone).unwrap().get();
+        app.update();
+
+        // Bound to another entity, until the hierarchy below the player changes
+        let decoy = app.world.spawn(Transform::default()).id();
+        let mut player = app.world.get_mut::<AnimationPlayer>(root).unwrap();
+        player.bypass_change_detection().animation.bindings = Some(vec![Some(decoy)]);
+        app.world.get_mut::<Transform>(bone).unwrap().translation = Vec3::ZERO;
+        let other_bone = app.world.spawn(Name::new("bone")).id();
+        app.world.spawn(Name::new("root")).push_children(&[other_bone]);
+        app.update();
+        assert_eq!(app.world.get::<Transform>(decoy).unwrap().translation, Vec3::X);
+        assert_eq!(app.world.get::<Transform>(bone).unwrap().translation, Vec3::ZERO);
+
+        app.world.entity_mut(bone).insert(Name::new("bone"));
+        app.update();
+        assert_eq!(app.world.get::<Transform>(bone).unwrap().translation, Vec3::X);
+    }
+
+    #[test]
+    fn nested_players() {
+        let (mut app, bone) = setup();
+        // A prop held by the bone, with its own rig
+        let prop_player = translation_player(&mut app, "prop", Vec3::Y);
+        let prop_bone = app.world.spawn((Transform::default(), Name::new("bone"))).id();
+        let prop = app
+            .world
+            .spawn((Transform::default(), Name::new("prop"), prop_player))
+            .push_children(&[prop_bone])
+            .id();
+        app.world.entity_mut(bone).push_children(&[prop]);
+        app.update();
+
+        assert_eq!(app.world.get::<Transform>(bone).unwrap().translation, Vec3::X);
+        assert_eq!(app.world.get::<Transform>(prop_bone).unwrap().translation, Vec3::Y);
+    }
+
+    #[test]
+    fn hidden_players_stay_in_sync() {
+        let (mut app, bone) = setup();
+        let root = app.world.get::<Parent>(bone).unwrap().get();
+        app.world
+            .entity_mut(root)
+            .insert((ComputedVisibility::default(), AnimationLod::default()));
+
+        let visible_player = translation_player(&mut app, "root", Vec3::X);
+        let visible_root = app.world.spawn(visible_player).id();
+        let startup = app.world.resource::<Time>().startup();
+        for frame in 1..=3 {
+            let instant = startup + Duration::from_secs_f32(frame as f32 / 60.0);
+            app.insert_resource(TimeUpdateStrategy::ManualInstant(instant));
+            app.update();
+        }
+
+        // Not visible, so not sampled
+        assert_eq!(app.world.get::<Transform>(bone).unwrap().translation, Vec3::ZERO);
+        let elapsed = app.world.get::<AnimationPlayer>(root).unwrap().elapsed();
+        assert!(elapsed > 0.0);
+        assert_eq!(elapsed, app.world.get::<AnimationPlayer>(visible_root).unwrap().elapsed());
+
+        app.world.entity_mut(root).remove::<ComputedVisibility>();
+        app.update();
+        assert_eq!(app.world.get::<Transform>(bone).unwrap().translation, Vec3::X);
+    }
+
+    #[test]
+    fn shared_poses() {
+        let (mut app, bone) = setup();
+        app.insert_resource(AnimationPoseCache::default());
+        let root = app.world.get::<Parent>(bone).unwrap().get();
+        let player = app.world.get::<AnimationPlayer>(root).unwrap();
+        let mut other_player = AnimationPlayer::default();
+        other_player.play(player.animation_clip().clone());
+        let other_bone = app.world.spawn((Transform::default(), Name::new("bone"))).id();
+        app.world
+            .spawn((Transform::default(), Name::new("root"), other_player))
+            .push_children(&[other_bone]);
+        app.update();
+
+        assert_eq!(app.world.get::<Transform>(bone).unwrap().translation, Vec3::X);
+        assert_eq!(app.world.get::<Transform>(other_bone).unwrap().translation, Vec3::X);
+        assert_eq!(app.world.resource::<AnimationPoseCache>().stats(), (2, 2));
+
+        // Modifying the clip drops its poses
+        let handle = app.world.get::<AnimationPlayer>(root).unwrap().animation_clip().clone();
+        let mut clips = app.world.resource_mut::<Assets<AnimationClip>>();
+        let clip = clips.get_mut(&handle).unwrap();
+        clip.curves[0][0].keyframes = Keyframes::Translation(vec![Vec3::Y]);
+        app.update();
+        app.update();
+        assert_eq!(app.world.get::<Transform>(bone).unwrap().translation, Vec3::Y);
+    }
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    struct Glow {
+        intensity: f32,
+        tint: Color,
+    }
+
+    #[test]
+    fn animates_properties() {
+        let mut app = test_app();
+        app.add_plugin(TransformPlugin).register_type::<Glow>();
+        let mut clip = AnimationClip::default();
+        let path = EntityPath { parts: vec![Name::new("root")] };
+        let property = |field: &str, keyframe_timestamps, keyframes| PropertyCurve {
+            component: std::any::type_name::<Glow>().to_string(),
+            field: field.to_string(),
+            keyframe_timestamps,
+            keyframes,
+        };
+        let intensity = property("intensity", vec![0.0, 2.0], PropertyKeyframes::Float(vec![0.0, 2.0]));
+        clip.add_property_curve_to_path(path.clone(), intensity);
+        clip.add_property_curve_to_path(
+            path.clone(),
+            property("tint", vec![0.0], PropertyKeyframes::Color(vec![Color::RED])),
+        );
+        let translation = PropertyCurve {
+            component: std::any::type_name::<Transform>().to_string(),
+            field: "translation".to_string(),
+            keyframe_timestamps: vec![0.0],
+            keyframes: PropertyKeyframes::Vec3(vec![Vec3::X]),
+        };
+        clip.add_property_curve_to_path(path, translation);
+        assert_eq!(clip.duration(), 2.0);
+        let clip = app.world.resource_mut::<Assets<AnimationClip>>().add(clip);
+
+        let mut player = AnimationPlayer::default();
+        player.play(clip).repeat().pause();
+        let root = app.world.spawn((Name::new("root"), Glow::default(), TransformBundle::default(), player)).id();
+        app.world.get_mut::<AnimationPlayer>(root).unwrap().set_elapsed(2.5);
+        app.update();
+
+        // Properties loop with the animation
+        let glow = app.world.get::<Glow>(root).unwrap();
+        assert_eq!(glow.intensity, 0.5);
+        assert_eq!(glow.tint, Color::RED);
+        // and are written before transforms are propagated, like poses
+        assert_eq!(app.world.get::<GlobalTransform>(root).unwrap().translation(), Vec3::X);
+    }
+
+    #[derive(Reflect, FromReflect, TypeUuid, Default)]
+    #[uuid = "5d0d5a8e-2d3a-4c6b-9a3e-6f1f3c7b2e41"]
+    struct Tint {
+        color: Color,
+    }
+
+    #[test]
+    fn animates_asset_properties() {
+        let mut app = test_app();
+        app.add_asset::<Tint>().register_asset_reflect::<Tint>();
+        let mut clip = AnimationClip::default();
+        let path = EntityPath { parts: vec![Name::new("root")] };
+        let color = PropertyCurve {
+            component: std::any::type_name::<Tint>().to_string(),
+            field: "color".to_string(),
+            keyframe_timestamps: vec![0.0, 2.0],
+            keyframes: PropertyKeyframes::Color(vec![Color::BLACK, Color::WHITE]),
+        };
+        clip.add_property_curve_to_path(path, color);
+        let clip = app.world.resource_mut::<Assets<AnimationClip>>().add(clip);
+        let tint = app.world.resource_mut::<Assets<Tint>>().add(Tint::default());
+
+        let mut player = AnimationPlayer::default();
+        player.play(clip).pause();
+        let root = app.world.spawn((Name::new("root"), tint.clone(), player)).id();
+        app.world.get_mut::<AnimationPlayer>(root).unwrap().set_elapsed(1.0);
+        app.update();
+
+        // The asset behind the handle of the entity is animated
+        let color = app.world.resource::<Assets<Tint>>().get(&tint).unwrap().color;
+        assert_eq!(color, Color::rgba_linear(0.5, 0.5, 0.5, 1.0));
+    }
+
+    fn curve(keyframe_count: usize) -> VariableCurve {
+        VariableCurve {
+            keyframe_timestamps: (0..keyframe_count).map(|i| i as f32).collect(),
+            keyframes: Keyframes::Translation(vec![Vec3::ZERO; keyframe_count]),
+        }
+    }
+
+    #[test]
+    fn keyframe_cursor() {
+        let curve = curve(20);
+        let mut cursor = 0;
+
+        // Walking forwards and backwards
+        for (time, step) in [(0.0, 0), (0.5, 0), (1.0, 1), (3.5, 3), (2.0, 2), (18.9, 18), (5.0, 5)] {
+            assert_eq!(keyframe_step(&curve.keyframe_timestamps, time, &mut cursor), Some(step), "at {time}");
+            assert_eq!(cursor, step);
+        }
+
+        // Not started, finished and NaN leave the cursor alone
+        assert_eq!(keyframe_step(&curve.keyframe_timestamps, -1.0, &mut cursor), None);
+        assert_eq!(keyframe_step(&curve.keyframe_timestamps, 19.0, &mut cursor), None);
+        assert_eq!(keyframe_step(&curve.keyframe_timestamps, f32::NAN, &mut cursor), None);
+        assert_eq!(cursor, 5);
+
+        // A stale cursor from a longer curve
+        let mut cursor = 100;
+        assert_eq!(keyframe_step(&curve.keyframe_timestamps, 0.5, &mut cursor), Some(0));
+    }
+
+    #[test]
+    fn sample_nan_time() {
+        let mut clip = AnimationClip::default();
+        let path = EntityPath { parts: vec![Name::new("root")] };
+        clip.add_curve_to_path(path.clone(), curve(3));
+
+        assert_eq!(clip.sample_joint(0, f32::NAN), Some(JointPose::default()));
+        assert_eq!(clip.get_joint_rotation_at(&path, f32::NAN), Quat::IDENTITY);
+    }
+}
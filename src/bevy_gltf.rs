@@ -1,6 +1,6 @@
 #![allow(clippy::type_complexity)]
 
-use crate::AnimationClip;
+use crate::{AnimationClip, EntityPath, PropertyCurve, PropertyKeyframes, Skeleton};
 use bevy::utils::{HashMap, HashSet};
 use bevy::app::prelude::*;
 use bevy::asset::{
@@ -32,7 +32,7 @@ use bevy::core_pipeline::prelude::Camera3dBundle;
 use bevy::ecs::{entity::Entity, prelude::FromWorld, world::World};
 use bevy::hierarchy::{BuildWorldChildren, WorldChildBuilder};
 use bevy::log::warn;
-use bevy::math::{Mat4, Vec3};
+use bevy::math::{Mat4, Quat, Vec3};
 
 #[cfg(not(target_arch = "wasm32"))]
 use bevy::tasks::IoTaskPool;
@@ -45,6 +45,7 @@ use gltf::{
     Material, Node, Primitive,
 };
 use std::{collections::VecDeque, path::Path};
+use serde::Serialize;
 use thiserror::Error;
 use anyhow::Result;
 
@@ -79,6 +80,8 @@ pub struct Gltf {
     pub default_scene: Option<Handle<Scene>>,
     pub animations: Vec<Handle<AnimationClip>>,
     pub named_animations: HashMap<String, Handle<AnimationClip>>,
+    pub skeletons: Vec<Handle<Skeleton>>,
+    pub named_skeletons: HashMap<String, Handle<Skeleton>>,
 }
 
 /// A glTF node with all of its child nodes, its [`GltfMesh`],
@@ -130,6 +133,8 @@ pub enum GltfError {
     Base64Decode(#[from] base64::DecodeError),
     #[error("unsupported buffer format")]
     BufferFormatUnsupported,
+    #[error("invalid percent-encoded uri")]
+    InvalidUri(#[from] std::str::Utf8Error),
     #[error("invalid image mime type: {0}")]
     InvalidImageMimeType(String),
     #[error("You may need to add the feature for the file format: {0}")]
@@ -182,7 +187,7 @@ async fn load_gltf<'a, 'b>(
     load_context: &'a mut LoadContext<'b>,
     supported_compressed_formats: CompressedImageFormats,
 ) -> Result<(), GltfError> {
-    let gltf = gltf::Gltf::from_slice(bytes)?;
+    let (gltf, pointers) = parse_gltf(bytes)?;
     let buffer_data = load_buffers(&gltf, load_context, load_context.path()).await?;
 
     let mut materials = vec![];
@@ -208,87 +213,15 @@ async fn load_gltf<'a, 'b>(
         }
     }
 
-    let paths = {
-        let mut paths = HashMap::<usize, (usize, Vec<Name>)>::new();
-        for scene in gltf.scenes() {
-            for node in scene.nodes() {
-                let root_index = node.index();
-                paths_recur(node, &[], &mut paths, root_index);
-            }
-        }
-        paths
-    };
+    let paths = node_paths(&gltf);
 
     let (animations, named_animations, animation_roots) = {
         let mut animations = vec![];
         let mut named_animations = HashMap::default();
         let mut animation_roots = HashSet::default();
         for animation in gltf.animations() {
-            let mut animation_clip = crate::AnimationClip::default();
-            for channel in animation.channels() {
-                match channel.sampler().interpolation() {
-                    gltf::animation::Interpolation::Linear => (),
-                    other => warn!(
-                        "Animation interpolation {:?} is not supported, will use linear",
-                        other
-                    ),
-                };
-                let node = channel.target().node();
-                let reader = channel.reader(|buffer| Some(&buffer_data[buffer.index()]));
-                let keyframe_timestamps: Vec<f32> = if let Some(inputs) = reader.read_inputs() {
-                    match inputs {
-                        gltf::accessor::Iter::Standard(times) => times.collect(),
-                        gltf::accessor::Iter::Sparse(_) => {
-                            warn!("Sparse accessor not supported for animation sampler input");
-                            continue;
-                        }
-                    }
-                } else {
-                    warn!("Animations without a sampler input are not supported");
-                    return Err(GltfError::MissingAnimationSampler(animation.index()));
-                };
-
-                let keyframes = if let Some(outputs) = reader.read_outputs() {
-                    match outputs {
-                        gltf::animation::util::ReadOutputs::Translations(tr) => {
-                            crate::Keyframes::Translation(tr.map(Vec3::from).collect())
-                        }
-                        gltf::animation::util::ReadOutputs::Rotations(rots) => {
-                            crate::Keyframes::Rotation(
-                                rots.into_f32().map(bevy::math::Quat::from_array).collect(),
-                            )
-                        }
-                        gltf::animation::util::ReadOutputs::Scales(scale) => {
-                            crate::Keyframes::Scale(scale.map(Vec3::from).collect())
-                        }
-                        gltf::animation::util::ReadOutputs::MorphTargetWeights(_) => {
-                            warn!("Morph animation property not yet supported");
-                            continue;
-                        }
-                    }
-                } else {
-                    warn!("Animations without a sampler output are not supported");
-                    return Err(GltfError::MissingAnimationSampler(animation.index()));
-                };
-
-                if let Some((root_index, path)) = paths.get(&node.index()) {
-                    animation_roots.insert(root_index);
-                    animation_clip.add_curve_to_path(
-                        crate::EntityPath {
-                            parts: path.clone(),
-                        },
-                        crate::VariableCurve {
-                            keyframe_timestamps,
-                            keyframes,
-                        },
-                    );
-                } else {
-                    warn!(
-                        "Animation ignored for node {}: part of its hierarchy is missing a name",
-                        node.index()
-                    );
-                }
-            }
+            let (animation_clip, _) =
+                read_animation_clip(&gltf, &animation, &buffer_data, &paths, &pointers, &mut animation_roots)?;
             let handle = load_context.set_labeled_asset(
                 &format!("Animation{}", animation.index()),
                 LoadedAsset::new(animation_clip),
@@ -531,6 +464,17 @@ async fn load_gltf<'a, 'b>(
         })
         .collect();
 
+    let mut skeletons = vec![];
+    let mut named_skeletons = HashMap::default();
+    for skin in gltf.skins() {
+        let skeleton = read_skeleton(&skin, &buffer_data, &paths);
+        let handle = load_context.set_labeled_asset(&skeleton_label(&skin), LoadedAsset::new(skeleton));
+        if let Some(name) = skin.name() {
+            named_skeletons.insert(name.to_string(), handle.clone());
+        }
+        skeletons.push(handle);
+    }
+
     let mut scenes = vec![];
     let mut named_scenes = HashMap::default();
     let mut active_camera_found = false;
@@ -612,11 +556,622 @@ async fn load_gltf<'a, 'b>(
         named_nodes,
         animations,
         named_animations,
+        skeletons,
+        named_skeletons,
     }));
 
     Ok(())
 }
 
+/// Why a glTF animation channel isn't read into an [`AnimationClip`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DroppedChannel {
+    /// The sampler input uses a sparse accessor.
+    SparseInput,
+    /// Morph target weights aren't supported.
+    MorphTargetWeights,
+    /// The target node isn't part of a scene, so it has no [`EntityPath`].
+    MissingHierarchy,
+    /// The `KHR_animation_pointer` target isn't supported, or doesn't exist.
+    UnsupportedPointer,
+    /// The sampler output isn't made of floats with as many components as the target.
+    InvalidOutput,
+}
+
+impl std::fmt::Display for DroppedChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DroppedChannel::SparseInput => write!(f, "sparse accessors aren't supported for sampler inputs"),
+            DroppedChannel::MorphTargetWeights => write!(f, "morph target weights aren't supported"),
+            DroppedChannel::MissingHierarchy => write!(f, "target node isn't part of a scene's named hierarchy"),
+            DroppedChannel::UnsupportedPointer => write!(f, "animation pointer target isn't supported"),
+            DroppedChannel::InvalidOutput => write!(f, "sampler output doesn't match the target"),
+        }
+    }
+}
+
+/// How a glTF animation channel is read.
+///
+/// `KHR_animation_pointer` channels targeting the light of several nodes are reported once per
+/// node.
+#[derive(Debug, Clone, Serialize)]
+pub struct GltfChannelReport {
+    /// Index of the target node, if the target is a node or the light of a node.
+    pub node: Option<usize>,
+    /// Path of the target entity, if it has one.
+    pub path: Option<EntityPath>,
+    /// Animated property, as named by glTF, or `pointer` for `KHR_animation_pointer` channels.
+    pub property: &'static str,
+    /// JSON pointer to the animated property of `KHR_animation_pointer` channels.
+    pub pointer: Option<String>,
+    /// Interpolation mode, as named by glTF. Only linear interpolation is supported.
+    pub interpolation: &'static str,
+    /// Number of keyframes.
+    pub keyframes: usize,
+    /// Why this channel is dropped, if it is.
+    pub dropped: Option<DroppedChannel>,
+}
+
+/// How a glTF animation is read, see [`inspect_gltf_animations`].
+#[derive(Debug, Clone, Serialize)]
+pub struct GltfAnimationReport {
+    /// Index of the animation.
+    pub index: usize,
+    /// Name of the animation.
+    pub name: Option<String>,
+    /// Duration of the resulting [`AnimationClip`], in seconds.
+    pub duration: f32,
+    /// Every channel of the animation, including dropped ones.
+    pub channels: Vec<GltfChannelReport>,
+}
+
+/// Name of the interpolation of `sampler`, warning if it isn't supported.
+fn interpolation_name(sampler: &gltf::animation::Sampler) -> &'static str {
+    match sampler.interpolation() {
+        gltf::animation::Interpolation::Linear => "LINEAR",
+        other => {
+            warn!(
+                "Animation interpolation {:?} is not supported, will use linear",
+                other
+            );
+            match other {
+                gltf::animation::Interpolation::Step => "STEP",
+                _ => "CUBICSPLINE",
+            }
+        }
+    }
+}
+
+/// Reads a glTF animation into an [`AnimationClip`], recording the root node of each animated node.
+///
+/// Channels of `pointers` for this animation are read too. Also reports how each channel was read.
+fn read_animation_clip(
+    gltf: &gltf::Document,
+    animation: &gltf::Animation,
+    buffer_data: &[Vec<u8>],
+    paths: &HashMap<usize, (usize, Vec<Name>)>,
+    pointers: &[PointerChannel],
+    animation_roots: &mut HashSet<usize>,
+) -> Result<(AnimationClip, Vec<GltfChannelReport>), GltfError> {
+    let mut animation_clip = AnimationClip::default();
+    let mut reports = Vec::new();
+    for channel in animation.channels() {
+        let interpolation = interpolation_name(&channel.sampler());
+        let node = channel.target().node();
+        let path = paths.get(&node.index());
+        let mut report = GltfChannelReport {
+            node: Some(node.index()),
+            path: path.map(|(_, parts)| EntityPath { parts: parts.clone() }),
+            property: match channel.target().property() {
+                gltf::animation::Property::Translation => "translation",
+                gltf::animation::Property::Rotation => "rotation",
+                gltf::animation::Property::Scale => "scale",
+                gltf::animation::Property::MorphTargetWeights => "weights",
+            },
+            pointer: None,
+            interpolation,
+            keyframes: channel.sampler().input().count(),
+            dropped: None,
+        };
+
+        let reader = channel.reader(|buffer| Some(&buffer_data[buffer.index()]));
+        let keyframe_timestamps: Vec<f32> = if let Some(inputs) = reader.read_inputs() {
+            match inputs {
+                gltf::accessor::Iter::Standard(times) => times.collect(),
+                gltf::accessor::Iter::Sparse(_) => {
+                    warn!("Sparse accessor not supported for animation sampler input");
+                    report.dropped = Some(DroppedChannel::SparseInput);
+                    reports.push(report);
+                    continue;
+                }
+            }
+        } else {
+            warn!("Animations without a sampler input are not supported");
+            return Err(GltfError::MissingAnimationSampler(animation.index()));
+        };
+
+        let keyframes = if let Some(outputs) = reader.read_outputs() {
+            match outputs {
+                gltf::animation::util::ReadOutputs::Translations(tr) => {
+                    crate::Keyframes::Translation(tr.map(Vec3::from).collect())
+                }
+                gltf::animation::util::ReadOutputs::Rotations(rots) => {
+                    crate::Keyframes::Rotation(
+                        rots.into_f32().map(bevy::math::Quat::from_array).collect(),
+                    )
+                }
+                gltf::animation::util::ReadOutputs::Scales(scale) => {
+                    crate::Keyframes::Scale(scale.map(Vec3::from).collect())
+                }
+                gltf::animation::util::ReadOutputs::MorphTargetWeights(_) => {
+                    warn!("Morph animation property not yet supported");
+                    report.dropped = Some(DroppedChannel::MorphTargetWeights);
+                    reports.push(report);
+                    continue;
+                }
+            }
+        } else {
+            warn!("Animations without a sampler output are not supported");
+            return Err(GltfError::MissingAnimationSampler(animation.index()));
+        };
+
+        if let Some((root_index, path)) = path {
+            animation_roots.insert(*root_index);
+            animation_clip.add_curve_to_path(
+                crate::EntityPath {
+                    parts: path.clone(),
+                },
+                crate::VariableCurve {
+                    keyframe_timestamps,
+                    keyframes,
+                },
+            );
+        } else {
+            warn!(
+                "Animation ignored for node {}: part of its hierarchy is missing a name",
+                node.index()
+            );
+            report.dropped = Some(DroppedChannel::MissingHierarchy);
+        }
+        reports.push(report);
+    }
+
+    for pointer in pointers.iter().filter(|pointer| pointer.animation == animation.index()) {
+        let sampler = animation
+            .samplers()
+            .nth(pointer.sampler)
+            .ok_or(GltfError::MissingAnimationSampler(animation.index()))?;
+        read_pointer_channel(
+            gltf,
+            &sampler,
+            &pointer.pointer,
+            buffer_data,
+            paths,
+            &mut animation_clip,
+            &mut reports,
+            animation_roots,
+        )?;
+    }
+    Ok((animation_clip, reports))
+}
+
+/// A channel of the `KHR_animation_pointer` extension, which targets a property by JSON pointer
+/// rather than a node and a path.
+struct PointerChannel {
+    animation: usize,
+    sampler: usize,
+    pointer: String,
+}
+
+/// Parses `bytes` as glTF, taking out the `KHR_animation_pointer` channels that [`gltf`] can't
+/// parse, which are returned separately.
+fn parse_gltf(bytes: &[u8]) -> Result<(gltf::Gltf, Vec<PointerChannel>), GltfError> {
+    const EXTENSION: &str = "KHR_animation_pointer";
+
+    let glb = bytes.starts_with(b"glTF").then(|| gltf::binary::Glb::from_slice(bytes)).transpose()?;
+    let json = glb.as_ref().map_or(bytes, |glb| &*glb.json);
+    if !json.windows(EXTENSION.len()).any(|window| window == EXTENSION.as_bytes()) {
+        return Ok((gltf::Gltf::from_slice(bytes)?, Vec::new()));
+    }
+
+    let mut root: serde_json::Value = serde_json::from_slice(json).map_err(gltf::Error::Deserialize)?;
+    let mut pointers = Vec::new();
+    if let Some(animations) = root.get_mut("animations").and_then(serde_json::Value::as_array_mut) {
+        for (animation, value) in animations.iter_mut().enumerate() {
+            let Some(channels) = value.get_mut("channels").and_then(serde_json::Value::as_array_mut) else {
+                continue;
+            };
+            channels.retain(|channel| {
+                let target = &channel["target"];
+                if target["path"] != "pointer" {
+                    return true;
+                }
+                let pointer = target["extensions"][EXTENSION]["pointer"].as_str();
+                match (channel["sampler"].as_u64(), pointer) {
+                    (Some(sampler), Some(pointer)) => {
+                        let sampler = sampler as usize;
+                        pointers.push(PointerChannel { animation, sampler, pointer: pointer.to_string() });
+                    }
+                    _ => warn!("Animation {} has a pointer channel without a sampler or a pointer", animation),
+                }
+                false
+            });
+        }
+    }
+    for key in ["extensionsUsed", "extensionsRequired"] {
+        if let Some(extensions) = root.get_mut(key).and_then(serde_json::Value::as_array_mut) {
+            extensions.retain(|extension| *extension != EXTENSION);
+        }
+    }
+
+    let json = serde_json::to_vec(&root).map_err(gltf::Error::Deserialize)?;
+    let gltf = match glb {
+        Some(glb) => gltf::Gltf::from_slice(&gltf::binary::Glb { json: json.into(), ..glb }.to_vec()?)?,
+        None => gltf::Gltf::from_slice(&json)?,
+    };
+    Ok((gltf, pointers))
+}
+
+/// Reads the floats of `accessor`, with the number of components of each of its elements.
+///
+/// Returns `None` if its components aren't floats, or if it has no data.
+fn read_floats(accessor: gltf::Accessor, buffer_data: &[Vec<u8>]) -> Option<(usize, Vec<f32>)> {
+    use gltf::accessor::{DataType, Dimensions, Iter};
+
+    if accessor.data_type() != DataType::F32 {
+        return None;
+    }
+    let get_buffer_data = |buffer: gltf::Buffer| Some(buffer_data[buffer.index()].as_slice());
+    match accessor.dimensions() {
+        Dimensions::Scalar => Some((1, Iter::<f32>::new(accessor, get_buffer_data)?.collect())),
+        Dimensions::Vec2 => Some((2, Iter::<[f32; 2]>::new(accessor, get_buffer_data)?.flatten().collect())),
+        Dimensions::Vec3 => Some((3, Iter::<[f32; 3]>::new(accessor, get_buffer_data)?.flatten().collect())),
+        Dimensions::Vec4 => Some((4, Iter::<[f32; 4]>::new(accessor, get_buffer_data)?.flatten().collect())),
+        _ => None,
+    }
+}
+
+/// Reads a `KHR_animation_pointer` channel into `animation_clip`, as a transform curve for
+/// node properties, or as a [`PropertyCurve`] of the light component spawned for the node of a
+/// light, or of the [`StandardMaterial`] of a material.
+#[allow(clippy::too_many_arguments)]
+fn read_pointer_channel(
+    gltf: &gltf::Document,
+    sampler: &gltf::animation::Sampler,
+    pointer: &str,
+    buffer_data: &[Vec<u8>],
+    paths: &HashMap<usize, (usize, Vec<Name>)>,
+    animation_clip: &mut AnimationClip,
+    reports: &mut Vec<GltfChannelReport>,
+    animation_roots: &mut HashSet<usize>,
+) -> Result<(), GltfError> {
+    let mut report = GltfChannelReport {
+        node: None,
+        path: None,
+        property: "pointer",
+        pointer: Some(pointer.to_string()),
+        interpolation: interpolation_name(sampler),
+        keyframes: sampler.input().count(),
+        dropped: None,
+    };
+
+    let keyframe_timestamps: Vec<f32> =
+        match gltf::accessor::Iter::<f32>::new(sampler.input(), |buffer| Some(&buffer_data[buffer.index()])) {
+            Some(gltf::accessor::Iter::Standard(times)) => times.collect(),
+            Some(gltf::accessor::Iter::Sparse(_)) => {
+                warn!("Sparse accessor not supported for animation sampler input");
+                report.dropped = Some(DroppedChannel::SparseInput);
+                reports.push(report);
+                return Ok(());
+            }
+            None => {
+                warn!("Animations without a sampler input are not supported");
+                return Err(GltfError::MissingAnimationSampler(sampler.animation().index()));
+            }
+        };
+    let Some((components, values)) = read_floats(sampler.output(), buffer_data) else {
+        warn!("Animation pointer {} has an output that isn't made of floats", pointer);
+        report.dropped = Some(DroppedChannel::InvalidOutput);
+        reports.push(report);
+        return Ok(());
+    };
+
+    if let Some((node, property)) = pointer.strip_prefix("/nodes/").and_then(|rest| rest.split_once('/')) {
+        let Some(node) = node.parse().ok().and_then(|node| gltf.nodes().nth(node)) else {
+            report.dropped = Some(DroppedChannel::UnsupportedPointer);
+            reports.push(report);
+            return Ok(());
+        };
+        report.node = Some(node.index());
+        let keyframes = match (property, components) {
+            ("translation", 3) => {
+                crate::Keyframes::Translation(values.chunks_exact(3).map(Vec3::from_slice).collect())
+            }
+            ("rotation", 4) => crate::Keyframes::Rotation(values.chunks_exact(4).map(Quat::from_slice).collect()),
+            ("scale", 3) => crate::Keyframes::Scale(values.chunks_exact(3).map(Vec3::from_slice).collect()),
+            ("translation" | "rotation" | "scale", _) => {
+                report.dropped = Some(DroppedChannel::InvalidOutput);
+                reports.push(report);
+                return Ok(());
+            }
+            ("weights", _) => {
+                warn!("Morph animation property not yet supported");
+                report.dropped = Some(DroppedChannel::MorphTargetWeights);
+                reports.push(report);
+                return Ok(());
+            }
+            _ => {
+                warn!("Animation pointer {} isn't supported", pointer);
+                report.dropped = Some(DroppedChannel::UnsupportedPointer);
+                reports.push(report);
+                return Ok(());
+            }
+        };
+        match paths.get(&node.index()) {
+            Some((root_index, path)) => {
+                animation_roots.insert(*root_index);
+                let path = EntityPath { parts: path.clone() };
+                report.path = Some(path.clone());
+                animation_clip.add_curve_to_path(path, crate::VariableCurve { keyframe_timestamps, keyframes });
+            }
+            None => {
+                warn!("Animation ignored for node {}: part of its hierarchy is missing a name", node.index());
+                report.dropped = Some(DroppedChannel::MissingHierarchy);
+            }
+        }
+        reports.push(report);
+        return Ok(());
+    }
+
+    if let Some(rest) = pointer.strip_prefix("/materials/") {
+        let material = rest
+            .split_once('/')
+            .and_then(|(material, property)| Some((gltf.materials().nth(material.parse().ok()?)?, property)));
+        let Some((material, (component, field, keyframes))) = material
+            .and_then(|(material, property)| Some((material, material_property(property, components, &values)?)))
+        else {
+            warn!("Animation pointer {} isn't supported", pointer);
+            report.dropped = Some(DroppedChannel::UnsupportedPointer);
+            reports.push(report);
+            return Ok(());
+        };
+
+        // Materials are assets shared by every primitive using them, so a single curve animates
+        // the material through one of them. Primitives are named after their mesh, so the first
+        // primitive of the mesh is the one found by the path.
+        let target = gltf.nodes().find_map(|node| {
+            let mesh = node.mesh()?;
+            let first_material = mesh.primitives().next()?.material().index();
+            let (root_index, path) = paths.get(&node.index()).filter(|_| first_material == material.index())?;
+            Some((node.index(), *root_index, path, mesh.name()?))
+        });
+        match target {
+            Some((node, root_index, path, mesh_name)) => {
+                animation_roots.insert(root_index);
+                let mut parts = path.clone();
+                parts.push(Name::new(mesh_name.to_string()));
+                let path = EntityPath { parts };
+                report.node = Some(node);
+                report.path = Some(path.clone());
+                let curve = PropertyCurve {
+                    component: component.to_string(),
+                    field: field.to_string(),
+                    keyframe_timestamps,
+                    keyframes,
+                };
+                animation_clip.add_property_curve_to_path(path, curve);
+            }
+            None => {
+                warn!(
+                    "Animation ignored for material {}: it isn't the first material of a named mesh in a scene",
+                    material.index().map_or("default".to_string(), |index| index.to_string())
+                );
+                report.dropped = Some(DroppedChannel::MissingHierarchy);
+            }
+        }
+        reports.push(report);
+        return Ok(());
+    }
+
+    let light = pointer
+        .strip_prefix("/extensions/KHR_lights_punctual/lights/")
+        .and_then(|rest| rest.split_once('/'))
+        .and_then(|(light, property)| Some((gltf.lights()?.nth(light.parse().ok()?)?, property)));
+    let Some((light, property)) = light else {
+        warn!("Animation pointer {} isn't supported", pointer);
+        report.dropped = Some(DroppedChannel::UnsupportedPointer);
+        reports.push(report);
+        return Ok(());
+    };
+    let Some((component, field, keyframes)) = light_property(&light, property, components, &values) else {
+        warn!("Animation pointer {} isn't supported", pointer);
+        report.dropped = Some(DroppedChannel::UnsupportedPointer);
+        reports.push(report);
+        return Ok(());
+    };
+
+    let nodes: Vec<Node> =
+        gltf.nodes().filter(|node| node.light().is_some_and(|other| other.index() == light.index())).collect();
+    if nodes.is_empty() {
+        report.dropped = Some(DroppedChannel::MissingHierarchy);
+        reports.push(report);
+        return Ok(());
+    }
+    for node in nodes {
+        let mut report = GltfChannelReport { node: Some(node.index()), ..report.clone() };
+        // Lights are spawned as a child of their node, named after the light
+        match (paths.get(&node.index()), light.name()) {
+            (Some((root_index, path)), Some(name)) => {
+                animation_roots.insert(*root_index);
+                let mut parts = path.clone();
+                parts.push(Name::new(name.to_string()));
+                let path = EntityPath { parts };
+                report.path = Some(path.clone());
+                let curve = PropertyCurve {
+                    component: component.to_string(),
+                    field: field.to_string(),
+                    keyframe_timestamps: keyframe_timestamps.clone(),
+                    keyframes: keyframes.clone(),
+                };
+                animation_clip.add_property_curve_to_path(path, curve);
+            }
+            _ => {
+                warn!("Animation ignored for the light of node {}: it has no named path", node.index());
+                report.dropped = Some(DroppedChannel::MissingHierarchy);
+            }
+        }
+        reports.push(report);
+    }
+    Ok(())
+}
+
+/// The component, field and keyframes animated by `property` of `light`, a JSON pointer relative
+/// to the light, in the units of the component spawned for the light by the loader.
+fn light_property(
+    light: &gltf::khr_lights_punctual::Light,
+    property: &str,
+    components: usize,
+    values: &[f32],
+) -> Option<(&'static str, &'static str, PropertyKeyframes)> {
+    use gltf::khr_lights_punctual::Kind;
+
+    let component = match light.kind() {
+        Kind::Directional => std::any::type_name::<DirectionalLight>(),
+        Kind::Point => std::any::type_name::<PointLight>(),
+        Kind::Spot { .. } => std::any::type_name::<SpotLight>(),
+    };
+    let floats = || PropertyKeyframes::Float(values.to_vec());
+    let (field, keyframes) = match (property, light.kind(), components) {
+        ("color", _, 3) => {
+            let colors = values.chunks_exact(3).map(|rgb| Color::rgb(rgb[0], rgb[1], rgb[2]));
+            ("color", PropertyKeyframes::Color(colors.collect()))
+        }
+        ("intensity", Kind::Directional, 1) => ("illuminance", floats()),
+        // Like when spawning lights, luminous intensity is converted to luminous power
+        ("intensity", _, 1) => {
+            let intensities = values.iter().map(|value| value * std::f32::consts::PI * 4.0);
+            ("intensity", PropertyKeyframes::Float(intensities.collect()))
+        }
+        ("range", Kind::Point | Kind::Spot { .. }, 1) => ("range", floats()),
+        ("spot/innerConeAngle", Kind::Spot { .. }, 1) => ("inner_angle", floats()),
+        ("spot/outerConeAngle", Kind::Spot { .. }, 1) => ("outer_angle", floats()),
+        _ => return None,
+    };
+    Some((component, field, keyframes))
+}
+
+/// The component, field and keyframes animated by `property` of a material, a JSON pointer
+/// relative to the material, in the units of the [`StandardMaterial`] loaded for it.
+fn material_property(
+    property: &str,
+    components: usize,
+    values: &[f32],
+) -> Option<(&'static str, &'static str, PropertyKeyframes)> {
+    let floats = || PropertyKeyframes::Float(values.to_vec());
+    let (field, keyframes) = match (property, components) {
+        ("pbrMetallicRoughness/baseColorFactor", 4) => {
+            let colors = values.chunks_exact(4).map(|rgba| Color::rgba_linear(rgba[0], rgba[1], rgba[2], rgba[3]));
+            ("base_color", PropertyKeyframes::Color(colors.collect()))
+        }
+        ("pbrMetallicRoughness/metallicFactor", 1) => ("metallic", floats()),
+        ("pbrMetallicRoughness/roughnessFactor", 1) => ("perceptual_roughness", floats()),
+        ("emissiveFactor", 3) => {
+            let colors = values.chunks_exact(3).map(|rgb| Color::rgb_linear(rgb[0], rgb[1], rgb[2]));
+            ("emissive", PropertyKeyframes::Color(colors.collect()))
+        }
+        _ => return None,
+    };
+    Some((std::any::type_name::<StandardMaterial>(), field, keyframes))
+}
+
+/// Reads the animations of a glTF file without an [`AssetServer`](bevy::asset::AssetServer),
+/// in the same way as [`GltfLoader`] does, along with their names.
+///
+/// Buffers that aren't embedded are read relative to `base_path`.
+pub fn read_gltf_animations(
+    bytes: &[u8],
+    base_path: &Path,
+) -> Result<Vec<(Option<String>, AnimationClip)>, GltfError> {
+    let animations = read_gltf_animations_with_reports(bytes, base_path)?;
+    Ok(animations.into_iter().map(|(clip, report)| (report.name, clip)).collect())
+}
+
+/// Reads the skeleton of each skin of a glTF file without an
+/// [`AssetServer`](bevy::asset::AssetServer), in the same way as [`GltfLoader`] does, along with
+/// their names.
+///
+/// Buffers that aren't embedded are read relative to `base_path`.
+pub fn read_gltf_skeletons(bytes: &[u8], base_path: &Path) -> Result<Vec<(Option<String>, Skeleton)>, GltfError> {
+    let (gltf, _) = parse_gltf(bytes)?;
+    let buffer_data = read_buffers(&gltf, base_path)?;
+    let paths = node_paths(&gltf);
+    Ok(gltf
+        .skins()
+        .map(|skin| (skin.name().map(str::to_string), read_skeleton(&skin, &buffer_data, &paths)))
+        .collect())
+}
+
+/// Reads the joints of `skin`, with the paths of their nodes in `paths`.
+fn read_skeleton(skin: &gltf::Skin, buffer_data: &[Vec<u8>], paths: &HashMap<usize, (usize, Vec<Name>)>) -> Skeleton {
+    let (joint_paths, rest_transforms) = skin
+        .joints()
+        .map(|node| {
+            // Joints outside of scenes only have their own name
+            let parts = paths.get(&node.index()).map_or_else(|| vec![node_name(&node)], |(_, path)| path.clone());
+            let transform = Transform::from_matrix(Mat4::from_cols_array_2d(&node.transform().matrix()));
+            (EntityPath { parts }, transform)
+        })
+        .unzip();
+    let skeleton = Skeleton::new(joint_paths, rest_transforms);
+
+    let reader = skin.reader(|buffer| Some(&buffer_data[buffer.index()]));
+    match reader.read_inverse_bind_matrices() {
+        Some(matrices) => {
+            skeleton.with_inverse_bind_matrices(matrices.map(|mat| Mat4::from_cols_array_2d(&mat)).collect())
+        }
+        // Without inverse bind matrices, glTF skins use identity matrices
+        None => {
+            let identities = vec![Mat4::IDENTITY; skeleton.len()];
+            skeleton.with_inverse_bind_matrices(identities)
+        }
+    }
+}
+
+/// Reports how [`GltfLoader`] reads each animation of a glTF file, including any dropped channels.
+///
+/// Buffers that aren't embedded are read relative to `base_path`.
+pub fn inspect_gltf_animations(
+    bytes: &[u8],
+    base_path: &Path,
+) -> Result<Vec<GltfAnimationReport>, GltfError> {
+    let animations = read_gltf_animations_with_reports(bytes, base_path)?;
+    Ok(animations.into_iter().map(|(_, report)| report).collect())
+}
+
+/// Reads each animation of a glTF file, with how it was read.
+fn read_gltf_animations_with_reports(
+    bytes: &[u8],
+    base_path: &Path,
+) -> Result<Vec<(AnimationClip, GltfAnimationReport)>, GltfError> {
+    let (gltf, pointers) = parse_gltf(bytes)?;
+    let buffer_data = read_buffers(&gltf, base_path)?;
+    let paths = node_paths(&gltf);
+    let mut animation_roots = HashSet::default();
+    gltf.animations()
+        .map(|animation| {
+            let (clip, channels) =
+                read_animation_clip(&gltf, &animation, &buffer_data, &paths, &pointers, &mut animation_roots)?;
+            let report = GltfAnimationReport {
+                index: animation.index(),
+                name: animation.name().map(str::to_string),
+                duration: clip.duration(),
+                channels,
+            };
+            Ok((clip, report))
+        })
+        .collect()
+}
+
 fn get_gltf_extras(extras: &gltf::json::Extras) -> Option<GltfExtras> {
     extras.as_ref().map(|extras| super::GltfExtras {
         value: extras.get().to_string(),
@@ -631,6 +1186,18 @@ fn node_name(node: &Node) -> Name {
     Name::new(name)
 }
 
+/// Maps each node index in the scenes of `gltf` to the index of its root node and its path of [`Name`]s.
+pub(crate) fn node_paths(gltf: &gltf::Document) -> HashMap<usize, (usize, Vec<Name>)> {
+    let mut paths = HashMap::<usize, (usize, Vec<Name>)>::new();
+    for scene in gltf.scenes() {
+        for node in scene.nodes() {
+            let root_index = node.index();
+            paths_recur(node, &[], &mut paths, root_index);
+        }
+    }
+    paths
+}
+
 fn paths_recur(
     node: Node,
     current_path: &[Name],
@@ -1019,6 +1586,10 @@ fn skin_label(skin: &gltf::Skin) -> String {
     format!("Skin{}", skin.index())
 }
 
+fn skeleton_label(skin: &gltf::Skin) -> String {
+    format!("Skeleton{}", skin.index())
+}
+
 /// Extracts the texture sampler data from the glTF texture.
 fn texture_sampler<'a>(texture: &gltf::Texture) -> SamplerDescriptor<'a> {
     let gltf_sampler = texture.sampler();
@@ -1099,40 +1670,59 @@ async fn load_buffers(
     load_context: &LoadContext<'_>,
     asset_path: &Path,
 ) -> Result<Vec<Vec<u8>>, GltfError> {
-    const VALID_MIME_TYPES: &[&str] = &["application/octet-stream", "application/gltf-buffer"];
-
     let mut buffer_data = Vec::new();
     for buffer in gltf.buffers() {
-        match buffer.source() {
-            gltf::buffer::Source::Uri(uri) => {
-                let uri = percent_encoding::percent_decode_str(uri)
-                    .decode_utf8()
-                    .unwrap();
-                let uri = uri.as_ref();
-                let buffer_bytes = match DataUri::parse(uri) {
-                    Ok(data_uri) if VALID_MIME_TYPES.contains(&data_uri.mime_type) => {
-                        data_uri.decode()?
-                    }
-                    Ok(_) => return Err(GltfError::BufferFormatUnsupported),
-                    Err(()) => {
-                        // TODO: Remove this and add dep
-                        let buffer_path = asset_path.parent().unwrap().join(uri);
-                        load_context.read_asset_bytes(buffer_path).await?
-                    }
-                };
-                buffer_data.push(buffer_bytes);
+        let buffer_bytes = match buffer_source(gltf, &buffer)? {
+            BufferSource::Data(bytes) => bytes,
+            BufferSource::File(uri) => {
+                // TODO: Remove this and add dep
+                let buffer_path = asset_path.parent().unwrap().join(uri.as_ref());
+                load_context.read_asset_bytes(buffer_path).await?
             }
-            gltf::buffer::Source::Bin => {
-                if let Some(blob) = gltf.blob.as_deref() {
-                    buffer_data.push(blob.into());
-                } else {
-                    return Err(GltfError::MissingBlob);
+        };
+        buffer_data.push(buffer_bytes);
+    }
+
+    Ok(buffer_data)
+}
+
+/// Reads the raw glTF buffer data for a glTF file from the file system.
+fn read_buffers(gltf: &gltf::Gltf, base_path: &Path) -> Result<Vec<Vec<u8>>, GltfError> {
+    gltf.buffers()
+        .map(|buffer| match buffer_source(gltf, &buffer)? {
+            BufferSource::Data(bytes) => Ok(bytes),
+            BufferSource::File(uri) => Ok(std::fs::read(base_path.join(uri.as_ref())).map_err(AssetIoError::from)?),
+        })
+        .collect()
+}
+
+/// Where the data of a glTF buffer is.
+enum BufferSource<'a> {
+    /// Data embedded in the file, as a data URI or the binary chunk of a GLB.
+    Data(Vec<u8>),
+    /// Path to a file, relative to the glTF file.
+    File(std::borrow::Cow<'a, str>),
+}
+
+fn buffer_source<'a>(gltf: &gltf::Gltf, buffer: &gltf::Buffer<'a>) -> Result<BufferSource<'a>, GltfError> {
+    const VALID_MIME_TYPES: &[&str] = &["application/octet-stream", "application/gltf-buffer"];
+
+    match buffer.source() {
+        gltf::buffer::Source::Uri(uri) => {
+            let uri = percent_encoding::percent_decode_str(uri).decode_utf8()?;
+            match DataUri::parse(&uri) {
+                Ok(data_uri) if VALID_MIME_TYPES.contains(&data_uri.mime_type) => {
+                    Ok(BufferSource::Data(data_uri.decode()?))
                 }
+                Ok(_) => Err(GltfError::BufferFormatUnsupported),
+                Err(()) => Ok(BufferSource::File(uri)),
             }
         }
+        gltf::buffer::Source::Bin => match gltf.blob.as_deref() {
+            Some(blob) => Ok(BufferSource::Data(blob.into())),
+            None => Err(GltfError::MissingBlob),
+        },
     }
-
-    Ok(buffer_data)
 }
 
 fn resolve_node_hierarchy(
@@ -1229,10 +1819,17 @@ impl<'a> DataUri<'a> {
 
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
+
+    use bevy::core::Name;
+    use bevy::math::{Mat4, Vec3};
+    use bevy::pbr::{PointLight, StandardMaterial};
+    use bevy::render::color::Color;
 
-    use super::resolve_node_hierarchy;
-    use crate::GltfNode;
+    use super::{
+        inspect_gltf_animations, read_gltf_animations, read_gltf_skeletons, resolve_node_hierarchy, DroppedChannel,
+    };
+    use crate::{EntityPath, GltfNode};
 
     impl GltfNode {
         fn empty() -> Self {
@@ -1349,4 +1946,198 @@ mod test {
         assert_eq!(result[0].0, "l2");
         assert_eq!(result[0].1.children.len(), 0);
     }
+
+    #[test]
+    fn read_animations_without_asset_server() {
+        let animations = read_gltf_animations(include_bytes!("../assets/Fox.glb"), Path::new("")).unwrap();
+
+        let names: Vec<_> = animations.iter().map(|(name, _)| name.as_deref()).collect();
+        assert_eq!(names, vec![Some("Survey"), Some("Walk"), Some("Run")]);
+        assert!(animations.iter().all(|(_, clip)| clip.duration() > 0.0));
+    }
+
+    #[test]
+    fn read_skeletons() {
+        let skeletons = read_gltf_skeletons(include_bytes!("../assets/Fox.glb"), Path::new("")).unwrap();
+        assert_eq!(skeletons.len(), 1);
+        let (_, skeleton) = &skeletons[0];
+        assert_eq!(skeleton.len(), 24);
+
+        let path =
+            |names: &[&str]| EntityPath { parts: names.iter().map(|name| Name::new(name.to_string())).collect() };
+        let hip = skeleton.index_of(&path(&["root", "_rootJoint", "b_Root_00", "b_Hip_01"])).unwrap();
+        let tail = skeleton.index_of(&path(&["root", "_rootJoint", "b_Root_00", "b_Hip_01", "b_Tail01_012"])).unwrap();
+        assert_eq!(skeleton.parents()[tail], Some(hip));
+        assert_eq!(skeleton.parents()[0], None);
+        assert_eq!(skeleton.name(hip).map(Name::as_str), Some("b_Hip_01"));
+
+        // The skin's inverse bind matrices match the rest pose
+        let rest_transforms = skeleton.model_rest_transforms();
+        for (transform, inverse_bind_matrix) in rest_transforms.iter().zip(skeleton.inverse_bind_matrices()) {
+            let identity = *inverse_bind_matrix * transform.compute_matrix();
+            assert!(identity.abs_diff_eq(Mat4::IDENTITY, 1e-3), "{identity}");
+        }
+    }
+
+    #[test]
+    fn read_animation_pointers() {
+        let floats: [f32; 10] = [0.0, 1.0, 1.0, 2.0, 0.0, 0.0, 0.0, 0.0, 3.0, 0.0];
+        let buffer: Vec<u8> = floats.iter().flat_map(|float| float.to_le_bytes()).collect();
+        let pointer = |sampler: usize, pointer: &str| {
+            serde_json::json!({
+                "sampler": sampler,
+                "target": { "path": "pointer", "extensions": { "KHR_animation_pointer": { "pointer": pointer } } },
+            })
+        };
+        let json = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "extensionsUsed": ["KHR_lights_punctual", "KHR_animation_pointer"],
+            "extensionsRequired": ["KHR_animation_pointer"],
+            "extensions": {
+                "KHR_lights_punctual": { "lights": [{ "name": "bulb", "type": "point", "intensity": 1.0 }] },
+            },
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "name": "lamp", "mesh": 0, "extensions": { "KHR_lights_punctual": { "light": 0 } } }],
+            "meshes": [{ "name": "glass", "primitives": [{ "attributes": { "POSITION": 2 }, "material": 0 }] }],
+            "materials": [{ "name": "glow" }],
+            "buffers": [{
+                "byteLength": buffer.len(),
+                "uri": format!("data:application/octet-stream;base64,{}", base64::encode(&buffer)),
+            }],
+            "bufferViews": [
+                { "buffer": 0, "byteLength": 8 },
+                { "buffer": 0, "byteOffset": 8, "byteLength": 8 },
+                { "buffer": 0, "byteOffset": 16, "byteLength": 24 },
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0.0], "max": [1.0] },
+                { "bufferView": 1, "componentType": 5126, "count": 2, "type": "SCALAR" },
+                {
+                    "bufferView": 2, "componentType": 5126, "count": 2, "type": "VEC3",
+                    "min": [0.0, 0.0, 0.0], "max": [0.0, 3.0, 0.0],
+                },
+            ],
+            "animations": [{
+                "name": "flicker",
+                "samplers": [{ "input": 0, "output": 1 }, { "input": 0, "output": 2 }],
+                "channels": [
+                    pointer(0, "/extensions/KHR_lights_punctual/lights/0/intensity"),
+                    pointer(1, "/nodes/0/translation"),
+                    pointer(0, "/materials/0/emissiveStrength"),
+                    pointer(1, "/materials/0/emissiveFactor"),
+                ],
+            }],
+        });
+        let bytes = serde_json::to_vec(&json).unwrap();
+
+        let animations = read_gltf_animations(&bytes, Path::new("")).unwrap();
+        let (name, clip) = &animations[0];
+        assert_eq!(name.as_deref(), Some("flicker"));
+        let lamp = EntityPath { parts: vec![Name::new("lamp")] };
+        let translation = &clip.get_curves_by_path(&lamp).unwrap()[0];
+        assert_eq!(translation.translation_at(1.0), Some(Vec3::new(0.0, 3.0, 0.0)));
+
+        let bulb = EntityPath { parts: vec![Name::new("lamp"), Name::new("bulb")] };
+        let intensity = &clip.get_property_curves_by_path(&bulb).unwrap()[0];
+        assert_eq!(intensity.component, std::any::type_name::<PointLight>());
+        assert_eq!(intensity.field, "intensity");
+        let Some(crate::PropertyValue::Float(value)) = intensity.value_at(1.0) else { panic!() };
+        assert!((value - 2.0 * 4.0 * std::f32::consts::PI).abs() < 1e-4);
+
+        // Materials are animated through the first primitive of the mesh using them
+        let glass = EntityPath { parts: vec![Name::new("lamp"), Name::new("glass")] };
+        let emissive = &clip.get_property_curves_by_path(&glass).unwrap()[0];
+        assert_eq!(emissive.component, std::any::type_name::<StandardMaterial>());
+        assert_eq!(emissive.field, "emissive");
+        let emissive_at_end = Some(crate::PropertyValue::Color(Color::rgb_linear(0.0, 3.0, 0.0)));
+        assert_eq!(emissive.value_at(1.0), emissive_at_end);
+
+        let reports = inspect_gltf_animations(&bytes, Path::new("")).unwrap();
+        let dropped: Vec<_> = reports[0].channels.iter().map(|channel| channel.dropped).collect();
+        assert_eq!(dropped, [None, None, Some(DroppedChannel::UnsupportedPointer), None]);
+        assert_eq!(reports[0].channels[0].path, Some(bulb));
+        assert_eq!(reports[0].channels[3].path, Some(glass));
+    }
+
+    #[test]
+    fn inspect_reports_dropped_channels() {
+        let mut buffer: Vec<u8> = [0.0_f32, 1.0, 0.0, 0.0, 0.0, 1.0, 2.0, 3.0]
+            .iter()
+            .flat_map(|float| float.to_le_bytes())
+            .collect();
+        // Sparse accessor that sets the second time to 1
+        buffer.extend(1_u32.to_le_bytes());
+        buffer.extend(1.0_f32.to_le_bytes());
+        let json = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scenes": [{ "nodes": [0] }],
+            // The second node isn't in a scene, so it has no path
+            "nodes": [{ "name": "root" }, { "name": "orphan" }],
+            "buffers": [{
+                "byteLength": buffer.len(),
+                "uri": format!("data:application/octet-stream;base64,{}", base64::encode(&buffer)),
+            }],
+            "bufferViews": [
+                { "buffer": 0, "byteLength": 8 },
+                { "buffer": 0, "byteOffset": 8, "byteLength": 24 },
+                { "buffer": 0, "byteOffset": 32, "byteLength": 4 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 4 },
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0.0], "max": [1.0] },
+                { "bufferView": 1, "componentType": 5126, "count": 2, "type": "VEC3" },
+                {
+                    "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0.0], "max": [1.0],
+                    "sparse": {
+                        "count": 1,
+                        "indices": { "bufferView": 2, "componentType": 5125 },
+                        "values": { "bufferView": 3 },
+                    },
+                },
+            ],
+            "animations": [{
+                "samplers": [{ "input": 0, "output": 1 }, { "input": 2, "output": 1 }, { "input": 0, "output": 0 }],
+                "channels": [
+                    { "sampler": 0, "target": { "node": 0, "path": "translation" } },
+                    { "sampler": 1, "target": { "node": 0, "path": "translation" } },
+                    { "sampler": 2, "target": { "node": 0, "path": "weights" } },
+                    { "sampler": 0, "target": { "node": 1, "path": "translation" } },
+                ],
+            }],
+        });
+        let bytes = serde_json::to_vec(&json).unwrap();
+
+        let reports = inspect_gltf_animations(&bytes, Path::new("")).unwrap();
+        let dropped: Vec<_> = reports[0].channels.iter().map(|channel| channel.dropped).collect();
+        assert_eq!(
+            dropped,
+            [
+                None,
+                Some(DroppedChannel::SparseInput),
+                Some(DroppedChannel::MorphTargetWeights),
+                Some(DroppedChannel::MissingHierarchy),
+            ]
+        );
+        assert_eq!(reports[0].channels[2].property, "weights");
+        assert_eq!(reports[0].channels[3].path, None);
+
+        let animations = read_gltf_animations(&bytes, Path::new("")).unwrap();
+        let curves: usize = animations[0].1.curves().iter().map(Vec::len).sum();
+        assert_eq!(curves, 1);
+    }
+
+    #[test]
+    fn inspect_reports_every_channel() {
+        let bytes = include_bytes!("../assets/Fox.glb");
+        let reports = inspect_gltf_animations(bytes, Path::new("")).unwrap();
+        let animations = read_gltf_animations(bytes, Path::new("")).unwrap();
+
+        assert_eq!(reports.len(), animations.len());
+        for (report, (_, clip)) in reports.iter().zip(&animations) {
+            let curves: usize = clip.curves().iter().map(Vec::len).sum();
+            assert_eq!(report.channels.len(), curves);
+            assert_eq!(report.duration, clip.duration());
+            assert!(report.channels.iter().all(|channel| channel.dropped.is_none()));
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,184 @@
+//! Applies motion warps to glTF files and inspects their animations without opening a window,
+//! for batch processing in asset pipelines.
+
+use std::{path::Path, process::ExitCode};
+
+use anyhow::{bail, Context, Result};
+use motion_warp::{
+    builder::MotionWarpClipBuilder, export_animation_glb, inspect_gltf_animations,
+    read_gltf_animations, AnimationClip, EntityPath, GltfExportOptions,
+};
+
+const USAGE: &str = "\
+Usage:
+  motion_warp warp <input.glb> <animation> <warp.ron> <output.glb> [options]
+  motion_warp inspect <input.glb> [--json]
+
+Arguments:
+  <animation>     Name or index of the animation to warp
+  <warp.ron>      Warp description, as saved by the editor
+
+Options:
+  --fps <fps>     Frame rate the warp is baked at [default: 30]
+  --name <name>   Name of the exported animation [default: <animation>_warped]
+  --copy-source   Write a full copy of <input.glb> with the warped animation appended
+  --json          Print the inspection report as JSON";
+
+const DEFAULT_FRAME_RATE: f32 = 30.0;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("warp") => warp(&args[1..]),
+        Some("inspect") => inspect(&args[1..]),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Positional arguments and `--flag [value]` options of a subcommand.
+struct Args<'a> {
+    positional: Vec<&'a str>,
+    options: Vec<(&'a str, Option<&'a str>)>,
+}
+
+impl<'a> Args<'a> {
+    /// Parses `args`, where `valued` lists the options that take a value.
+    fn parse(args: &'a [String], valued: &[&str]) -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg.starts_with("--") {
+                let value = if valued.contains(&arg.as_str()) {
+                    Some(args.next().with_context(|| format!("{arg} needs a value"))?.as_str())
+                } else {
+                    None
+                };
+                options.push((arg.as_str(), value));
+            } else {
+                positional.push(arg.as_str());
+            }
+        }
+        Ok(Args { positional, options })
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| *option == name)
+    }
+
+    fn value(&self, name: &str) -> Option<&'a str> {
+        self.options.iter().rev().find(|(option, _)| *option == name).and_then(|(_, value)| *value)
+    }
+}
+
+fn read_animation(input: &Path, animation: &str) -> Result<(String, AnimationClip)> {
+    let bytes = std::fs::read(input).with_context(|| format!("couldn't read {}", input.display()))?;
+    let base_path = input.parent().unwrap_or_else(|| Path::new(""));
+    let mut animations = read_gltf_animations(&bytes, base_path)
+        .with_context(|| format!("couldn't load animations from {}", input.display()))?;
+
+    let index = match animations.iter().position(|(name, _)| name.as_deref() == Some(animation)) {
+        Some(index) => index,
+        None => match animation.parse::<usize>() {
+            Ok(index) if index < animations.len() => index,
+            _ => bail!("{} has no animation named {animation:?}", input.display()),
+        },
+    };
+    let (name, clip) = animations.swap_remove(index);
+    Ok((name.unwrap_or_else(|| format!("Animation{index}")), clip))
+}
+
+fn warp(args: &[String]) -> Result<()> {
+    let args = Args::parse(args, &["--fps", "--name"])?;
+    let &[input, animation, description, output] = args.positional.as_slice() else {
+        bail!("expected 4 arguments, got {}\n\n{USAGE}", args.positional.len());
+    };
+    let frame_rate = match args.value("--fps") {
+        Some(fps) => fps.parse().with_context(|| format!("invalid frame rate {fps:?}"))?,
+        None => DEFAULT_FRAME_RATE,
+    };
+    if !(frame_rate > 0.0 && frame_rate.is_finite()) {
+        bail!("frame rate must be positive and finite, got {frame_rate}");
+    }
+
+    let input = Path::new(input);
+    let (name, clip) = read_animation(input, animation)?;
+
+    let description = std::fs::read_to_string(description)
+        .with_context(|| format!("couldn't read {description}"))?;
+    let mut builder = MotionWarpClipBuilder::from_ron(&description)
+        .with_context(|| "invalid warp description")?;
+    builder.validate(&clip).with_context(|| "invalid warp description")?;
+    let warped = builder.build(&clip).bake(&clip, frame_rate);
+
+    let options = GltfExportOptions {
+        animation_name: Some(args.value("--name").map_or_else(|| format!("{name}_warped"), str::to_string)),
+        copy_source: args.flag("--copy-source"),
+    };
+    let source = std::fs::read(input)?;
+    let bytes = export_animation_glb(&source, &warped, &options)?;
+    std::fs::write(output, bytes).with_context(|| format!("couldn't write {output}"))?;
+
+    println!("Wrote {:?} to {output}", options.animation_name.unwrap());
+    Ok(())
+}
+
+fn format_path(path: &EntityPath) -> String {
+    path.parts.iter().map(|part| part.as_str()).collect::<Vec<_>>().join("/")
+}
+
+fn inspect(args: &[String]) -> Result<()> {
+    let args = Args::parse(args, &[])?;
+    let &[input] = args.positional.as_slice() else {
+        bail!("expected 1 argument, got {}\n\n{USAGE}", args.positional.len());
+    };
+
+    let input = Path::new(input);
+    let bytes = std::fs::read(input).with_context(|| format!("couldn't read {}", input.display()))?;
+    let base_path = input.parent().unwrap_or_else(|| Path::new(""));
+    let reports = inspect_gltf_animations(&bytes, base_path)
+        .with_context(|| format!("couldn't load animations from {}", input.display()))?;
+
+    if args.flag("--json") {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+
+    for report in reports {
+        let dropped = report.channels.iter().filter(|channel| channel.dropped.is_some()).count();
+        println!(
+            "Animation {} {:?}: {:.3}s, {} channels ({} dropped)",
+            report.index,
+            report.name.as_deref().unwrap_or(""),
+            report.duration,
+            report.channels.len(),
+            dropped,
+        );
+        for channel in &report.channels {
+            let target = match (&channel.path, channel.node) {
+                (Some(path), _) => format_path(path),
+                (None, Some(node)) => format!("node {node}"),
+                (None, None) => "no node".to_string(),
+            };
+            let property = channel.pointer.as_deref().unwrap_or(channel.property);
+            match channel.dropped {
+                None => println!(
+                    "  {target}  {property}  {}  {} keyframes",
+                    channel.interpolation, channel.keyframes
+                ),
+                Some(reason) => println!("  DROPPED {target}  {property}: {reason}"),
+            }
+        }
+    }
+    Ok(())
+}
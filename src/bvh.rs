@@ -0,0 +1,494 @@
+//! Loading of BVH motion capture files.
+
+use std::str::SplitAsciiWhitespace;
+
+use bevy::app::prelude::*;
+use bevy::asset::{AddAsset, AssetLoader, BoxedFuture, Handle, LoadContext, LoadedAsset};
+use bevy::core::Name;
+use bevy::ecs::world::World;
+use bevy::hierarchy::{BuildWorldChildren, WorldChildBuilder};
+use bevy::math::{Quat, Vec3};
+use bevy::pbr::{PbrBundle, StandardMaterial};
+use bevy::reflect::TypeUuid;
+use bevy::render::{color::Color, mesh::{shape, Mesh}, prelude::SpatialBundle};
+use bevy::scene::Scene;
+use bevy::transform::components::Transform;
+use thiserror::Error;
+
+use crate::{AnimationClip, AnimationPlayer, EntityPath, Keyframes, VariableCurve};
+
+/// Adds support for BVH file loading to the app.
+#[derive(Default)]
+pub struct BvhPlugin;
+
+impl Plugin for BvhPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset_loader::<BvhLoader>().add_asset::<Bvh>();
+    }
+}
+
+/// Representation of a loaded BVH file.
+///
+/// Like glTF files, the skeleton scene is labeled `Scene0` and the motion `Animation0`.
+#[derive(Debug, TypeUuid)]
+#[uuid = "a7d4c3b6-3f0e-4d5e-9a1c-6a2f0f1d8e51"]
+pub struct Bvh {
+    pub scene: Handle<Scene>,
+    pub animation: Handle<AnimationClip>,
+    pub joints: Vec<BvhJoint>,
+}
+
+/// A channel of a BVH joint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BvhChannel {
+    XPosition,
+    YPosition,
+    ZPosition,
+    XRotation,
+    YRotation,
+    ZRotation,
+}
+
+impl BvhChannel {
+    fn parse(token: &str) -> Result<Self, BvhError> {
+        match token {
+            "Xposition" => Ok(BvhChannel::XPosition),
+            "Yposition" => Ok(BvhChannel::YPosition),
+            "Zposition" => Ok(BvhChannel::ZPosition),
+            "Xrotation" => Ok(BvhChannel::XRotation),
+            "Yrotation" => Ok(BvhChannel::YRotation),
+            "Zrotation" => Ok(BvhChannel::ZRotation),
+            other => Err(BvhError::InvalidChannel(other.to_string())),
+        }
+    }
+
+    /// The name of this channel in a BVH file.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BvhChannel::XPosition => "Xposition",
+            BvhChannel::YPosition => "Yposition",
+            BvhChannel::ZPosition => "Zposition",
+            BvhChannel::XRotation => "Xrotation",
+            BvhChannel::YRotation => "Yrotation",
+            BvhChannel::ZRotation => "Zrotation",
+        }
+    }
+
+    /// Is this a position channel?
+    pub fn is_position(&self) -> bool {
+        matches!(self, BvhChannel::XPosition | BvhChannel::YPosition | BvhChannel::ZPosition)
+    }
+}
+
+/// A joint of a BVH skeleton.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BvhJoint {
+    pub name: String,
+    /// Index of the parent joint, which always comes before this joint.
+    pub parent: Option<usize>,
+    /// Offset from the parent joint in the rest pose.
+    pub offset: Vec3,
+    pub channels: Vec<BvhChannel>,
+    /// Offset of the `End Site` of this joint, if it has one.
+    pub end_site: Option<Vec3>,
+}
+
+/// A parsed BVH file.
+#[derive(Clone, Debug)]
+pub struct BvhFile {
+    /// Joints in depth-first order, so the root comes first and parents come before their children.
+    pub joints: Vec<BvhJoint>,
+    /// Duration of a frame, in seconds.
+    pub frame_time: f32,
+    /// Channel values of each frame, in the order of `joints` and their channels.
+    pub frames: Vec<Vec<f32>>,
+}
+
+/// An error that occurs when loading a BVH file.
+#[derive(Error, Debug)]
+pub enum BvhError {
+    #[error("BVH file isn't valid utf-8")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("unexpected end of file, expected {0}")]
+    UnexpectedEnd(&'static str),
+    #[error("expected {expected}, found {found:?}")]
+    UnexpectedToken { expected: &'static str, found: String },
+    #[error("invalid number {0:?}")]
+    InvalidNumber(String),
+    #[error("invalid channel {0:?}")]
+    InvalidChannel(String),
+    #[error("BVH file has no joints")]
+    NoJoints,
+    #[error("BVH file has several roots, only one is supported")]
+    MultipleRoots,
+}
+
+struct Tokens<'a>(SplitAsciiWhitespace<'a>);
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self, expected: &'static str) -> Result<&'a str, BvhError> {
+        self.0.next().ok_or(BvhError::UnexpectedEnd(expected))
+    }
+
+    fn expect(&mut self, expected: &'static str) -> Result<(), BvhError> {
+        let found = self.next(expected)?;
+        if found == expected {
+            Ok(())
+        } else {
+            Err(BvhError::UnexpectedToken { expected, found: found.to_string() })
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, expected: &'static str) -> Result<T, BvhError> {
+        let token = self.next(expected)?;
+        token.parse().map_err(|_| BvhError::InvalidNumber(token.to_string()))
+    }
+
+    fn vec3(&mut self) -> Result<Vec3, BvhError> {
+        Ok(Vec3::new(self.number("x")?, self.number("y")?, self.number("z")?))
+    }
+}
+
+impl BvhFile {
+    /// Parses the text of a BVH file.
+    ///
+    /// Files with several `ROOT` joints are rejected with [`BvhError::MultipleRoots`].
+    pub fn parse(text: &str) -> Result<Self, BvhError> {
+        let mut tokens = Tokens(text.split_ascii_whitespace());
+        let mut joints = Vec::new();
+
+        tokens.expect("HIERARCHY")?;
+        loop {
+            match tokens.next("ROOT or MOTION")? {
+                // The skeleton is played by a single animation player on its root
+                "ROOT" if !joints.is_empty() => return Err(BvhError::MultipleRoots),
+                "ROOT" => parse_joint(&mut tokens, None, &mut joints)?,
+                "MOTION" => break,
+                found => {
+                    return Err(BvhError::UnexpectedToken {
+                        expected: "ROOT or MOTION",
+                        found: found.to_string(),
+                    })
+                }
+            }
+        }
+        if joints.is_empty() {
+            return Err(BvhError::NoJoints);
+        }
+
+        tokens.expect("Frames:")?;
+        let frame_count: usize = tokens.number("frame count")?;
+        tokens.expect("Frame")?;
+        tokens.expect("Time:")?;
+        let frame_time: f32 = tokens.number("frame time")?;
+
+        let channel_count: usize = joints.iter().map(|joint: &BvhJoint| joint.channels.len()).sum();
+        let frames = (0..frame_count)
+            .map(|_| (0..channel_count).map(|_| tokens.number("channel value")).collect())
+            .collect::<Result<_, _>>()?;
+
+        Ok(BvhFile { joints, frame_time, frames })
+    }
+
+    /// The [`EntityPath`] of each joint in the skeleton scene built by [`BvhLoader`].
+    pub fn joint_paths(&self) -> Vec<EntityPath> {
+        joint_paths(&self.joints)
+    }
+
+    /// The motion of this file, with Euler angle channels converted to quaternions.
+    pub fn animation_clip(&self) -> AnimationClip {
+        let keyframe_timestamps: Vec<f32> =
+            (0..self.frames.len()).map(|i| i as f32 * self.frame_time).collect();
+
+        let mut clip = AnimationClip::default();
+        let mut first_channel = 0;
+        for (joint, path) in self.joints.iter().zip(self.joint_paths()) {
+            let channels = first_channel..first_channel + joint.channels.len();
+            first_channel = channels.end;
+
+            let (translations, rotations): (Vec<_>, Vec<_>) = self
+                .frames
+                .iter()
+                .map(|frame| joint_transform(joint, &frame[channels.clone()]))
+                .unzip();
+
+            if joint.channels.iter().any(BvhChannel::is_position) {
+                clip.add_curve_to_path(
+                    path.clone(),
+                    VariableCurve {
+                        keyframe_timestamps: keyframe_timestamps.clone(),
+                        keyframes: Keyframes::Translation(translations),
+                    },
+                );
+            }
+            if joint.channels.iter().any(|channel| !channel.is_position()) {
+                clip.add_curve_to_path(
+                    path,
+                    VariableCurve {
+                        keyframe_timestamps: keyframe_timestamps.clone(),
+                        keyframes: Keyframes::Rotation(rotations),
+                    },
+                );
+            }
+        }
+        clip
+    }
+}
+
+/// The [`EntityPath`] of each joint, given joints whose parents come before them.
+pub(crate) fn joint_paths(joints: &[BvhJoint]) -> Vec<EntityPath> {
+    let mut paths: Vec<EntityPath> = Vec::with_capacity(joints.len());
+    for joint in joints {
+        let mut path = joint.parent.map(|parent| paths[parent].clone()).unwrap_or_default();
+        path.parts.push(Name::new(joint.name.clone()));
+        paths.push(path);
+    }
+    paths
+}
+
+fn parse_joint(
+    tokens: &mut Tokens,
+    parent: Option<usize>,
+    joints: &mut Vec<BvhJoint>,
+) -> Result<(), BvhError> {
+    let name = tokens.next("joint name")?.to_string();
+    tokens.expect("{")?;
+    tokens.expect("OFFSET")?;
+    let offset = tokens.vec3()?;
+    tokens.expect("CHANNELS")?;
+    let channel_count: usize = tokens.number("channel count")?;
+    let channels = (0..channel_count)
+        .map(|_| BvhChannel::parse(tokens.next("channel")?))
+        .collect::<Result<_, _>>()?;
+
+    let index = joints.len();
+    joints.push(BvhJoint { name, parent, offset, channels, end_site: None });
+
+    loop {
+        match tokens.next("JOINT, End or }")? {
+            "JOINT" => parse_joint(tokens, Some(index), joints)?,
+            "End" => {
+                tokens.expect("Site")?;
+                tokens.expect("{")?;
+                tokens.expect("OFFSET")?;
+                joints[index].end_site = Some(tokens.vec3()?);
+                tokens.expect("}")?;
+            }
+            "}" => return Ok(()),
+            found => {
+                return Err(BvhError::UnexpectedToken {
+                    expected: "JOINT, End or }",
+                    found: found.to_string(),
+                })
+            }
+        }
+    }
+}
+
+/// The local translation and rotation of a joint, from its channel values in a frame.
+///
+/// Position channels replace the offset, and rotation channels are applied in the order
+/// they're listed, as intrinsic rotations in degrees.
+pub(crate) fn joint_transform(joint: &BvhJoint, values: &[f32]) -> (Vec3, Quat) {
+    let mut translation = joint.offset;
+    let mut rotation = Quat::IDENTITY;
+    for (channel, value) in joint.channels.iter().zip(values) {
+        match channel {
+            BvhChannel::XPosition => translation.x = *value,
+            BvhChannel::YPosition => translation.y = *value,
+            BvhChannel::ZPosition => translation.z = *value,
+            BvhChannel::XRotation => rotation *= Quat::from_rotation_x(value.to_radians()),
+            BvhChannel::YRotation => rotation *= Quat::from_rotation_y(value.to_radians()),
+            BvhChannel::ZRotation => rotation *= Quat::from_rotation_z(value.to_radians()),
+        }
+    }
+    (translation, rotation)
+}
+
+/// Loads BVH files as a skeleton [`Scene`] with an [`AnimationPlayer`] and an [`AnimationClip`].
+#[derive(Default)]
+pub struct BvhLoader;
+
+impl AssetLoader for BvhLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move { Ok(load_bvh(bytes, load_context)?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bvh"]
+    }
+}
+
+fn load_bvh(bytes: &[u8], load_context: &mut LoadContext) -> Result<(), BvhError> {
+    let bvh = BvhFile::parse(std::str::from_utf8(bytes)?)?;
+
+    let animation =
+        load_context.set_labeled_asset("Animation0", LoadedAsset::new(bvh.animation_clip()));
+
+    // Joints are shown as spheres sized relative to the skeleton
+    let bone_lengths: Vec<f32> = bvh
+        .joints
+        .iter()
+        .filter(|joint| joint.parent.is_some())
+        .map(|joint| joint.offset.length())
+        .collect();
+    let radius = if bone_lengths.is_empty() {
+        1.0
+    } else {
+        0.1 * bone_lengths.iter().sum::<f32>() / bone_lengths.len() as f32
+    };
+    let joint_mesh = load_context.set_labeled_asset(
+        "JointMesh",
+        LoadedAsset::new(Mesh::from(shape::UVSphere { radius, ..Default::default() })),
+    );
+    let joint_material = load_context.set_labeled_asset(
+        "JointMaterial",
+        LoadedAsset::new(StandardMaterial::from(Color::rgb(0.8, 0.7, 0.6))),
+    );
+
+    let mut world = World::default();
+    world
+        .spawn(SpatialBundle::INHERITED_IDENTITY)
+        .with_children(|parent| {
+            spawn_joint(parent, &bvh, 0, &joint_mesh, &joint_material);
+        });
+    let scene = load_context.set_labeled_asset("Scene0", LoadedAsset::new(Scene::new(world)));
+
+    load_context.set_default_asset(LoadedAsset::new(Bvh {
+        scene,
+        animation,
+        joints: bvh.joints,
+    }));
+    Ok(())
+}
+
+fn spawn_joint(
+    world_builder: &mut WorldChildBuilder,
+    bvh: &BvhFile,
+    index: usize,
+    mesh: &Handle<Mesh>,
+    material: &Handle<StandardMaterial>,
+) {
+    let joint = &bvh.joints[index];
+    let mut node = world_builder.spawn((
+        SpatialBundle::from(Transform::from_translation(joint.offset)),
+        Name::new(joint.name.clone()),
+    ));
+    if joint.parent.is_none() {
+        node.insert(AnimationPlayer::default());
+    }
+    node.with_children(|parent| {
+        parent.spawn(PbrBundle {
+            mesh: mesh.clone(),
+            material: material.clone(),
+            ..Default::default()
+        });
+        for child in (index + 1..bvh.joints.len()).filter(|i| bvh.joints[*i].parent == Some(index)) {
+            spawn_joint(parent, bvh, child, mesh, material);
+        }
+    });
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use bevy::math::EulerRot;
+
+    use super::*;
+
+    pub(crate) const TEST_BVH: &str = "\
+HIERARCHY
+ROOT Hips
+{
+    OFFSET 0.0 0.0 0.0
+    CHANNELS 6 Xposition Yposition Zposition Zrotation Xrotation Yrotation
+    JOINT Spine
+    {
+        OFFSET 0.0 10.0 0.0
+        CHANNELS 3 Zrotation Xrotation Yrotation
+        End Site
+        {
+            OFFSET 0.0 5.0 0.0
+        }
+    }
+    JOINT LeftLeg
+    {
+        OFFSET 3.0 -1.0 0.0
+        CHANNELS 3 Zrotation Xrotation Yrotation
+    }
+}
+MOTION
+Frames: 2
+Frame Time: 0.5
+0.0 20.0 0.0 0.0 0.0 0.0 0.0 0.0 0.0 0.0 0.0 0.0
+1.0 21.0 2.0 90.0 0.0 0.0 0.0 45.0 0.0 10.0 20.0 30.0
+";
+
+    fn path(names: &[&str]) -> EntityPath {
+        EntityPath { parts: names.iter().map(|name| Name::new(name.to_string())).collect() }
+    }
+
+    #[test]
+    fn parse_hierarchy() {
+        let bvh = BvhFile::parse(TEST_BVH).unwrap();
+
+        assert_eq!(bvh.joints.len(), 3);
+        assert_eq!(bvh.joints[1].parent, Some(0));
+        assert_eq!(bvh.joints[2].parent, Some(0));
+        assert_eq!(bvh.joints[1].end_site, Some(Vec3::new(0.0, 5.0, 0.0)));
+        assert_eq!(bvh.joints[0].channels.len(), 6);
+        assert_eq!(bvh.frames.len(), 2);
+        assert_eq!(bvh.frame_time, 0.5);
+        assert_eq!(bvh.joint_paths()[2], path(&["Hips", "LeftLeg"]));
+    }
+
+    #[test]
+    fn clip_matches_hierarchy() {
+        let clip = BvhFile::parse(TEST_BVH).unwrap().animation_clip();
+
+        assert_eq!(clip.duration(), 0.5);
+        assert_eq!(clip.get_curves_by_path(&path(&["Hips"])).unwrap().len(), 2);
+        assert_eq!(clip.get_curves_by_path(&path(&["Hips", "Spine"])).unwrap().len(), 1);
+
+        let Keyframes::Translation(translations) = &clip.get_curves_by_path(&path(&["Hips"])).unwrap()[0].keyframes else {
+            panic!("root should have a translation curve first");
+        };
+        assert_eq!(translations[1], Vec3::new(1.0, 21.0, 2.0));
+    }
+
+    #[test]
+    fn euler_order() {
+        let clip = BvhFile::parse(TEST_BVH).unwrap().animation_clip();
+
+        let path = path(&["Hips", "LeftLeg"]);
+        let end = clip.get_curves_by_path(&path).unwrap()[0].rotation_at(0.5).unwrap();
+        let expected = Quat::from_euler(
+            EulerRot::ZXY,
+            10f32.to_radians(),
+            20f32.to_radians(),
+            30f32.to_radians(),
+        );
+        assert!(end.abs_diff_eq(expected, 1e-5));
+
+        let halfway = clip.get_joint_rotation_at(&path, 0.25);
+        assert!(halfway.abs_diff_eq(Quat::IDENTITY.slerp(expected, 0.5), 1e-5));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(BvhFile::parse("HIERARCHY MOTION"), Err(BvhError::NoJoints)));
+        assert!(matches!(
+            BvhFile::parse("HIERARCHY ROOT a { OFFSET 0 0 0 CHANNELS 1 Wrotation }"),
+            Err(BvhError::InvalidChannel(_))
+        ));
+        let two_roots = TEST_BVH.replacen("MOTION", "ROOT Prop\n{\nOFFSET 0 0 0\nCHANNELS 0\n}\nMOTION", 1);
+        assert!(matches!(BvhFile::parse(&two_roots), Err(BvhError::MultipleRoots)));
+        assert!(matches!(
+            BvhFile::parse(&TEST_BVH[..TEST_BVH.len() - 10]),
+            Err(BvhError::UnexpectedEnd(_))
+        ));
+    }
+}
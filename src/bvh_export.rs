@@ -0,0 +1,309 @@
+//! Writes [`AnimationClip`]s out as BVH motion capture files.
+
+use std::fmt::Write;
+
+use bevy::math::{EulerRot, Quat, Vec3};
+use thiserror::Error;
+
+use crate::{joint_paths, AnimationClip, BvhChannel, BvhJoint, Keyframes, MotionWarpClip};
+
+/// Options for [`export_bvh`].
+#[derive(Clone, Copy, Debug)]
+pub struct BvhExportOptions {
+    /// Order of the rotation channels of every joint.
+    pub rotation_order: EulerRot,
+    /// Frame rate the clip is sampled at.
+    pub frame_rate: f32,
+}
+
+impl Default for BvhExportOptions {
+    fn default() -> Self {
+        BvhExportOptions {
+            rotation_order: EulerRot::ZXY,
+            frame_rate: 30.0,
+        }
+    }
+}
+
+/// An error that occurs when exporting a BVH file.
+#[derive(Error, Debug)]
+pub enum BvhExportError {
+    #[error("skeleton has no joints")]
+    NoJoints,
+    #[error("invalid frame rate {0}")]
+    InvalidFrameRate(f32),
+    #[error("a joint's parent must come before it")]
+    InvalidHierarchy,
+    #[error("skeleton has several roots, which BVH files can't have")]
+    MultipleRoots,
+    #[error("failed to format BVH file: {0}")]
+    Fmt(#[from] std::fmt::Error),
+}
+
+/// Exports `clip`, with `warp` applied if given, as a BVH file with the rest skeleton `joints`.
+///
+/// Channels are matched to joints by [`EntityPath`], in the same way as
+/// [`crate::BvhLoader`] names them, so `joints` would usually be [`crate::Bvh::joints`].
+/// The channels of `joints` are ignored: every joint gets rotation channels in the order of
+/// [`BvhExportOptions::rotation_order`], and position channels if it's the root or its
+/// translation is animated. Joints without curves are written in their rest pose. Like the
+/// importer, a single root joint is supported.
+pub fn export_bvh(
+    joints: &[BvhJoint],
+    clip: &AnimationClip,
+    warp: Option<&MotionWarpClip>,
+    options: &BvhExportOptions,
+) -> Result<String, BvhExportError> {
+    if joints.is_empty() {
+        return Err(BvhExportError::NoJoints);
+    }
+    if !(options.frame_rate > 0.0 && options.frame_rate.is_finite()) {
+        return Err(BvhExportError::InvalidFrameRate(options.frame_rate));
+    }
+    if joints
+        .iter()
+        .enumerate()
+        .any(|(i, joint)| joint.parent.is_some_and(|parent| parent >= i))
+    {
+        return Err(BvhExportError::InvalidHierarchy);
+    }
+    // Parents come first, so the first joint is the root
+    if joints[1..].iter().any(|joint| joint.parent.is_none()) {
+        return Err(BvhExportError::MultipleRoots);
+    }
+
+    let rotation_axes = rotation_channels(Quat::IDENTITY, options.rotation_order)
+        .map(|(channel, _)| channel);
+    let paths = joint_paths(joints);
+    let joints: Vec<BvhJoint> = joints
+        .iter()
+        .zip(&paths)
+        .map(|(joint, path)| {
+            let translated = joint.parent.is_none()
+                || clip.get_curves_by_path(path).is_some_and(|curves| {
+                    curves.iter().any(|curve| {
+                        matches!(curve.keyframes, Keyframes::Translation(_) | Keyframes::CompressedTranslation(_))
+                    })
+                });
+            let mut channels = Vec::with_capacity(6);
+            if translated {
+                channels.extend([BvhChannel::XPosition, BvhChannel::YPosition, BvhChannel::ZPosition]);
+            }
+            channels.extend(rotation_axes);
+            BvhJoint { channels, ..joint.clone() }
+        })
+        .collect();
+
+    // Joints are written depth-first, and frame values must follow the same order
+    let mut order = Vec::with_capacity(joints.len());
+    let mut text = String::from("HIERARCHY\n");
+    write_joint(&mut text, &joints, 0, 0, &mut order)?;
+
+    let frame_count = (clip.duration() * options.frame_rate).round() as usize + 1;
+    writeln!(text, "MOTION")?;
+    writeln!(text, "Frames: {frame_count}")?;
+    writeln!(text, "Frame Time: {:.6}", 1.0 / options.frame_rate)?;
+
+    for frame in 0..frame_count {
+        let time = frame as f32 / options.frame_rate;
+        let mut values = Vec::new();
+        for &index in &order {
+            let joint = &joints[index];
+            // Joints without curves are in their rest pose
+            let bone_id = clip.paths().get(&paths[index]);
+            let mut pose = bone_id.and_then(|id| clip.sample_joint_clamped(*id, time)).unwrap_or_default();
+            if let Some(warp) = warp {
+                warp.warp_joint(&paths[index], time, &mut pose);
+            }
+            let translation = pose.translation.unwrap_or(joint.offset);
+            let rotation = pose.rotation.unwrap_or(Quat::IDENTITY);
+            if joint.channels[0].is_position() {
+                values.extend(translation.to_array());
+            }
+            values.extend(rotation_channels(rotation, options.rotation_order).map(|(_, value)| value));
+        }
+        let line: Vec<String> = values.iter().map(|value| format!("{value:.6}")).collect();
+        writeln!(text, "{}", line.join(" "))?;
+    }
+
+    Ok(text)
+}
+
+fn write_joint(
+    text: &mut String,
+    joints: &[BvhJoint],
+    index: usize,
+    depth: usize,
+    order: &mut Vec<usize>,
+) -> Result<(), std::fmt::Error> {
+    let joint = &joints[index];
+    let indent = "\t".repeat(depth);
+    let keyword = if joint.parent.is_none() { "ROOT" } else { "JOINT" };
+    order.push(index);
+
+    writeln!(text, "{indent}{keyword} {}", joint.name)?;
+    writeln!(text, "{indent}{{")?;
+    writeln!(text, "{indent}\tOFFSET {}", format_vec3(joint.offset))?;
+    let channels: Vec<&str> = joint.channels.iter().map(BvhChannel::as_str).collect();
+    writeln!(text, "{indent}\tCHANNELS {} {}", channels.len(), channels.join(" "))?;
+    for child in (index + 1..joints.len()).filter(|i| joints[*i].parent == Some(index)) {
+        write_joint(text, joints, child, depth + 1, order)?;
+    }
+    if let Some(end_site) = joint.end_site {
+        writeln!(text, "{indent}\tEnd Site")?;
+        writeln!(text, "{indent}\t{{")?;
+        writeln!(text, "{indent}\t\tOFFSET {}", format_vec3(end_site))?;
+        writeln!(text, "{indent}\t}}")?;
+    }
+    writeln!(text, "{indent}}}")
+}
+
+fn format_vec3(v: Vec3) -> String {
+    format!("{:.6} {:.6} {:.6}", v.x, v.y, v.z)
+}
+
+/// Converts a rotation to the Euler angles of a BVH channel order, in degrees.
+fn rotation_channels(rotation: Quat, order: EulerRot) -> [(BvhChannel, f32); 3] {
+    use BvhChannel::*;
+    let axes = match order {
+        EulerRot::ZYX => [ZRotation, YRotation, XRotation],
+        EulerRot::ZXY => [ZRotation, XRotation, YRotation],
+        EulerRot::YXZ => [YRotation, XRotation, ZRotation],
+        EulerRot::YZX => [YRotation, ZRotation, XRotation],
+        EulerRot::XYZ => [XRotation, YRotation, ZRotation],
+        EulerRot::XZY => [XRotation, ZRotation, YRotation],
+    };
+    let (a, b, c) = rotation.normalize().to_euler(order);
+    [(axes[0], a.to_degrees()), (axes[1], b.to_degrees()), (axes[2], c.to_degrees())]
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::core::Name;
+
+    use super::*;
+    use crate::builder::{MotionWarpClipBuilder, MotionWarpClipFrame, MotionWarpCurveFrame};
+    use crate::{bvh::tests::TEST_BVH, joint_transform, BvhFile, EntityPath};
+
+    fn path(names: &[&str]) -> EntityPath {
+        EntityPath { parts: names.iter().map(|name| Name::new(name.to_string())).collect() }
+    }
+
+    fn same_rotation(a: Quat, b: Quat) -> bool {
+        a.dot(b).abs() > 1.0 - 1e-5
+    }
+
+    fn sample_joint(clip: &AnimationClip, path: &EntityPath, time: f32) -> (Vec3, Quat) {
+        let joint = clip.sample_joint_clamped(clip.paths()[path], time).unwrap();
+        (joint.translation.unwrap_or_default(), joint.rotation.unwrap_or_default())
+    }
+
+    fn assert_same_motion(a: &AnimationClip, b: &AnimationClip, paths: &[EntityPath], times: &[f32]) {
+        for path in paths {
+            for &time in times {
+                let (ta, ra) = sample_joint(a, path, time);
+                let (tb, rb) = sample_joint(b, path, time);
+                assert!(ta.abs_diff_eq(tb, 1e-4), "{path:?} at {time}: {ta} != {tb}");
+                assert!(same_rotation(ra, rb), "{path:?} at {time}: {ra} != {rb}");
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let bvh = BvhFile::parse(TEST_BVH).unwrap();
+        let clip = bvh.animation_clip();
+        let options = BvhExportOptions { frame_rate: 2.0, ..Default::default() };
+
+        let exported = BvhFile::parse(&export_bvh(&bvh.joints, &clip, None, &options).unwrap()).unwrap();
+
+        assert_eq!(exported.joints, bvh.joints);
+        assert_eq!(exported.frames.len(), 2);
+        assert_eq!(exported.frame_time, 0.5);
+        assert_same_motion(&clip, &exported.animation_clip(), &bvh.joint_paths(), &[0.0, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn round_trip_rotation_order() {
+        let bvh = BvhFile::parse(TEST_BVH).unwrap();
+        let clip = bvh.animation_clip();
+        let options = BvhExportOptions { rotation_order: EulerRot::XYZ, frame_rate: 4.0 };
+
+        let exported = BvhFile::parse(&export_bvh(&bvh.joints, &clip, None, &options).unwrap()).unwrap();
+
+        assert_eq!(
+            exported.joints[1].channels,
+            vec![BvhChannel::XRotation, BvhChannel::YRotation, BvhChannel::ZRotation]
+        );
+        assert_eq!(exported.frames.len(), 3);
+        assert_same_motion(&clip, &exported.animation_clip(), &bvh.joint_paths(), &[0.0, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn rotation_channels_invert_euler() {
+        for order in [EulerRot::ZYX, EulerRot::ZXY, EulerRot::YXZ, EulerRot::YZX, EulerRot::XYZ, EulerRot::XZY] {
+            let rotation = Quat::from_euler(order, 0.1, 0.2, 0.3);
+            let channels = rotation_channels(rotation, order);
+            let joint = BvhJoint {
+                name: String::new(),
+                parent: None,
+                offset: Vec3::ZERO,
+                channels: channels.map(|(channel, _)| channel).to_vec(),
+                end_site: None,
+            };
+            let (_, converted) = joint_transform(&joint, &channels.map(|(_, value)| value));
+            assert!(same_rotation(converted, rotation), "{order:?}");
+        }
+    }
+
+    #[test]
+    fn export_warped() {
+        let bvh = BvhFile::parse(TEST_BVH).unwrap();
+        let clip = bvh.animation_clip();
+        let leg = path(&["Hips", "LeftLeg"]);
+        let target = Quat::from_rotation_x(1.0);
+
+        let mut builder = MotionWarpClipBuilder {
+            clips: vec![MotionWarpClipFrame {
+                time: 0.25,
+                warp_time: None,
+                map: [(leg.clone(), MotionWarpCurveFrame { rotation: target, fix_a: false })].into_iter().collect(),
+            }],
+            start_time: 0.0,
+            end_time: 0.5,
+            blend_margin: 0.1,
+            tension: 0.5,
+            boundary: Default::default(),
+        };
+        let warp = builder.build(&clip);
+        let options = BvhExportOptions { frame_rate: 4.0, ..Default::default() };
+
+        let exported = BvhFile::parse(&export_bvh(&bvh.joints, &clip, Some(&warp), &options).unwrap()).unwrap();
+        let exported = exported.animation_clip();
+
+        let (_, rotation) = sample_joint(&exported, &leg, 0.25);
+        assert!(same_rotation(rotation, target));
+        assert_same_motion(&clip, &exported, &[path(&["Hips", "Spine"])], &[0.0, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn export_errors() {
+        let bvh = BvhFile::parse(TEST_BVH).unwrap();
+        let clip = bvh.animation_clip();
+        let options = BvhExportOptions { frame_rate: 0.0, ..Default::default() };
+
+        assert!(matches!(export_bvh(&[], &clip, None, &Default::default()), Err(BvhExportError::NoJoints)));
+        assert!(matches!(
+            export_bvh(&bvh.joints, &clip, None, &options),
+            Err(BvhExportError::InvalidFrameRate(_))
+        ));
+
+        // The importer rejects files with several roots, so they aren't written
+        let mut joints = bvh.joints.clone();
+        joints.push(BvhJoint { parent: None, ..joints[0].clone() });
+        assert!(matches!(
+            export_bvh(&joints, &clip, None, &Default::default()),
+            Err(BvhExportError::MultipleRoots)
+        ));
+    }
+}
@@ -0,0 +1,635 @@
+//! Editing [`AnimationClip`]s: keyframes, resampling, trimming, concatenating and reversing.
+
+use bevy::math::{Quat, Vec3};
+use thiserror::Error;
+
+use crate::{AnimationClip, EntityPath, Keyframes, PropertyCurve, PropertyKeyframes, PropertyValue, VariableCurve};
+
+/// A single keyframe of a [`VariableCurve`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Keyframe {
+    Rotation(Quat),
+    Translation(Vec3),
+    Scale(Vec3),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ClipEditError {
+    #[error("keyframe doesn't animate the same property as the curve")]
+    PropertyMismatch,
+    #[error("frame rate must be positive, got {0}")]
+    InvalidFrameRate(f32),
+    #[error("invalid time range {0}..{1}")]
+    InvalidRange(f32, f32),
+}
+
+impl Keyframes {
+    /// Keyframe `index`, or `None` if there is no such keyframe.
+    pub fn get(&self, index: usize) -> Option<Keyframe> {
+        if index >= self.len() {
+            return None;
+        }
+        self.rotation(index)
+            .map(Keyframe::Rotation)
+            .or_else(|| self.translation(index).map(Keyframe::Translation))
+            .or_else(|| self.scale(index).map(Keyframe::Scale))
+    }
+
+    /// Do these keyframes and `other` animate the same property?
+    pub fn same_property(&self, other: &Keyframes) -> bool {
+        std::mem::discriminant(&self.decompressed()) == std::mem::discriminant(&other.decompressed())
+    }
+
+    /// Keyframes of the same property built from `keyframes`, which must all animate it.
+    fn with_keyframes(&self, keyframes: impl IntoIterator<Item = Keyframe>) -> Keyframes {
+        let keyframes = keyframes.into_iter();
+        match self.decompressed() {
+            Keyframes::Rotation(_) => Keyframes::Rotation(
+                keyframes
+                    .map(|keyframe| match keyframe {
+                        Keyframe::Rotation(rotation) => rotation,
+                        _ => unreachable!(),
+                    })
+                    .collect(),
+            ),
+            Keyframes::Translation(_) => Keyframes::Translation(
+                keyframes
+                    .map(|keyframe| match keyframe {
+                        Keyframe::Translation(translation) => translation,
+                        _ => unreachable!(),
+                    })
+                    .collect(),
+            ),
+            Keyframes::Scale(_) => Keyframes::Scale(
+                keyframes
+                    .map(|keyframe| match keyframe {
+                        Keyframe::Scale(scale) => scale,
+                        _ => unreachable!(),
+                    })
+                    .collect(),
+            ),
+            _ => unreachable!("keyframes are decompressed"),
+        }
+    }
+}
+
+/// Editing keyframes. Quantized keyframes are decompressed by any edit.
+impl VariableCurve {
+    /// The value of this curve at `time`, clamped to the first and last keyframes.
+    ///
+    /// Returns `None` if the curve has no keyframes.
+    pub fn keyframe_at(&self, time: f32) -> Option<Keyframe> {
+        self.rotation_at(time)
+            .map(Keyframe::Rotation)
+            .or_else(|| self.translation_at(time).map(Keyframe::Translation))
+            .or_else(|| self.scale_at(time).map(Keyframe::Scale))
+    }
+
+    /// Iterates over the timestamp and value of each keyframe.
+    pub fn iter(&self) -> impl Iterator<Item = (f32, Keyframe)> + '_ {
+        self.keyframe_timestamps
+            .iter()
+            .enumerate()
+            .filter_map(|(index, time)| Some((*time, self.keyframes.get(index)?)))
+    }
+
+    /// Sets the keyframes, which must be sorted by time.
+    fn set_keyframes(&mut self, keyframes: Vec<(f32, Keyframe)>) {
+        self.keyframe_timestamps = keyframes.iter().map(|(time, _)| *time).collect();
+        self.keyframes = self.keyframes.with_keyframes(keyframes.into_iter().map(|(_, keyframe)| keyframe));
+    }
+
+    /// Inserts a keyframe at `time`, replacing any keyframe already at that time, and returns
+    /// its index.
+    pub fn insert_keyframe(&mut self, time: f32, keyframe: Keyframe) -> Result<usize, ClipEditError> {
+        let property = match keyframe {
+            Keyframe::Rotation(_) => Keyframes::Rotation(Vec::new()),
+            Keyframe::Translation(_) => Keyframes::Translation(Vec::new()),
+            Keyframe::Scale(_) => Keyframes::Scale(Vec::new()),
+        };
+        if !self.keyframes.same_property(&property) {
+            return Err(ClipEditError::PropertyMismatch);
+        }
+
+        let mut keyframes: Vec<_> = self.iter().collect();
+        let index = keyframes.partition_point(|(probe, _)| *probe < time);
+        if keyframes.get(index).is_some_and(|(probe, _)| *probe == time) {
+            keyframes[index].1 = keyframe;
+        } else {
+            keyframes.insert(index, (time, keyframe));
+        }
+        self.set_keyframes(keyframes);
+        Ok(index)
+    }
+
+    /// Removes keyframe `index`, returning its time and value.
+    pub fn remove_keyframe(&mut self, index: usize) -> Option<(f32, Keyframe)> {
+        let mut keyframes: Vec<_> = self.iter().collect();
+        if index >= keyframes.len() {
+            return None;
+        }
+        let removed = keyframes.remove(index);
+        self.set_keyframes(keyframes);
+        Some(removed)
+    }
+
+    /// Moves keyframe `index` to `time`, replacing any keyframe already at that time, and
+    /// returns its new index.
+    pub fn move_keyframe(&mut self, index: usize, time: f32) -> Option<usize> {
+        let (_, keyframe) = self.remove_keyframe(index)?;
+        self.insert_keyframe(time, keyframe).ok()
+    }
+
+    /// Samples this curve every `1 / frame_rate` seconds from its first to its last keyframe,
+    /// both included.
+    pub fn resample(&mut self, frame_rate: f32) -> Result<(), ClipEditError> {
+        if !(frame_rate > 0.0 && frame_rate.is_finite()) {
+            return Err(ClipEditError::InvalidFrameRate(frame_rate));
+        }
+        let (Some(&first), Some(&last)) = (self.keyframe_timestamps.first(), self.keyframe_timestamps.last()) else {
+            return Ok(());
+        };
+
+        let keyframes = frame_times(first, last, frame_rate)
+            .map(|time| (time, self.keyframe_at(time).unwrap()))
+            .collect();
+        self.set_keyframes(keyframes);
+        Ok(())
+    }
+
+    /// Keeps the part of this curve between `start` and `end`, adding keyframes at both ends
+    /// if needed, and moves it back by `start`.
+    ///
+    /// Curves with a single keyframe are kept. Returns `false` if the curve doesn't overlap the
+    /// range, in which case it's left untouched.
+    fn trim(&mut self, start: f32, end: f32) -> bool {
+        let keyframes = trimmed(&self.keyframe_timestamps, self.iter(), |time| self.keyframe_at(time), start, end);
+        let Some(keyframes) = keyframes else { return false };
+        self.set_keyframes(keyframes);
+        true
+    }
+
+    /// Distributes the difference between the last and first keyframes over the whole curve,
+    /// so it ends on its first keyframe and loops without a pop.
+    pub fn fix_loop(&mut self) {
+        let (Some(&first), Some(&last)) = (self.keyframe_timestamps.first(), self.keyframe_timestamps.last()) else {
+            return;
+        };
+        let Some(last_index) = self.keyframes.len().checked_sub(1) else { return };
+        let (Some(start), Some(end)) = (self.keyframes.get(0), self.keyframes.get(last_index)) else { return };
+        if last <= first {
+            return;
+        }
+
+        let keyframes = self
+            .iter()
+            .map(|(time, keyframe)| {
+                let weight = (time - first) / (last - first);
+                let keyframe = match (keyframe, start, end) {
+                    (Keyframe::Rotation(rotation), Keyframe::Rotation(start), Keyframe::Rotation(end)) => {
+                        let mut correction = start.normalize() * end.normalize().inverse();
+                        // Take the shortest way
+                        if correction.w < 0.0 {
+                            correction = -correction;
+                        }
+                        Keyframe::Rotation((Quat::IDENTITY.slerp(correction, weight) * rotation).normalize())
+                    }
+                    (Keyframe::Translation(translation), Keyframe::Translation(start), Keyframe::Translation(end)) => {
+                        Keyframe::Translation(translation + (start - end) * weight)
+                    }
+                    (Keyframe::Scale(scale), Keyframe::Scale(start), Keyframe::Scale(end)) => {
+                        Keyframe::Scale(scale + (start - end) * weight)
+                    }
+                    _ => unreachable!("keyframes of a curve animate the same property"),
+                };
+                (time, keyframe)
+            })
+            .collect();
+        self.set_keyframes(keyframes);
+    }
+
+    /// Plays this curve backwards in a clip lasting `duration`.
+    fn reverse(&mut self, duration: f32) {
+        self.set_keyframes(reversed(self.iter(), duration));
+    }
+}
+
+impl PropertyKeyframes {
+    /// Keyframes of the same type built from `values`, which must all be of that type.
+    fn with_values(&self, values: impl IntoIterator<Item = PropertyValue>) -> PropertyKeyframes {
+        macro_rules! collect {
+            ($variant:ident) => {
+                PropertyKeyframes::$variant(
+                    values
+                        .into_iter()
+                        .map(|value| match value {
+                            PropertyValue::$variant(value) => value,
+                            _ => unreachable!(),
+                        })
+                        .collect(),
+                )
+            };
+        }
+        match self {
+            PropertyKeyframes::Float(_) => collect!(Float),
+            PropertyKeyframes::Vec2(_) => collect!(Vec2),
+            PropertyKeyframes::Vec3(_) => collect!(Vec3),
+            PropertyKeyframes::Vec4(_) => collect!(Vec4),
+            PropertyKeyframes::Quat(_) => collect!(Quat),
+            PropertyKeyframes::Color(_) => collect!(Color),
+        }
+    }
+}
+
+/// Editing keyframes of property curves, like [`VariableCurve`]s.
+impl PropertyCurve {
+    /// Iterates over the timestamp and value of each keyframe.
+    pub fn iter(&self) -> impl Iterator<Item = (f32, PropertyValue)> + '_ {
+        self.keyframe_timestamps
+            .iter()
+            .enumerate()
+            .filter_map(|(index, time)| Some((*time, self.keyframes.get(index)?)))
+    }
+
+    /// Sets the keyframes, which must be sorted by time.
+    fn set_keyframes(&mut self, keyframes: Vec<(f32, PropertyValue)>) {
+        self.keyframe_timestamps = keyframes.iter().map(|(time, _)| *time).collect();
+        self.keyframes = self.keyframes.with_values(keyframes.into_iter().map(|(_, value)| value));
+    }
+
+    /// Samples this curve every `1 / frame_rate` seconds, see [`VariableCurve::resample`].
+    fn resample(&mut self, frame_rate: f32) {
+        let (Some(&first), Some(&last)) = (self.keyframe_timestamps.first(), self.keyframe_timestamps.last()) else {
+            return;
+        };
+        let keyframes = frame_times(first, last, frame_rate)
+            .map(|time| (time, self.value_at(time).unwrap()))
+            .collect();
+        self.set_keyframes(keyframes);
+    }
+
+    /// Keeps the part of this curve between `start` and `end`, like [`VariableCurve`]s.
+    fn trim(&mut self, start: f32, end: f32) -> bool {
+        let keyframes = trimmed(&self.keyframe_timestamps, self.iter(), |time| self.value_at(time), start, end);
+        let Some(keyframes) = keyframes else { return false };
+        self.set_keyframes(keyframes);
+        true
+    }
+
+    /// Plays this curve backwards in a clip lasting `duration`.
+    fn reverse(&mut self, duration: f32) {
+        self.set_keyframes(reversed(self.iter(), duration));
+    }
+}
+
+/// Times every `1 / frame_rate` seconds from `first` to `last`, both included.
+fn frame_times(first: f32, last: f32, frame_rate: f32) -> impl Iterator<Item = f32> {
+    // Not a frame just before the last keyframe because of rounding
+    let frames = ((last - first) * frame_rate - 1e-3).ceil().max(0.0) as usize;
+    (0..frames).map(move |frame| first + frame as f32 / frame_rate).chain(std::iter::once(last))
+}
+
+/// The keyframes of a curve between `start` and `end`, moved back by `start`, with keyframes
+/// sampled by `value_at` at both ends if needed.
+///
+/// Single keyframes are kept. Returns `None` if the curve doesn't overlap the range.
+fn trimmed<K>(
+    timestamps: &[f32],
+    keyframes: impl Iterator<Item = (f32, K)>,
+    value_at: impl Fn(f32) -> Option<K>,
+    start: f32,
+    end: f32,
+) -> Option<Vec<(f32, K)>> {
+    let (first, last) = (*timestamps.first()?, *timestamps.last()?);
+    if timestamps.len() == 1 {
+        return Some(keyframes.map(|(time, keyframe)| ((time - start).max(0.0), keyframe)).collect());
+    }
+    let (start_inside, end_inside) = (start.max(first), end.min(last));
+    if start_inside > end_inside {
+        return None;
+    }
+
+    let inner = keyframes.filter(|(time, _)| start_inside < *time && *time < end_inside);
+    let keyframes = std::iter::once((start_inside, value_at(start_inside).unwrap()))
+        .chain(inner)
+        .chain((end_inside > start_inside).then(|| (end_inside, value_at(end_inside).unwrap())))
+        .map(|(time, keyframe)| (time - start, keyframe))
+        .collect();
+    Some(keyframes)
+}
+
+/// The keyframes of a curve played backwards in a clip lasting `duration`.
+fn reversed<K>(keyframes: impl Iterator<Item = (f32, K)>, duration: f32) -> Vec<(f32, K)> {
+    let keyframes = keyframes.collect::<Vec<_>>().into_iter().rev();
+    keyframes.map(|(time, keyframe)| ((duration - time).max(0.0), keyframe)).collect()
+}
+
+/// `keyframes` followed by `appended`, which replace the keyframes from their first time on.
+fn append_keyframes<K>(mut keyframes: Vec<(f32, K)>, appended: Vec<(f32, K)>) -> Vec<(f32, K)> {
+    let first_appended = appended.first().map_or(f32::INFINITY, |(time, _)| *time);
+    keyframes.retain(|(time, _)| *time < first_appended);
+    keyframes.extend(appended);
+    keyframes
+}
+
+impl AnimationClip {
+    /// Mutable [`VariableCurve`]s for each bone. Indexed by the bone ID.
+    ///
+    /// Call [`AnimationClip::recompute_duration`] after changing keyframe timestamps.
+    pub fn curves_mut(&mut self) -> &mut Vec<Vec<VariableCurve>> {
+        &mut self.curves
+    }
+
+    /// Gets the mutable curves for a bone.
+    ///
+    /// Returns `None` if the bone is invalid.
+    pub fn get_curves_mut(&mut self, bone_id: usize) -> Option<&mut Vec<VariableCurve>> {
+        self.curves.get_mut(bone_id)
+    }
+
+    /// Gets the mutable curves by its [`EntityPath`].
+    ///
+    /// Returns `None` if the bone is invalid.
+    pub fn get_curves_by_path_mut(&mut self, path: &EntityPath) -> Option<&mut Vec<VariableCurve>> {
+        self.paths.get(path).and_then(|id| self.curves.get_mut(*id))
+    }
+
+    /// Sets the duration to the time of the last keyframe of every curve.
+    pub fn recompute_duration(&mut self) {
+        let properties = self.property_curves.iter().flatten().map(|curve| &curve.keyframe_timestamps);
+        self.duration = self
+            .curves
+            .iter()
+            .flatten()
+            .map(|curve| &curve.keyframe_timestamps)
+            .chain(properties)
+            .filter_map(|timestamps| timestamps.last())
+            .fold(0.0, |duration, time| duration.max(*time));
+    }
+
+    /// Resamples every curve with more than one keyframe at `frame_rate`, property curves
+    /// included, see [`VariableCurve::resample`].
+    pub fn resample(&mut self, frame_rate: f32) -> Result<(), ClipEditError> {
+        if !(frame_rate > 0.0 && frame_rate.is_finite()) {
+            return Err(ClipEditError::InvalidFrameRate(frame_rate));
+        }
+        for curve in self.curves.iter_mut().flatten() {
+            if curve.keyframe_timestamps.len() > 1 {
+                curve.resample(frame_rate)?;
+            }
+        }
+        for curve in self.property_curves.iter_mut().flatten() {
+            if curve.keyframe_timestamps.len() > 1 {
+                curve.resample(frame_rate);
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps the part of the clip between `start` and `end` seconds, which then starts at 0.
+    ///
+    /// Curves outside that range are removed, and bone and property IDs are kept.
+    pub fn trim(&mut self, start: f32, end: f32) -> Result<(), ClipEditError> {
+        if !(0.0 <= start && start <= end && end.is_finite()) {
+            return Err(ClipEditError::InvalidRange(start, end));
+        }
+        for curves in &mut self.curves {
+            curves.retain_mut(|curve| curve.trim(start, end));
+        }
+        for curves in &mut self.property_curves {
+            curves.retain_mut(|curve| curve.trim(start, end));
+        }
+        self.duration = self.duration.min(end) - start.min(self.duration);
+        Ok(())
+    }
+
+    /// Plays the clip backwards, property curves included.
+    pub fn reverse(&mut self) {
+        for curve in self.curves.iter_mut().flatten() {
+            if curve.keyframe_timestamps.len() > 1 {
+                curve.reverse(self.duration);
+            }
+        }
+        for curve in self.property_curves.iter_mut().flatten() {
+            if curve.keyframe_timestamps.len() > 1 {
+                curve.reverse(self.duration);
+            }
+        }
+    }
+
+    /// Makes the curves for which `filter` returns `true` end on their first keyframe, so the
+    /// clip loops seamlessly, see [`VariableCurve::fix_loop`].
+    ///
+    /// Property curves are left as they are, since a pop in a property is usually intended.
+    ///
+    /// Curves that should keep moving from one loop to the next, such as root motion, can be
+    /// left out by `filter`.
+    pub fn fix_loop(&mut self, mut filter: impl FnMut(&EntityPath, &VariableCurve) -> bool) {
+        for (path, bone_id) in &self.paths {
+            for curve in &mut self.curves[*bone_id] {
+                if filter(path, curve) {
+                    curve.fix_loop();
+                }
+            }
+        }
+    }
+
+    /// Appends `other` after the end of this clip.
+    ///
+    /// Curves of `other` are merged with curves of the same bone and property, and added
+    /// otherwise. Keyframes of `other` replace keyframes of this clip at the same time. Property
+    /// curves are merged with curves of the same entity, component and field.
+    pub fn append(&mut self, other: &AnimationClip) {
+        let offset = self.duration;
+        for (path, bone_id) in &other.paths {
+            for other_curve in &other.curves[*bone_id] {
+                let appended: Vec<_> = other_curve.iter().map(|(time, keyframe)| (time + offset, keyframe)).collect();
+                let curve = self.get_curves_by_path_mut(path).and_then(|curves| {
+                    curves.iter_mut().find(|curve| curve.keyframes.same_property(&other_curve.keyframes))
+                });
+                match curve {
+                    Some(curve) => curve.set_keyframes(append_keyframes(curve.iter().collect(), appended)),
+                    None => {
+                        let mut curve = other_curve.clone();
+                        curve.set_keyframes(appended);
+                        self.add_curve_to_path(path.clone(), curve);
+                    }
+                }
+            }
+        }
+        for (path, property_id) in &other.property_paths {
+            for other_curve in &other.property_curves[*property_id] {
+                let appended: Vec<_> = other_curve.iter().map(|(time, value)| (time + offset, value)).collect();
+                let curve = self.property_paths.get(path).and_then(|id| {
+                    self.property_curves[*id].iter_mut().find(|curve| {
+                        let same_type = std::mem::discriminant(&curve.keyframes)
+                            == std::mem::discriminant(&other_curve.keyframes);
+                        curve.component == other_curve.component && curve.field == other_curve.field && same_type
+                    })
+                });
+                match curve {
+                    Some(curve) => curve.set_keyframes(append_keyframes(curve.iter().collect(), appended)),
+                    None => {
+                        let mut curve = other_curve.clone();
+                        curve.set_keyframes(appended);
+                        self.add_property_curve_to_path(path.clone(), curve);
+                    }
+                }
+            }
+        }
+        self.duration = offset + other.duration;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::core::Name;
+
+    use super::*;
+
+    fn path() -> EntityPath {
+        EntityPath { parts: vec![Name::new("root")] }
+    }
+
+    fn translation_clip(keyframes: &[(f32, f32)]) -> AnimationClip {
+        let mut clip = AnimationClip::default();
+        clip.add_curve_to_path(
+            path(),
+            VariableCurve {
+                keyframe_timestamps: keyframes.iter().map(|(time, _)| *time).collect(),
+                keyframes: Keyframes::Translation(keyframes.iter().map(|(_, x)| Vec3::new(*x, 0.0, 0.0)).collect()),
+            },
+        );
+        clip
+    }
+
+    fn keyframes(clip: &AnimationClip) -> Vec<(f32, f32)> {
+        clip.get_curves(0).unwrap()[0]
+            .iter()
+            .map(|(time, keyframe)| match keyframe {
+                Keyframe::Translation(translation) => (time, translation.x),
+                _ => panic!("not a translation"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn edit_keyframes() {
+        let mut clip = translation_clip(&[(0.0, 0.0), (1.0, 1.0)]);
+        let curve = &mut clip.get_curves_mut(0).unwrap()[0];
+
+        assert_eq!(curve.insert_keyframe(0.5, Keyframe::Translation(Vec3::X * 4.0)), Ok(1));
+        assert_eq!(curve.insert_keyframe(0.5, Keyframe::Translation(Vec3::X * 5.0)), Ok(1));
+        assert_eq!(curve.insert_keyframe(2.0, Keyframe::Scale(Vec3::ONE)), Err(ClipEditError::PropertyMismatch));
+        assert_eq!(curve.move_keyframe(0, 3.0), Some(2));
+        assert_eq!(curve.remove_keyframe(0), Some((0.5, Keyframe::Translation(Vec3::X * 5.0))));
+        assert_eq!(curve.remove_keyframe(5), None);
+        clip.recompute_duration();
+
+        assert_eq!(keyframes(&clip), [(1.0, 1.0), (3.0, 0.0)]);
+        assert_eq!(clip.duration(), 3.0);
+    }
+
+    #[test]
+    fn resample() {
+        let mut clip = translation_clip(&[(0.0, 0.0), (1.0, 1.0), (1.1, 0.0)]);
+        clip.resample(4.0).unwrap();
+        assert_eq!(keyframes(&clip), [(0.0, 0.0), (0.25, 0.25), (0.5, 0.5), (0.75, 0.75), (1.0, 1.0), (1.1, 0.0)]);
+        assert_eq!(clip.resample(0.0), Err(ClipEditError::InvalidFrameRate(0.0)));
+    }
+
+    #[test]
+    fn trim() {
+        let mut clip = translation_clip(&[(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]);
+        // Outside of the range
+        clip.add_curve_to_path(
+            path(),
+            VariableCurve { keyframe_timestamps: vec![0.0, 0.2], keyframes: Keyframes::Scale(vec![Vec3::ONE; 2]) },
+        );
+        clip.trim(0.5, 1.5).unwrap();
+
+        assert_eq!(keyframes(&clip), [(0.0, 0.5), (0.5, 1.0), (1.0, 0.5)]);
+        assert_eq!(clip.get_curves(0).unwrap().len(), 1);
+        assert_eq!(clip.duration(), 1.0);
+        assert_eq!(clip.trim(1.0, 0.5), Err(ClipEditError::InvalidRange(1.0, 0.5)));
+    }
+
+    #[test]
+    fn reverse_and_append() {
+        let mut clip = translation_clip(&[(0.0, 0.0), (1.0, 1.0), (2.0, 3.0)]);
+        clip.reverse();
+        assert_eq!(keyframes(&clip), [(0.0, 3.0), (1.0, 1.0), (2.0, 0.0)]);
+
+        let mut other = translation_clip(&[(0.0, 5.0), (1.0, 6.0)]);
+        let other_path = EntityPath { parts: vec![Name::new("root"), Name::new("child")] };
+        other.add_curve_to_path(
+            other_path.clone(),
+            VariableCurve {
+                keyframe_timestamps: vec![0.0, 1.0],
+                keyframes: Keyframes::Rotation(vec![Quat::IDENTITY; 2]),
+            },
+        );
+        clip.append(&other);
+
+        assert_eq!(keyframes(&clip), [(0.0, 3.0), (1.0, 1.0), (2.0, 5.0), (3.0, 6.0)]);
+        assert_eq!(clip.get_curves_by_path(&other_path).unwrap()[0].keyframe_timestamps, [2.0, 3.0]);
+        assert_eq!(clip.duration(), 3.0);
+    }
+
+    #[test]
+    fn edits_property_curves() {
+        let intensity = |keyframes: &[(f32, f32)]| PropertyCurve {
+            component: "Glow".to_string(),
+            field: "intensity".to_string(),
+            keyframe_timestamps: keyframes.iter().map(|(time, _)| *time).collect(),
+            keyframes: PropertyKeyframes::Float(keyframes.iter().map(|(_, value)| *value).collect()),
+        };
+        let values = |clip: &AnimationClip| clip.property_curves()[0][0].iter().collect::<Vec<_>>();
+        let mut clip = translation_clip(&[(0.0, 0.0), (2.0, 0.0)]);
+        clip.add_property_curve_to_path(path(), intensity(&[(0.0, 0.0), (1.0, 2.0), (2.0, 0.0)]));
+
+        clip.trim(0.5, 2.0).unwrap();
+        clip.reverse();
+        let expected = [(0.0, 0.0), (1.0, 2.0), (1.5, 1.0)];
+        assert_eq!(values(&clip), expected.map(|(time, value)| (time, PropertyValue::Float(value))));
+
+        let mut other = AnimationClip::default();
+        other.add_property_curve_to_path(path(), intensity(&[(0.0, 4.0), (1.0, 5.0)]));
+        other.recompute_duration();
+        clip.append(&other);
+        clip.resample(1.0).unwrap();
+        let expected = [(0.0, 0.0), (1.0, 2.0), (2.0, 4.5), (2.5, 5.0)];
+        assert_eq!(values(&clip), expected.map(|(time, value)| (time, PropertyValue::Float(value))));
+        assert_eq!(clip.property_curves()[0].len(), 1);
+    }
+
+    #[test]
+    fn fix_loop() {
+        let mut clip = translation_clip(&[(0.0, 0.0), (1.0, 3.0), (2.0, 1.0)]);
+        let other_path = EntityPath { parts: vec![Name::new("root"), Name::new("child")] };
+        clip.add_curve_to_path(
+            other_path.clone(),
+            VariableCurve {
+                keyframe_timestamps: vec![0.0, 1.0, 2.0],
+                keyframes: Keyframes::Rotation(vec![
+                    Quat::IDENTITY,
+                    Quat::from_rotation_x(1.0),
+                    Quat::from_rotation_x(0.2),
+                ]),
+            },
+        );
+        clip.fix_loop(|path, _| *path != other_path);
+        assert_eq!(keyframes(&clip), [(0.0, 0.0), (1.0, 2.5), (2.0, 0.0)]);
+        assert_eq!(clip.get_curves_by_path(&other_path).unwrap()[0].rotation_at(2.0), Some(Quat::from_rotation_x(0.2)));
+
+        clip.fix_loop(|_, _| true);
+        let curve = &clip.get_curves_by_path(&other_path).unwrap()[0];
+        assert!(curve.rotation_at(2.0).unwrap().abs_diff_eq(Quat::IDENTITY, 1e-6));
+        assert!(curve.rotation_at(1.0).unwrap().abs_diff_eq(Quat::from_rotation_x(0.9), 1e-6));
+
+        // Malformed curves without keyframes are left alone
+        let mut empty = VariableCurve { keyframe_timestamps: vec![0.0, 1.0], keyframes: Keyframes::Translation(vec![]) };
+        empty.fix_loop();
+        assert_eq!(empty.keyframe_timestamps, [0.0, 1.0]);
+    }
+}
@@ -0,0 +1,495 @@
+//! Compression of [`AnimationClip`] curves, by removing keyframes within an error bound and
+//! quantizing the remaining ones.
+
+use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2};
+
+use bevy::math::{Quat, Vec3};
+use bevy::reflect::{FromReflect, Reflect};
+use serde::Serialize;
+
+use crate::{slerp_keyframes, AnimationClip, EntityPath, Keyframes, VariableCurve};
+
+/// Largest value of a 15 bit quantized component
+const MAX_15_BITS: f32 = 0x7fff as f32;
+
+/// Rotation keyframes quantized to 48 bits each with the smallest-three encoding.
+///
+/// The largest component of each quaternion is dropped and recomputed from the three others,
+/// which are stored with 15 bits each. The index of the dropped component is stored in the
+/// remaining bits.
+#[derive(Reflect, FromReflect, Clone, Debug, Default, PartialEq)]
+pub struct CompressedRotations {
+    data: Vec<u16>,
+}
+
+impl CompressedRotations {
+    /// Quantizes `rotations`.
+    pub fn new(rotations: &[Quat]) -> Self {
+        CompressedRotations { data: rotations.iter().flat_map(|rotation| encode_rotation(*rotation)).collect() }
+    }
+
+    /// Number of keyframes.
+    pub fn len(&self) -> usize {
+        self.data.len() / 3
+    }
+
+    /// Are there no keyframes?
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Decodes keyframe `index`.
+    #[inline]
+    pub fn get(&self, index: usize) -> Quat {
+        decode_rotation([self.data[3 * index], self.data[3 * index + 1], self.data[3 * index + 2]])
+    }
+
+    /// Decodes every keyframe.
+    pub fn iter(&self) -> impl Iterator<Item = Quat> + '_ {
+        (0..self.len()).map(|index| self.get(index))
+    }
+}
+
+fn encode_rotation(rotation: Quat) -> [u16; 3] {
+    let mut components = rotation.normalize().to_array();
+    let largest = (0..4).max_by(|a, b| components[*a].abs().total_cmp(&components[*b].abs())).unwrap();
+    // q and -q are the same rotation, so the dropped component is always positive
+    if components[largest] < 0.0 {
+        components = components.map(|component| -component);
+    }
+
+    let mut encoded = [0; 3];
+    let smallest = (0..4).filter(|index| *index != largest);
+    for (encoded, index) in encoded.iter_mut().zip(smallest) {
+        // The smallest components are within ±1/√2
+        let unit = (components[index] * SQRT_2 * 0.5 + 0.5).clamp(0.0, 1.0);
+        *encoded = (unit * MAX_15_BITS).round() as u16;
+    }
+    encoded[0] |= ((largest as u16) >> 1) << 15;
+    encoded[1] |= ((largest as u16) & 1) << 15;
+    encoded
+}
+
+fn decode_rotation(encoded: [u16; 3]) -> Quat {
+    let largest = ((encoded[0] >> 15) << 1 | encoded[1] >> 15) as usize;
+    let mut components = [0.0; 4];
+    let smallest = (0..4).filter(|index| *index != largest);
+    for (encoded, index) in encoded.iter().zip(smallest) {
+        let unit = (encoded & 0x7fff) as f32 / MAX_15_BITS;
+        components[index] = (unit * 2.0 - 1.0) * FRAC_1_SQRT_2;
+    }
+    let squared: f32 = components.iter().map(|component| component * component).sum();
+    components[largest] = (1.0 - squared).max(0.0).sqrt();
+    Quat::from_array(components).normalize()
+}
+
+/// Translation or scale keyframes quantized to 16 bits per component, within the bounds of
+/// the keyframes.
+#[derive(Reflect, FromReflect, Clone, Debug, Default, PartialEq)]
+pub struct QuantizedVec3s {
+    min: Vec3,
+    extent: Vec3,
+    data: Vec<u16>,
+}
+
+impl QuantizedVec3s {
+    /// Quantizes `values`.
+    pub fn new(values: &[Vec3]) -> Self {
+        let min = values.iter().copied().reduce(Vec3::min).unwrap_or_default();
+        let max = values.iter().copied().reduce(Vec3::max).unwrap_or_default();
+        let extent = max - min;
+        let data = values
+            .iter()
+            .flat_map(|value| {
+                let unit = ((*value - min) / extent).to_array();
+                // Components that don't vary have no extent
+                unit.map(|unit| match unit.is_finite() {
+                    true => (unit.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16,
+                    false => 0,
+                })
+            })
+            .collect();
+        QuantizedVec3s { min, extent, data }
+    }
+
+    /// Number of keyframes.
+    pub fn len(&self) -> usize {
+        self.data.len() / 3
+    }
+
+    /// Are there no keyframes?
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Decodes keyframe `index`.
+    #[inline]
+    pub fn get(&self, index: usize) -> Vec3 {
+        let unit = Vec3::new(
+            self.data[3 * index] as f32,
+            self.data[3 * index + 1] as f32,
+            self.data[3 * index + 2] as f32,
+        ) / u16::MAX as f32;
+        self.min + unit * self.extent
+    }
+
+    /// Decodes every keyframe.
+    pub fn iter(&self) -> impl Iterator<Item = Vec3> + '_ {
+        (0..self.len()).map(|index| self.get(index))
+    }
+}
+
+/// How [`AnimationClip::compress`] compresses curves.
+#[derive(Clone, Copy, Debug)]
+pub struct CompressionSettings {
+    /// Largest rotation error introduced by removing and quantizing keyframes, in radians.
+    pub max_rotation_error: f32,
+    /// Largest translation error introduced by removing and quantizing keyframes.
+    pub max_translation_error: f32,
+    /// Largest scale error introduced by removing and quantizing keyframes.
+    pub max_scale_error: f32,
+    /// Quantize the remaining keyframes. Curves whose keyframes can't be quantized within the
+    /// largest error, such as translations over long distances, are kept unquantized.
+    pub quantize: bool,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        CompressionSettings {
+            max_rotation_error: 0.002,
+            max_translation_error: 0.001,
+            max_scale_error: 0.001,
+            quantize: true,
+        }
+    }
+}
+
+/// Size and error of an [`AnimationClip`] compressed with [`AnimationClip::compress`].
+///
+/// Errors are measured at the times of the original keyframes.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CompressionReport {
+    /// Size of the original curves, in bytes.
+    pub original_size: usize,
+    /// Size of the compressed curves, in bytes.
+    pub compressed_size: usize,
+    /// Number of keyframes in the original curves.
+    pub original_keyframes: usize,
+    /// Number of keyframes in the compressed curves.
+    pub compressed_keyframes: usize,
+    /// Largest rotation error, in radians.
+    pub max_rotation_error: f32,
+    /// Largest translation error.
+    pub max_translation_error: f32,
+    /// Largest scale error.
+    pub max_scale_error: f32,
+}
+
+impl CompressionReport {
+    /// Compressed size relative to the original size.
+    pub fn ratio(&self) -> f32 {
+        self.compressed_size as f32 / self.original_size.max(1) as f32
+    }
+}
+
+impl VariableCurve {
+    /// Memory used by the keyframes of this curve, in bytes.
+    pub fn size(&self) -> usize {
+        let keyframes = match &self.keyframes {
+            Keyframes::Rotation(keyframes) => std::mem::size_of_val(keyframes.as_slice()),
+            Keyframes::Translation(keyframes) | Keyframes::Scale(keyframes) => {
+                std::mem::size_of_val(keyframes.as_slice())
+            }
+            Keyframes::CompressedRotation(keyframes) => std::mem::size_of_val(keyframes.data.as_slice()),
+            Keyframes::CompressedTranslation(keyframes) | Keyframes::CompressedScale(keyframes) => {
+                2 * std::mem::size_of::<Vec3>() + std::mem::size_of_val(keyframes.data.as_slice())
+            }
+        };
+        std::mem::size_of_val(self.keyframe_timestamps.as_slice()) + keyframes
+    }
+}
+
+impl AnimationClip {
+    /// Memory used by the keyframes of this clip, in bytes.
+    pub fn size(&self) -> usize {
+        self.curves().iter().flatten().map(VariableCurve::size).sum()
+    }
+
+    /// A copy of this clip with fewer keyframes, quantized if `settings.quantize` is set, that
+    /// [`crate::AnimationPlayer`]s sample directly.
+    ///
+    /// Keyframes are removed as long as interpolating between the remaining ones, once quantized,
+    /// stays within the errors allowed by `settings`. Bone IDs are kept.
+    pub fn compress(&self, settings: &CompressionSettings) -> (AnimationClip, CompressionReport) {
+        let mut paths: Vec<_> = self.paths().iter().collect();
+        paths.sort_unstable_by_key(|(_, bone_id)| **bone_id);
+
+        let mut compressed = AnimationClip::default();
+        let mut report = CompressionReport::default();
+        for (path, bone_id) in paths {
+            for curve in &self.curves()[*bone_id] {
+                let compressed_curve = compress_curve(curve, settings, &mut report);
+                report.original_size += curve.size();
+                report.compressed_size += compressed_curve.size();
+                report.original_keyframes += curve.keyframe_timestamps.len();
+                report.compressed_keyframes += compressed_curve.keyframe_timestamps.len();
+                compressed.add_curve_to_path(path.clone(), compressed_curve);
+            }
+        }
+        compressed.copy_property_curves(self, EntityPath::clone);
+        (compressed, report)
+    }
+}
+
+fn compress_curve(
+    curve: &VariableCurve,
+    settings: &CompressionSettings,
+    report: &mut CompressionReport,
+) -> VariableCurve {
+    let timestamps = &curve.keyframe_timestamps;
+    match curve.keyframes.decompressed() {
+        Keyframes::Rotation(rotations) => {
+            let max_error = settings.max_rotation_error;
+            let quantized: Vec<Quat> = CompressedRotations::new(&rotations).iter().collect();
+            let quantize = settings.quantize && within_error(&rotations, &quantized, rotation_error, max_error);
+            let decoded = if quantize { &quantized } else { &rotations };
+            let (keep, error) =
+                reduce_keyframes(timestamps, &rotations, decoded, slerp_keyframes, rotation_error, max_error);
+            report.max_rotation_error = report.max_rotation_error.max(error);
+            let rotations: Vec<Quat> = keep.iter().map(|index| rotations[*index]).collect();
+            VariableCurve {
+                keyframe_timestamps: keep.iter().map(|index| timestamps[*index]).collect(),
+                keyframes: if quantize {
+                    Keyframes::CompressedRotation(CompressedRotations::new(&rotations))
+                } else {
+                    Keyframes::Rotation(rotations)
+                },
+            }
+        }
+        Keyframes::Translation(translations) => {
+            let (curve, error) = compress_vec3s(
+                timestamps,
+                &translations,
+                settings,
+                settings.max_translation_error,
+                Keyframes::Translation,
+                Keyframes::CompressedTranslation,
+            );
+            report.max_translation_error = report.max_translation_error.max(error);
+            curve
+        }
+        Keyframes::Scale(scales) => {
+            let (curve, error) = compress_vec3s(
+                timestamps,
+                &scales,
+                settings,
+                settings.max_scale_error,
+                Keyframes::Scale,
+                Keyframes::CompressedScale,
+            );
+            report.max_scale_error = report.max_scale_error.max(error);
+            curve
+        }
+        Keyframes::CompressedRotation(_) | Keyframes::CompressedTranslation(_) | Keyframes::CompressedScale(_) => {
+            unreachable!("keyframes are decompressed")
+        }
+    }
+}
+
+/// Compresses translation or scale keyframes into a curve made with `raw` or, if quantized,
+/// `quantized`. Returns the curve and its largest error.
+fn compress_vec3s(
+    timestamps: &[f32],
+    values: &[Vec3],
+    settings: &CompressionSettings,
+    max_error: f32,
+    raw: fn(Vec<Vec3>) -> Keyframes,
+    quantized: fn(QuantizedVec3s) -> Keyframes,
+) -> (VariableCurve, f32) {
+    // Quantized within the bounds of every keyframe, so the kept keyframes decode to the
+    // values the error is measured with
+    let encoded = QuantizedVec3s::new(values);
+    let encoded_values: Vec<Vec3> = encoded.iter().collect();
+    let quantize = settings.quantize && within_error(values, &encoded_values, Vec3::distance, max_error);
+    let decoded = if quantize { &encoded_values } else { values };
+    let (keep, error) = reduce_keyframes(timestamps, values, decoded, Vec3::lerp, Vec3::distance, max_error);
+
+    let keyframes = if quantize {
+        quantized(QuantizedVec3s {
+            min: encoded.min,
+            extent: encoded.extent,
+            data: keep.iter().flat_map(|index| &encoded.data[3 * index..3 * index + 3]).copied().collect(),
+        })
+    } else {
+        raw(keep.iter().map(|index| values[*index]).collect())
+    };
+    let keyframe_timestamps = keep.iter().map(|index| timestamps[*index]).collect();
+    (VariableCurve { keyframe_timestamps, keyframes }, error)
+}
+
+/// Angle between two rotations, precise for small angles.
+fn rotation_error(a: Quat, b: Quat) -> f32 {
+    let difference = a.conjugate() * b;
+    2.0 * difference.xyz().length().atan2(difference.w.abs())
+}
+
+/// Are all the `decoded` values within `max_error` of the original `values`?
+fn within_error<T: Copy>(values: &[T], decoded: &[T], error: impl Fn(T, T) -> f32, max_error: f32) -> bool {
+    values.iter().zip(decoded).all(|(value, decoded)| error(*decoded, *value) <= max_error)
+}
+
+/// Indices of the keyframes to keep so that interpolating between their `decoded` values
+/// stays within `max_error` of the original `values`, and the largest error of the result.
+///
+/// The first and last keyframes are always kept, so the curve keeps its time range. The kept
+/// keyframes are only within `max_error` if every decoded value is.
+fn reduce_keyframes<T: Copy>(
+    timestamps: &[f32],
+    values: &[T],
+    decoded: &[T],
+    interpolate: impl Fn(T, T, f32) -> T,
+    error: impl Fn(T, T) -> f32,
+    max_error: f32,
+) -> (Vec<usize>, f32) {
+    let len = timestamps.len().min(values.len());
+    if len == 0 {
+        return (Vec::new(), 0.0);
+    }
+    // Error at keyframe `index`, interpolated between the keyframes `start` and `end`
+    let keyframe_error = |start: usize, end: usize, index: usize| {
+        let span = timestamps[end] - timestamps[start];
+        let lerp = if span > 0.0 { (timestamps[index] - timestamps[start]) / span } else { 0.0 };
+        error(interpolate(decoded[start], decoded[end], lerp), values[index])
+    };
+    // Including the end keyframe, for its quantization error
+    let within =
+        |start: usize, end: usize| (start + 1..=end).all(|index| keyframe_error(start, end, index) <= max_error);
+
+    let mut keep = vec![0];
+    let mut start = 0;
+    while start < len - 1 {
+        // Double the segment from `start` until it exceeds the error bound, then bisect between
+        // the longest segment found within the bound and the shortest one beyond it, so that
+        // only a logarithmic number of segments are checked
+        let (mut good, mut bad) = (start + 1, len);
+        while good < len - 1 {
+            let end = (2 * good - start).min(len - 1);
+            if !within(start, end) {
+                bad = end;
+                break;
+            }
+            good = end;
+        }
+        while bad - good > 1 {
+            let end = (good + bad) / 2;
+            if within(start, end) {
+                good = end;
+            } else {
+                bad = end;
+            }
+        }
+        keep.push(good);
+        start = good;
+    }
+
+    let max = keep
+        .windows(2)
+        .flat_map(|segment| (segment[0] + 1..=segment[1]).map(|index| keyframe_error(segment[0], segment[1], index)))
+        .chain(std::iter::once(error(decoded[0], values[0])))
+        .fold(0.0, f32::max);
+    (keep, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::core::Name;
+
+    use super::*;
+
+    #[test]
+    fn smallest_three() {
+        for rotation in [
+            Quat::IDENTITY,
+            -Quat::IDENTITY,
+            Quat::from_rotation_x(3.0),
+            Quat::from_euler(bevy::math::EulerRot::YXZ, 0.3, -1.2, 2.5),
+            Quat::from_xyzw(0.5, 0.5, -0.5, -0.5),
+        ] {
+            let decoded = decode_rotation(encode_rotation(rotation));
+            assert!(rotation_error(rotation, decoded) < 2e-4, "{rotation} decoded to {decoded}");
+        }
+    }
+
+    #[test]
+    fn quantized_vec3s() {
+        let values = [Vec3::new(-1.0, 2.0, 5.0), Vec3::new(3.0, 2.0, 5.5), Vec3::ZERO];
+        let quantized = QuantizedVec3s::new(&values);
+        assert_eq!(quantized.len(), 3);
+        for (value, decoded) in values.iter().zip(quantized.iter()) {
+            assert!(value.abs_diff_eq(decoded, 1e-4), "{value} decoded to {decoded}");
+        }
+    }
+
+    #[test]
+    fn compress_clip() {
+        let path = EntityPath { parts: vec![Name::new("root")] };
+        let mut clip = AnimationClip::default();
+        let timestamps: Vec<f32> = (0..=60).map(|i| i as f32 / 30.0).collect();
+        // A linear translation and a constant speed rotation, with a bump in the middle
+        clip.add_curve_to_path(
+            path.clone(),
+            VariableCurve {
+                keyframe_timestamps: timestamps.clone(),
+                keyframes: Keyframes::Translation(
+                    timestamps.iter().map(|t| Vec3::new(*t, if *t == 1.0 { 0.5 } else { 0.0 }, 0.0)).collect(),
+                ),
+            },
+        );
+        clip.add_curve_to_path(
+            path.clone(),
+            VariableCurve {
+                keyframe_timestamps: timestamps.clone(),
+                keyframes: Keyframes::Rotation(timestamps.iter().map(|t| Quat::from_rotation_y(*t)).collect()),
+            },
+        );
+
+        let settings = CompressionSettings::default();
+        let (compressed, report) = clip.compress(&settings);
+        assert_eq!(report.original_keyframes, 122);
+        // Both ends, and the bump with its neighbours
+        assert_eq!(compressed.get_curves(0).unwrap()[0].keyframe_timestamps.len(), 5);
+        assert_eq!(compressed.get_curves(0).unwrap()[1].keyframe_timestamps.len(), 2);
+        assert!(report.ratio() < 0.1);
+        assert!(report.max_translation_error <= settings.max_translation_error);
+        assert!(report.max_rotation_error <= settings.max_rotation_error);
+        assert_eq!(compressed.duration(), clip.duration());
+
+        // Sampled directly
+        for t in [0.0, 0.5, 1.0, 1.7] {
+            let expected = clip.sample_joint(0, t).unwrap();
+            let joint = compressed.sample_joint(0, t).unwrap();
+            assert!(joint.translation.unwrap().abs_diff_eq(expected.translation.unwrap(), 1e-3));
+            assert!(rotation_error(joint.rotation.unwrap(), expected.rotation.unwrap()) < 2e-3);
+        }
+
+        // Translations too far apart to be quantized within the error bound
+        let mut far = AnimationClip::default();
+        far.add_curve_to_path(
+            path.clone(),
+            VariableCurve {
+                keyframe_timestamps: timestamps.clone(),
+                keyframes: Keyframes::Translation(
+                    timestamps.iter().map(|t| Vec3::new(1000.0 * t, (10.0 * t).sin(), 0.0)).collect(),
+                ),
+            },
+        );
+        let (compressed, report) = far.compress(&settings);
+        assert!(matches!(compressed.get_curves(0).unwrap()[0].keyframes, Keyframes::Translation(_)));
+        assert!(report.max_translation_error <= settings.max_translation_error);
+
+        // Without quantization the remaining keyframes are exact
+        let (compressed, report) = clip.compress(&CompressionSettings { quantize: false, ..settings });
+        assert!(matches!(compressed.get_curves(0).unwrap()[1].keyframes, Keyframes::Rotation(_)));
+        assert_eq!(report.max_translation_error, 0.0);
+    }
+}
@@ -37,6 +37,7 @@ impl Plugin for MainPlugin {
                     clips: Vec::new(),
                     blend_margin: 0.1,
                     tension: 0.5,
+                    boundary: Default::default(),
                 }
             )
             .add_event::<RebuildWarpClip>()
@@ -48,11 +49,13 @@ impl Plugin for MainPlugin {
                     timeline_panel, 
                     keyframe_panel, 
                     settings_panel.run_if(in_state(Mode::Settings)),
-                    property_panel.run_if(in_state(Mode::Keyframe))
+                    property_panel.run_if(in_state(Mode::Keyframe)),
+                    joint_limit_overlay.run_if(in_state(Mode::Keyframe)),
                 ).chain()
             )
             .add_system(build_warp_clip)
             .add_system(add_entity_paths)
+            .add_system(load_joint_limits)
             .edit_schedule(OnExit(Mode::Preview), |schedule| {
                 schedule.add_system(pause_on_preview_exit);
             })
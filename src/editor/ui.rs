@@ -12,6 +12,9 @@ const KEYFRAME_PANEL_ID: i32 = 2;
 const TIMELINE_PANEL_ID: i32 = 3;
 const SETTINGS_PANEL_ID: i32 = 4;
 
+const WARP_DESCRIPTION_PATH: &str = "warp.ron";
+const JOINT_LIMITS_PATH: &str = "joint_limits.ron";
+
 #[derive(Resource, Default)]
 pub struct UiHovered(bool);
 
@@ -21,6 +24,9 @@ pub struct CurrentAnimation(Handle<AnimationClip>);
 #[derive(Resource)]
 pub struct CurrentMotionWarp(Handle<MotionWarpClip>);
 
+#[derive(Resource)]
+pub struct CurrentSkeleton(Handle<Skeleton>);
+
 pub struct RebuildWarpClip;
 
 #[derive(Resource)]
@@ -58,15 +64,18 @@ pub fn set_animation_time_on_keyframe_enter(
     player.set_elapsed(time);
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn build_warp_clip(
     current_animation: Res<CurrentAnimation>,
-    mut current_keyframe: Option<ResMut<CurrentKeyframe>>,
+    current_keyframe: Option<ResMut<CurrentKeyframe>>,
     mut clip_builder: ResMut<MotionWarpClipBuilder>,
     mut commands: Commands,
     mut motion_warps: ResMut<Assets<MotionWarpClip>>,
     mut player: Query<&mut AnimationPlayer>,
     mut rebuild_ev: EventReader<RebuildWarpClip>,
-    animations: Res<Assets<AnimationClip>>
+    animations: Res<Assets<AnimationClip>>,
+    current_skeleton: Res<CurrentSkeleton>,
+    skeletons: Res<Assets<Skeleton>>,
 ) {
     if rebuild_ev.iter().next().is_some() {
         let Some(animation) = animations.get(&current_animation.0) else {
@@ -86,16 +95,29 @@ pub fn build_warp_clip(
             current_keyframe.0 = clip_builder.clips.binary_search_by(|clip| clip.time.partial_cmp(&current_keyframe_time).unwrap()).unwrap();
         }
 
-        let handle = motion_warps.add(clip_builder.build(animation));
+        let mut warp = clip_builder.build(animation);
+        if let Some(skeleton) = skeletons.get(&current_skeleton.0) {
+            warp = warp.with_joint_limits(skeleton);
+        }
+        let handle = motion_warps.add(warp);
         player.play_warp(handle.clone());
         commands.insert_resource(CurrentMotionWarp(handle));
     }
 }   
 
+#[allow(clippy::too_many_arguments)]
 pub fn top_panel(
     mut contexts: EguiContexts,
     mode: Res<State<Mode>>,
     mut next_mode: ResMut<NextState<Mode>>,
+    clip_builder: Res<MotionWarpClipBuilder>,
+    current_skeleton: Res<CurrentSkeleton>,
+    skeletons: Res<Assets<Skeleton>>,
+    current_animation: Res<CurrentAnimation>,
+    mut animations: ResMut<Assets<AnimationClip>>,
+    mut player: Query<&mut AnimationPlayer>,
+    mut commands: Commands,
+    mut rebuild_ev: EventWriter<RebuildWarpClip>,
 ) {
     egui::TopBottomPanel::top(egui::Id::new(TOP_PANEL_ID)).show(contexts.ctx_mut(), |ui| {
         egui::menu::bar(ui, |ui| {
@@ -108,6 +130,46 @@ pub fn top_panel(
             if mode.0 == Mode::Keyframe {
                 let _ = ui.add_enabled(false, egui::Button::new("Keyframe"));
             }
+            if ui.button("Save warp").clicked() {
+                let saved = clip_builder
+                    .to_ron()
+                    .map_err(|err| err.to_string())
+                    .and_then(|ron| std::fs::write(WARP_DESCRIPTION_PATH, ron).map_err(|err| err.to_string()));
+                match saved {
+                    Ok(()) => info!("Saved warp description to {}", WARP_DESCRIPTION_PATH),
+                    Err(err) => warn!("Couldn't save warp description: {}", err),
+                }
+            }
+            let rig = skeletons.get(&current_skeleton.0);
+            if ui.add_enabled(rig.is_some(), egui::Button::new("Save joint limits")).clicked() {
+                let saved = rig
+                    .unwrap()
+                    .limits_to_ron()
+                    .map_err(|err| err.to_string())
+                    .and_then(|ron| std::fs::write(JOINT_LIMITS_PATH, ron).map_err(|err| err.to_string()));
+                match saved {
+                    Ok(()) => info!("Saved joint limits to {}", JOINT_LIMITS_PATH),
+                    Err(err) => warn!("Couldn't save joint limits: {}", err),
+                }
+            }
+            // The fox's joints have the same orientation on both sides, so they're mirrored with
+            // the rest pose of its skeleton
+            if ui.add_enabled(rig.is_some(), egui::Button::new("Mirror clip")).clicked() {
+                let mirror = PoseMirror::default().with_skeleton(rig.unwrap());
+                let mirrored = animations.get(&current_animation.0).map(|animation| animation.mirrored(&mirror));
+                if let Some(mirrored) = mirrored {
+                    let handle = animations.add(mirrored);
+                    if let Ok(mut player) = player.get_single_mut() {
+                        let elapsed = player.elapsed();
+                        player.play(handle.clone()).repeat().set_elapsed(elapsed);
+                    }
+                    commands.insert_resource(CurrentAnimation(handle));
+                    rebuild_ev.send(RebuildWarpClip);
+                }
+                else {
+                    warn!("Can't find animation clip.");
+                }
+            }
             
         });
     });
@@ -115,7 +177,7 @@ pub fn top_panel(
 
 pub fn timeline_panel(
     mut contexts: EguiContexts, 
-    mut player: Query<&mut AnimationPlayer>,
+    mut player: Query<(&mut AnimationPlayer, Option<&FootLocking>)>,
     clips: Res<Assets<AnimationClip>>,
     current_motion_warp: Option<Res<CurrentMotionWarp>>,
     mode: Res<State<Mode>>,
@@ -123,7 +185,7 @@ pub fn timeline_panel(
 ) {
     egui::TopBottomPanel::bottom(egui::Id::new(TIMELINE_PANEL_ID)).show(contexts.ctx_mut(), |ui| {
         ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
-            let Ok(mut player) = player.get_single_mut() else { return; };
+            let Ok((mut player, foot_locking)) = player.get_single_mut() else { return; };
 
             if player.is_paused() {
                 if ui.button("Play").clicked() {
@@ -154,18 +216,46 @@ pub fn timeline_panel(
 
             let mut elapsed = player.elapsed() % duration;
 
-            ui.style_mut().spacing.slider_width = ui.available_width();
-            if ui.add(egui::Slider::new(&mut elapsed, 0.0..=duration).show_value(true)).dragged() {
-                if mode.0 == Mode::Keyframe {
+            let width = ui.available_width();
+            ui.vertical(|ui| {
+                ui.style_mut().spacing.slider_width = width;
+                if ui.add(egui::Slider::new(&mut elapsed, 0.0..=duration).show_value(true)).dragged() && mode.0 == Mode::Keyframe {
                     next_mode.0 = Some(Mode::Preview);
+                };
+                if let Some(foot_locking) = foot_locking {
+                    foot_contacts(ui, foot_locking, width, duration, player.is_warped());
                 }
-            };
+            });
 
             player.set_elapsed(elapsed);
         });
     });
 }
 
+/// Draws a row under the timeline for each locked foot, filled where the foot touches the ground.
+fn foot_contacts(ui: &mut egui::Ui, foot_locking: &FootLocking, width: f32, duration: f32, warped: bool) {
+    const ROW_HEIGHT: f32 = 4.0;
+    let size = egui::vec2(width, ROW_HEIGHT * foot_locking.feet.len() as f32);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+    response.on_hover_text("Foot contacts, locked while warped");
+
+    let painter = ui.painter();
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(40));
+    let color = if warped { egui::Color32::from_rgb(90, 200, 120) } else { egui::Color32::from_gray(140) };
+    for (row, foot) in foot_locking.feet.iter().enumerate() {
+        let top = rect.top() + ROW_HEIGHT * row as f32;
+        for contact in foot.contacts() {
+            let x = |time: f32| rect.left() + width * (time / duration).clamp(0.0, 1.0);
+            // Contacts of a single frame still get a visible mark
+            let contact_rect = egui::Rect::from_min_max(
+                egui::pos2(x(contact.start), top + 0.5),
+                egui::pos2(x(contact.end).max(x(contact.start) + 1.0), top + ROW_HEIGHT - 0.5),
+            );
+            painter.rect_filled(contact_rect, 0.0, color);
+        }
+    }
+}
+
 pub fn keyframe_panel(
     mut contexts: EguiContexts, 
     mut clip_builder: ResMut<MotionWarpClipBuilder>,
@@ -207,6 +297,7 @@ pub fn keyframe_panel(
 }
 
 
+#[allow(clippy::too_many_arguments)]
 pub fn property_panel(
     mut contexts: EguiContexts, 
     mut clip_builder: ResMut<MotionWarpClipBuilder>,
@@ -214,8 +305,13 @@ pub fn property_panel(
     current_keyframe: Res<CurrentKeyframe>,
     animation_clips: Res<Assets<AnimationClip>>,
     mut joint_paths: Query<(&TrackedEntityPath, &mut Transform)>,
+    current_skeleton: Res<CurrentSkeleton>,
+    mut skeletons: ResMut<Assets<Skeleton>>,
     mut rebuild_ev: EventWriter<RebuildWarpClip>
 ) {
+    // Only borrow the skeleton mutably when a limit changes, which marks it as modified
+    let mut limit_changes: Vec<(usize, Option<JointLimit>)> = Vec::new();
+    let rig = skeletons.get(&current_skeleton.0);
     egui::SidePanel::left(egui::Id::new(PROPERTY_PANEL_ID)).show(contexts.ctx_mut(), |ui| {
         let Ok(player) = player.get_single() else { return; };
 
@@ -224,7 +320,8 @@ pub fn property_panel(
             start_time, 
             end_time, 
             blend_margin, 
-            tension 
+            tension,
+            boundary,
         } = &mut *clip_builder;
         let Some(clip_frame) = motion_clips.get_mut(current_keyframe.0) else { return; };
 
@@ -246,6 +343,15 @@ pub fn property_panel(
         ui.label("Tension:");
         rebuild |= ui.add(egui::DragValue::new(tension).speed(0.001).clamp_range(0.0..=1.0)).dragged();
 
+        ui.label("Boundary:");
+        egui::ComboBox::from_id_source("warp_boundary")
+            .selected_text(format!("{:?}", boundary))
+            .show_ui(ui, |ui| {
+                for option in [WarpBoundary::Cyclic, WarpBoundary::Clamped, WarpBoundary::Natural] {
+                    rebuild |= ui.selectable_value(boundary, option, format!("{:?}", option)).changed();
+                }
+            });
+
         ui.label(format!("Frame time: {:?}", clip_frame.time));
 
         ui.label("Warp time: ");
@@ -273,17 +379,15 @@ pub fn property_panel(
         ui.label("Add Joint");
         egui::ComboBox::from_label("").show_ui(ui, |ui| {
             for (path, transform) in joint_paths.iter() {
-                if !clip_frame.map.contains_key(&path.0) {
-                    if ui.button(format!("{:?}", path)).clicked() {
-                        rebuild = true;
-                        clip_frame.map.insert(
-                            path.0.clone(), 
-                            MotionWarpCurveFrame {
-                                rotation: transform.rotation,
-                                fix_a: true,
-                            }
-                        );
-                    }
+                if !clip_frame.map.contains_key(&path.0) && ui.button(format!("{:?}", path)).clicked() {
+                    rebuild = true;
+                    clip_frame.map.insert(
+                        path.0.clone(), 
+                        MotionWarpCurveFrame {
+                            rotation: transform.rotation,
+                            fix_a: true,
+                        }
+                    );
                 }
             }
         });
@@ -293,7 +397,12 @@ pub fn property_panel(
             let (mut x, mut y, mut z) = quat.to_euler(EulerRot::XYZ);
             let mut changed = false;
 
-            egui::CollapsingHeader::new(format!("{:?}", path)).show(ui, |ui| {
+            let joint = rig.and_then(|rig| Some((rig, rig.index_of(&path.0)?)));
+            let mut header = egui::RichText::new(format!("{:?}", path));
+            if joint.is_some_and(|(rig, index)| !rig.allows(index, quat)) {
+                header = header.color(egui::Color32::RED);
+            }
+            egui::CollapsingHeader::new(header).id_source(&path.0).show(ui, |ui| {
                 
                 ui.label("x rotation:");
                 let x_drag = ui.add(egui::DragValue::new(&mut x).speed(TAU/50.0));
@@ -308,6 +417,14 @@ pub fn property_panel(
                     changed = true;
                 }
 
+                if let Some((rig, index)) = joint {
+                    let mut limit = rig.limits()[index];
+                    if joint_limit_editor(ui, &mut limit, rig.allows(index, quat)) {
+                        limit_changes.push((index, limit));
+                        rebuild = true;
+                    }
+                }
+
                 rebuild |= changed;
 
                 ui.label("delete");
@@ -334,6 +451,126 @@ pub fn property_panel(
             rebuild_ev.send(RebuildWarpClip);
         }
     });
+
+    if !limit_changes.is_empty() {
+        if let Some(skeleton) = skeletons.get_mut(&current_skeleton.0) {
+            for (index, limit) in limit_changes {
+                skeleton.set_limit(index, limit);
+            }
+        }
+    }
+}
+
+/// Edits the limit of a joint, and flags its rotation if it isn't `allowed` by the limit. Returns
+/// whether the limit changed.
+fn joint_limit_editor(ui: &mut egui::Ui, joint_limit: &mut Option<JointLimit>, allowed: bool) -> bool {
+    let mut changed = false;
+    let mut limited = joint_limit.is_some();
+    if ui.checkbox(&mut limited, "limit").clicked() {
+        *joint_limit = limited.then(|| JointLimit { max_swing: PI / 4.0, min_twist: -PI / 4.0, max_twist: PI / 4.0, ..default() });
+        changed = true;
+    }
+    if let Some(limit) = joint_limit {
+        ui.label("max swing:");
+        changed |= ui.add(egui::DragValue::new(&mut limit.max_swing).speed(0.01).clamp_range(0.0..=PI)).dragged();
+        ui.label("twist range:");
+        ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
+            changed |= ui.add(egui::DragValue::new(&mut limit.min_twist).speed(0.01).clamp_range(-PI..=0.0)).dragged();
+            changed |= ui.add(egui::DragValue::new(&mut limit.max_twist).speed(0.01).clamp_range(0.0..=PI)).dragged();
+        });
+    }
+    if !allowed {
+        ui.colored_label(egui::Color32::RED, "Outside of the joint limit; the warp is clamped");
+    }
+    changed
+}
+
+/// Draws the limit cones of the joints warped by the current keyframe, with the direction of each
+/// joint in green, or in red when it's outside of its limit.
+#[allow(clippy::too_many_arguments)]
+pub fn joint_limit_overlay(
+    mut contexts: EguiContexts,
+    current_skeleton: Res<CurrentSkeleton>,
+    skeletons: Res<Assets<Skeleton>>,
+    clip_builder: Res<MotionWarpClipBuilder>,
+    current_keyframe: Res<CurrentKeyframe>,
+    camera: Query<(&Camera, &GlobalTransform), With<PrimaryCamera>>,
+    joints: Query<(&TrackedEntityPath, &Transform, &GlobalTransform, Option<&Children>)>,
+    global_transforms: Query<&GlobalTransform>,
+) {
+    const SEGMENTS: usize = 24;
+    let Ok((camera, camera_transform)) = camera.get_single() else { return; };
+    let Some(clip_frame) = clip_builder.clips.get(current_keyframe.0) else { return; };
+    let Some(skeleton) = skeletons.get(&current_skeleton.0) else { return; };
+    let Some(viewport_size) = camera.logical_viewport_size() else { return; };
+    let to_screen = |position: Vec3| {
+        camera
+            .world_to_viewport(camera_transform, position)
+            .map(|position| egui::pos2(position.x, viewport_size.y - position.y))
+    };
+
+    let painter = contexts.ctx_mut().layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("joint_limits")));
+    for (path, transform, global_transform, children) in &joints {
+        if !clip_frame.map.contains_key(&path.0) {
+            continue;
+        }
+        let Some(index) = skeleton.index_of(&path.0) else { continue; };
+        let Some(limit) = skeleton.limits()[index] else { continue; };
+
+        let (_, rotation, apex) = global_transform.to_scale_rotation_translation();
+        let parent_rotation = rotation * transform.rotation.inverse();
+        // Draw the cone as long as the bone
+        let length = children
+            .and_then(|children| global_transforms.get(*children.first()?).ok())
+            .map_or(10.0, |child| child.translation().distance(apex))
+            .max(1.0);
+
+        let rest_rotation = skeleton.rest_transforms()[index].rotation;
+        let axis = (parent_rotation * rest_rotation * limit.twist_axis).normalize();
+        let (u, v) = axis.any_orthonormal_pair();
+        let (sin, cos) = limit.max_swing.min(PI).sin_cos();
+        let rim: Vec<Option<egui::Pos2>> = (0..=SEGMENTS)
+            .map(|i| {
+                let angle = TAU * i as f32 / SEGMENTS as f32;
+                to_screen(apex + length * (cos * axis + sin * (angle.cos() * u + angle.sin() * v)))
+            })
+            .collect();
+
+        let cone_stroke = egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(255, 200, 60, 160));
+        let Some(apex_position) = to_screen(apex) else { continue; };
+        for segment in rim.windows(2) {
+            if let [Some(a), Some(b)] = segment {
+                painter.line_segment([*a, *b], cone_stroke);
+            }
+        }
+        for rim_position in rim.iter().step_by(SEGMENTS / 4).flatten() {
+            painter.line_segment([apex_position, *rim_position], cone_stroke);
+        }
+
+        let color = if skeleton.allows(index, transform.rotation) { egui::Color32::GREEN } else { egui::Color32::RED };
+        if let Some(direction) = to_screen(apex + rotation * limit.twist_axis.normalize() * length) {
+            painter.line_segment([apex_position, direction], egui::Stroke::new(2.0, color));
+        }
+    }
+}
+
+/// Loads the saved joint limits into the current skeleton once it's loaded, if they were saved.
+pub fn load_joint_limits(
+    current_skeleton: Res<CurrentSkeleton>,
+    mut skeletons: ResMut<Assets<Skeleton>>,
+    mut skeleton_events: EventReader<AssetEvent<Skeleton>>,
+) {
+    for event in skeleton_events.iter() {
+        let AssetEvent::Created { handle } = event else { continue; };
+        if *handle != current_skeleton.0 {
+            continue;
+        }
+        let Ok(limits) = std::fs::read_to_string(JOINT_LIMITS_PATH) else { continue; };
+        let Some(skeleton) = skeletons.get_mut(handle) else { continue; };
+        if let Err(err) = skeleton.load_limits_ron(&limits) {
+            warn!("Couldn't load joint limits: {}", err);
+        }
+    }
 }
 
 // TODO: add settings
@@ -380,6 +617,7 @@ pub fn setup(mut commands: Commands) {
 // TODO: allow for models/animations to be loaded dynamically.
 pub fn debug_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.insert_resource(CurrentAnimation(asset_server.load("Fox.glb#Animation2")));
+    commands.insert_resource(CurrentSkeleton(asset_server.load("Fox.glb#Skeleton0")));
 
     commands.spawn(SceneBundle {
         scene: asset_server.load("Fox.glb#Scene0"),
@@ -389,13 +627,41 @@ pub fn debug_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
 
 pub fn play_once_loaded(
     animation: Res<CurrentAnimation>,
-    mut player: Query<&mut AnimationPlayer>,
+    skeleton: Res<CurrentSkeleton>,
+    mut commands: Commands,
+    mut player: Query<(Entity, &mut AnimationPlayer)>,
     mut done: Local<bool>,
 ) {
     if !*done {
-        if let Ok(mut player) = player.get_single_mut() {
+        if let Ok((entity, mut player)) = player.get_single_mut() {
             player.play(animation.0.clone_weak()).repeat();
+            commands.entity(entity).insert(fox_foot_locking().with_skeleton(skeleton.0.clone()));
             *done = true;
         }
     }
 }
+
+// TODO: remove along with debug_setup once models are loaded dynamically.
+fn fox_foot_locking() -> FootLocking {
+    let path = |parts: &[&str]| {
+        let hip = ["root", "_rootJoint", "b_Root_00", "b_Hip_01"];
+        EntityPath { parts: hip.iter().chain(parts).map(|part| Name::new(part.to_string())).collect() }
+    };
+    let leg = |joints: [&str; 3]| LockedFoot::new(path(&joints[..1]), path(&joints[..2]), path(&joints));
+    let arm = |joints: [&str; 3]| {
+        let joints = ["b_Spine01_02", "b_Spine02_03", joints[0], joints[1], joints[2]];
+        LockedFoot::new(path(&joints[..3]), path(&joints[..4]), path(&joints))
+    };
+    // The Fox is about 60 units tall. Its clips don't move its root, so planted feet slide back as
+    // fast as it moves, up to 190 units per second in its run, and only paws sweeping forward near
+    // the ground are faster.
+    FootLocking::new(
+        vec![
+            leg(["b_LeftLeg01_015", "b_LeftLeg02_016", "b_LeftFoot01_017"]),
+            leg(["b_RightLeg01_019", "b_RightLeg02_020", "b_RightFoot01_021"]),
+            arm(["b_LeftUpperArm_09", "b_LeftForeArm_010", "b_LeftHand_011"]),
+            arm(["b_RightUpperArm_06", "b_RightForeArm_07", "b_RightHand_08"]),
+        ],
+        ContactSettings { max_height: 3.0, max_speed: 250.0, frame_rate: 30.0 },
+    )
+}
@@ -0,0 +1,356 @@
+//! Detecting when feet touch the ground in [`AnimationClip`]s, and keeping them planted while
+//! the animation is warped.
+
+use std::ops::Range;
+
+use bevy::asset::{Assets, Handle};
+use bevy::core::Name;
+use bevy::ecs::prelude::*;
+use bevy::hierarchy::{Children, Parent};
+use bevy::math::Vec3;
+use bevy::reflect::{FromReflect, Reflect};
+use bevy::time::Time;
+use bevy::transform::components::Transform;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::ik::{bend_two_bones, global_transform, JointBindings};
+use crate::{bevy_animation::find_bone, AnimationClip, AnimationPlayer, EntityPath, Skeleton};
+
+/// Thresholds under which a foot is considered to touch the ground.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct ContactSettings {
+    /// Largest height of the foot above its lowest point in the clip, in the space of the
+    /// animation root.
+    pub max_height: f32,
+    /// Largest speed of the foot, in units per second.
+    pub max_speed: f32,
+    /// Rate at which the clip is sampled, in frames per second.
+    pub frame_rate: f32,
+}
+
+impl Default for ContactSettings {
+    fn default() -> Self {
+        ContactSettings { max_height: 0.03, max_speed: 0.3, frame_rate: 30.0 }
+    }
+}
+
+impl AnimationClip {
+    /// Position of the joint at `path` at `time`, relative to the first joint of the path.
+    ///
+    /// Properties that the clip doesn't animate are taken from the local transforms in `rest`,
+    /// or are the identity if the joint isn't in `rest`.
+    pub fn model_position(&self, path: &EntityPath, rest: &HashMap<EntityPath, Transform>, time: f32) -> Vec3 {
+        let mut position = Vec3::ZERO;
+        let mut parent = Transform::IDENTITY;
+        for length in 2..=path.parts.len() {
+            let joint_path = EntityPath { parts: path.parts[..length].to_vec() };
+            let mut local = rest.get(&joint_path).copied().unwrap_or_default();
+            if let Some(joint) = self.paths().get(&joint_path).and_then(|id| self.sample_joint(*id, time)) {
+                joint.apply(&mut local, 1.0);
+            }
+            parent = parent.mul_transform(local);
+            position = parent.translation;
+        }
+        position
+    }
+
+    /// Intervals of time during which the joint at `foot` touches the ground, from its height and
+    /// speed in the space of the animation root.
+    pub fn foot_contacts(
+        &self,
+        foot: &EntityPath,
+        rest: &HashMap<EntityPath, Transform>,
+        settings: &ContactSettings,
+    ) -> Vec<Range<f32>> {
+        // Curves have ended at the duration, so the last frame is just before it
+        let duration = self.duration();
+        let frames = ((duration * settings.frame_rate).ceil() as usize).max(1);
+        let times: Vec<f32> = (0..frames).map(|frame| duration * frame as f32 / frames as f32).collect();
+        let positions: Vec<Vec3> = times.iter().map(|time| self.model_position(foot, rest, *time)).collect();
+        let ground = positions.iter().map(|position| position.y).fold(f32::INFINITY, f32::min);
+
+        let mut contacts: Vec<Range<f32>> = Vec::new();
+        for (frame, time) in times.iter().enumerate() {
+            let (previous, next) = (frame.saturating_sub(1), (frame + 1).min(frames - 1));
+            let speed = if previous != next {
+                positions[previous].distance(positions[next]) / (times[next] - times[previous])
+            } else {
+                0.0
+            };
+            if positions[frame].y - ground > settings.max_height || speed > settings.max_speed {
+                continue;
+            }
+            match contacts.last_mut() {
+                Some(contact) if frame > 0 && contact.end == times[frame - 1] => contact.end = *time,
+                _ => contacts.push(*time..*time),
+            }
+        }
+        contacts
+    }
+}
+
+/// A leg whose foot is kept planted by [`FootLocking`].
+#[derive(Clone, Debug, Reflect, FromReflect)]
+pub struct LockedFoot {
+    /// Path to the top joint of the leg.
+    pub hip: EntityPath,
+    /// Path to the middle joint of the leg.
+    pub knee: EntityPath,
+    /// Path to the foot, whose contacts with the ground are detected.
+    pub ankle: EntityPath,
+    #[reflect(ignore)]
+    contacts: Vec<Range<f32>>,
+    #[reflect(ignore)]
+    in_contact: bool,
+    #[reflect(ignore)]
+    lock: Option<Vec3>,
+    #[reflect(ignore)]
+    weight: f32,
+    #[reflect(ignore)]
+    bindings: JointBindings,
+}
+
+impl LockedFoot {
+    /// A leg going through three joints.
+    pub fn new(hip: EntityPath, knee: EntityPath, ankle: EntityPath) -> Self {
+        LockedFoot {
+            hip,
+            knee,
+            ankle,
+            contacts: Vec::new(),
+            in_contact: false,
+            lock: None,
+            weight: 0.0,
+            bindings: JointBindings::default(),
+        }
+    }
+
+    /// Intervals of time during which the foot touches the ground in the playing clip.
+    pub fn contacts(&self) -> &[Range<f32>] {
+        &self.contacts
+    }
+
+    /// Is the foot locked?
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    /// Locks the foot at `position` when a contact starts, even if it's still being released from
+    /// the last one, and releases it by `release` once the contact ends.
+    fn update_lock(&mut self, contact: bool, release: f32, position: impl FnOnce() -> Vec3) {
+        if contact {
+            if !self.in_contact {
+                self.lock = Some(position());
+            }
+            self.weight = 1.0;
+        } else if self.lock.is_some() {
+            self.weight -= release;
+            if self.weight <= 0.0 {
+                self.lock = None;
+            }
+        }
+        self.in_contact = contact;
+    }
+}
+
+/// Keeps the feet of the [`AnimationPlayer`] on the same entity planted while they touch the
+/// ground in its clip and a [`crate::MotionWarpClip`] is playing.
+///
+/// Contacts are detected in the clip when it starts playing. While a foot touches the ground, the
+/// leg is bent so that the foot stays where it was when the contact started, and it's released
+/// over `release_time` seconds afterwards. A contact starting during a release locks the foot
+/// where it is again.
+///
+/// Paths start with the name of this entity, and are resolved like those of
+/// [`crate::TwoBoneIk`]. The properties of the joints above the feet that the clip doesn't animate
+/// are taken from the rest pose of the skeleton given with [`FootLocking::with_skeleton`], or else
+/// from the joints' local transforms when the clip starts, which may still hold the pose of the
+/// previous clip.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct FootLocking {
+    /// Legs whose feet are locked.
+    pub feet: Vec<LockedFoot>,
+    /// Thresholds used to detect contacts.
+    pub settings: ContactSettings,
+    /// Seconds taken to release a foot after its contact ends.
+    pub release_time: f32,
+    #[reflect(ignore)]
+    skeleton: Option<Handle<Skeleton>>,
+    #[reflect(ignore)]
+    clip: Option<Handle<AnimationClip>>,
+}
+
+impl Default for FootLocking {
+    fn default() -> Self {
+        FootLocking {
+            feet: Vec::new(),
+            settings: ContactSettings::default(),
+            release_time: 0.2,
+            skeleton: None,
+            clip: None,
+        }
+    }
+}
+
+impl FootLocking {
+    /// Feet locked with `settings`.
+    pub fn new(feet: Vec<LockedFoot>, settings: ContactSettings) -> Self {
+        FootLocking { feet, settings, ..Default::default() }
+    }
+
+    /// These feet, with contacts detected from the rest pose of `skeleton`. Feet aren't locked
+    /// until it's loaded.
+    pub fn with_skeleton(mut self, skeleton: Handle<Skeleton>) -> Self {
+        self.skeleton = Some(skeleton);
+        self
+    }
+
+    /// Detects the contacts and resolves the joints again the next time the feet are locked,
+    /// such as after the settings, the clip or the paths changed.
+    pub fn reset(&mut self) {
+        self.clip = None;
+        self.rebind();
+    }
+
+    /// Resolves the joints of the legs again the next time the feet are locked.
+    pub(crate) fn rebind(&mut self) {
+        for foot in &mut self.feet {
+            foot.bindings.reset();
+        }
+    }
+}
+
+/// System that detects the contacts of every [`FootLocking`], and bends legs to keep their feet
+/// planted during contacts while their animation is warped.
+///
+/// It runs after [`crate::apply_animation_poses`] and before the IK solvers.
+#[allow(clippy::too_many_arguments)]
+pub fn lock_feet(
+    time: Res<Time>,
+    animations: Res<Assets<AnimationClip>>,
+    skeletons: Res<Assets<Skeleton>>,
+    mut lockings: Query<(Entity, &AnimationPlayer, &mut FootLocking)>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+    parents: Query<&Parent>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for (root, player, mut locking) in &mut lockings {
+        let locking = locking.bypass_change_detection();
+        let Some(clip) = animations.get(player.animation_clip()) else { continue };
+
+        if locking.clip.as_ref() != Some(player.animation_clip()) {
+            let skeleton = match &locking.skeleton {
+                Some(skeleton) => match skeletons.get(skeleton) {
+                    Some(skeleton) => Some(skeleton),
+                    None => continue,
+                },
+                None => None,
+            };
+            // Joints above the feet, for the properties the clip doesn't animate
+            let paths = locking.feet.iter().flat_map(|foot| {
+                (2..=foot.ankle.parts.len()).map(|length| EntityPath { parts: foot.ankle.parts[..length].to_vec() })
+            });
+            let rest: HashMap<EntityPath, Transform> = paths
+                .filter_map(|path| {
+                    let transform = match skeleton {
+                        Some(skeleton) => skeleton.rest_transforms()[skeleton.index_of(&path)?],
+                        None => *transforms.get(find_bone(root, &path, &children, &names)?).ok()?,
+                    };
+                    Some((path, transform))
+                })
+                .collect();
+            for foot in &mut locking.feet {
+                foot.contacts = clip.foot_contacts(&foot.ankle, &rest, &locking.settings);
+                foot.in_contact = false;
+                foot.lock = None;
+            }
+            locking.clip = Some(player.animation_clip().clone_weak());
+        }
+
+        let clip_time = if player.is_repeating() {
+            player.elapsed().rem_euclid(clip.duration())
+        } else {
+            player.elapsed()
+        };
+        let release = if locking.release_time > 0.0 { time.delta_seconds() / locking.release_time } else { 1.0 };
+        for foot in &mut locking.feet {
+            let paths = [&foot.hip, &foot.knee, &foot.ankle];
+            let Some(&[hip, knee, ankle]) = foot.bindings.get_or_bind(root, paths, &children, &names) else {
+                continue;
+            };
+
+            let contact = player.is_warped()
+                && foot.contacts.iter().any(|contact| contact.start <= clip_time && clip_time <= contact.end);
+            foot.update_lock(contact, release, || global_transform(ankle, &parents, &transforms).translation());
+            if let Some(lock) = foot.lock {
+                bend_two_bones([hip, knee, ankle], lock, Vec3::ZERO, foot.weight, &parents, &mut transforms);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Keyframes, VariableCurve};
+
+    fn path(parts: &[&str]) -> EntityPath {
+        EntityPath { parts: parts.iter().map(|part| Name::new(part.to_string())).collect() }
+    }
+
+    /// A foot stepping every second: planted for the first half, lifted for the second.
+    fn stepping_clip() -> AnimationClip {
+        let mut clip = AnimationClip::default();
+        let timestamps: Vec<f32> = (0..=8).map(|frame| frame as f32 * 0.25).collect();
+        let heights = [0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.5, 0.0];
+        let translations = timestamps
+            .iter()
+            .zip(heights)
+            .map(|(time, height)| Vec3::new(0.0, height, if height > 0.0 { *time } else { (*time).floor() }))
+            .collect();
+        clip.add_curve_to_path(
+            path(&["root", "foot"]),
+            VariableCurve { keyframe_timestamps: timestamps, keyframes: Keyframes::Translation(translations) },
+        );
+        clip
+    }
+
+    #[test]
+    fn detects_contacts() {
+        let clip = stepping_clip();
+        let mut rest = HashMap::new();
+        rest.insert(path(&["root", "foot"]), Transform::from_xyz(5.0, 1.0, 0.0));
+        assert_eq!(clip.model_position(&path(&["root", "foot"]), &rest, 0.75), Vec3::new(0.0, 0.5, 0.75));
+
+        let settings = ContactSettings { max_height: 0.05, max_speed: 0.1, frame_rate: 20.0 };
+        let contacts = clip.foot_contacts(&path(&["root", "foot"]), &rest, &settings);
+        assert_eq!(contacts.len(), 2, "{contacts:?}");
+        // Within a frame of the steps, whose speed is measured over two frames
+        for (contact, start) in contacts.iter().zip([0.0, 1.0]) {
+            assert!((contact.start - start).abs() < 0.06 && (contact.end - start - 0.5).abs() < 0.06, "{contacts:?}");
+        }
+    }
+
+    #[test]
+    fn relocks_new_contacts() {
+        let mut foot = LockedFoot::new(path(&["hip"]), path(&["hip", "knee"]), path(&["hip", "knee", "ankle"]));
+        foot.update_lock(true, 0.25, || Vec3::X);
+        foot.update_lock(true, 0.25, || Vec3::Y);
+        assert_eq!(foot.lock, Some(Vec3::X));
+
+        // Released over four frames, but a contact starting halfway locks the foot where it is
+        foot.update_lock(false, 0.25, || Vec3::Y);
+        foot.update_lock(false, 0.25, || Vec3::Y);
+        assert_eq!((foot.lock, foot.weight), (Some(Vec3::X), 0.5));
+        foot.update_lock(true, 0.25, || Vec3::Z);
+        assert_eq!((foot.lock, foot.weight), (Some(Vec3::Z), 1.0));
+
+        for _ in 0..4 {
+            foot.update_lock(false, 0.25, || Vec3::Y);
+        }
+        assert!(!foot.is_locked());
+    }
+}
@@ -0,0 +1,377 @@
+//! Writes [`AnimationClip`]s back out as glTF animations.
+
+use std::borrow::Cow;
+
+use bevy::log::warn;
+use bevy::utils::HashMap;
+use gltf::binary::{Glb, Header};
+use gltf::json::{
+    self,
+    accessor::{ComponentType, GenericComponentType, Type},
+    animation::{Channel, Interpolation, Property, Sampler, Target},
+    validation::{Checked::Valid, USize64},
+    Index,
+};
+use thiserror::Error;
+
+use crate::{node_paths, AnimationClip, EntityPath, Keyframes};
+
+/// Options for [`export_animation_glb`].
+#[derive(Clone, Debug, Default)]
+pub struct GltfExportOptions {
+    /// Name given to the exported glTF animation.
+    pub animation_name: Option<String>,
+    /// Emit a full copy of the source glTF (meshes, skins, materials, ...) with the
+    /// animation appended, instead of only the node hierarchy and the animation.
+    pub copy_source: bool,
+}
+
+/// An error that occurs when exporting a glTF file.
+#[derive(Error, Debug)]
+pub enum GltfExportError {
+    #[error("invalid glTF file: {0}")]
+    Gltf(#[from] gltf::Error),
+    #[error("failed to serialize glTF json: {0}")]
+    Json(#[from] json::Error),
+    #[error("animation has no channels matching nodes of the source glTF")]
+    NoMatchingChannels,
+    #[error("animated path {0:?} matches several nodes of the source glTF")]
+    AmbiguousPath(EntityPath),
+}
+
+/// Exports `clip` as a binary glTF animation whose channels target the nodes of `source`.
+///
+/// Channels are matched to nodes by [`EntityPath`], in the same way as [`crate::GltfLoader`]
+/// names them, so `source` should be the file the clip was originally loaded from. Baked
+/// warped clips (see [`crate::MotionWarpClip::bake`]) can be exported the same way.
+///
+/// Fails with [`GltfExportError::AmbiguousPath`] if an animated path matches sibling nodes with
+/// the same name, since the channel can't be assigned to one of them.
+pub fn export_animation_glb(
+    source: &[u8],
+    clip: &AnimationClip,
+    options: &GltfExportOptions,
+) -> Result<Vec<u8>, GltfExportError> {
+    let gltf = gltf::Gltf::from_slice(source)?;
+    // Siblings with the same name have the same path, so a path can match several nodes
+    let mut node_indices = HashMap::<EntityPath, Vec<usize>>::new();
+    for (node_index, (_, parts)) in node_paths(&gltf) {
+        node_indices.entry(EntityPath { parts }).or_default().push(node_index);
+    }
+    let blob = gltf.blob.clone();
+
+    let (mut root, mut bin) = if options.copy_source {
+        copy_root(gltf.document.into_json(), blob)
+    } else {
+        (hierarchy_root(gltf.document.into_json()), Vec::new())
+    };
+
+    let buffer = Index::new(0);
+    let mut animation = json::Animation {
+        extensions: None,
+        extras: Default::default(),
+        channels: Vec::new(),
+        name: options.animation_name.clone(),
+        samplers: Vec::new(),
+    };
+
+    // Sort by node so that exports are deterministic
+    let mut paths = Vec::new();
+    for (path, bone_id) in clip.paths() {
+        match node_indices.get(path).map(Vec::as_slice) {
+            Some(&[node_index]) => paths.push((node_index, *bone_id)),
+            Some(_) => return Err(GltfExportError::AmbiguousPath(path.clone())),
+            None => warn!("Animation ignored for path {:?}: no matching node in the source glTF", path),
+        }
+    }
+    paths.sort_unstable();
+
+    for (node_index, bone_id) in paths {
+        for curve in clip.get_curves(bone_id).unwrap() {
+            if curve.keyframe_timestamps.is_empty() {
+                continue;
+            }
+            let (property, type_, values): (_, _, Vec<f32>) = match curve.keyframes.decompressed() {
+                Keyframes::Translation(keyframes) => (
+                    Property::Translation,
+                    Type::Vec3,
+                    keyframes.iter().flat_map(|v| v.to_array()).collect(),
+                ),
+                Keyframes::Rotation(keyframes) => (
+                    Property::Rotation,
+                    Type::Vec4,
+                    keyframes.iter().flat_map(|q| q.normalize().to_array()).collect(),
+                ),
+                Keyframes::Scale(keyframes) => (
+                    Property::Scale,
+                    Type::Vec3,
+                    keyframes.iter().flat_map(|v| v.to_array()).collect(),
+                ),
+                Keyframes::CompressedRotation(_)
+                | Keyframes::CompressedTranslation(_)
+                | Keyframes::CompressedScale(_) => unreachable!("keyframes are decompressed"),
+            };
+
+            let min = curve.keyframe_timestamps.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = curve.keyframe_timestamps.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let input = push_accessor(
+                &mut root,
+                &mut bin,
+                buffer,
+                &curve.keyframe_timestamps,
+                Type::Scalar,
+                Some((min, max)),
+            );
+            let output = push_accessor(&mut root, &mut bin, buffer, &values, type_, None);
+
+            let sampler = Index::push(
+                &mut animation.samplers,
+                Sampler {
+                    extensions: None,
+                    extras: Default::default(),
+                    input,
+                    interpolation: Valid(Interpolation::Linear),
+                    output,
+                },
+            );
+            animation.channels.push(Channel {
+                sampler,
+                target: Target {
+                    extensions: None,
+                    extras: Default::default(),
+                    node: Index::new(node_index as u32),
+                    path: Valid(property),
+                },
+                extensions: None,
+                extras: Default::default(),
+            });
+        }
+    }
+
+    if animation.channels.is_empty() {
+        return Err(GltfExportError::NoMatchingChannels);
+    }
+    root.push(animation);
+
+    align_to_four(&mut bin);
+    root.buffers[0].byte_length = USize64::from(bin.len());
+
+    let json = root.to_vec()?;
+    let glb = Glb {
+        header: Header {
+            magic: *b"glTF",
+            version: 2,
+            // Computed when writing
+            length: 0,
+        },
+        json: Cow::Owned(json),
+        bin: Some(Cow::Owned(bin)),
+    };
+    Ok(glb.to_vec()?)
+}
+
+/// The source document, with its binary buffer (if any) as the first buffer.
+fn copy_root(mut root: json::Root, blob: Option<Vec<u8>>) -> (json::Root, Vec<u8>) {
+    let has_blob = root.buffers.first().is_some_and(|buffer| buffer.uri.is_none());
+    if !has_blob {
+        // The binary chunk of a GLB must be the first buffer
+        for view in &mut root.buffer_views {
+            view.buffer = Index::new(view.buffer.value() as u32 + 1);
+        }
+        root.buffers.insert(0, empty_buffer());
+    }
+    let bin = if has_blob { blob.unwrap_or_default() } else { Vec::new() };
+    (root, bin)
+}
+
+/// Only the nodes and scenes of the source document, so node indices are kept.
+fn hierarchy_root(source: json::Root) -> json::Root {
+    let nodes = source
+        .nodes
+        .into_iter()
+        .map(|node| json::Node {
+            children: node.children,
+            matrix: node.matrix,
+            name: node.name,
+            rotation: node.rotation,
+            scale: node.scale,
+            translation: node.translation,
+            ..Default::default()
+        })
+        .collect();
+    let scenes = source
+        .scenes
+        .into_iter()
+        .map(|scene| json::Scene {
+            extensions: None,
+            extras: Default::default(),
+            name: scene.name,
+            nodes: scene.nodes,
+        })
+        .collect();
+
+    json::Root {
+        asset: json::Asset {
+            generator: Some("motion_warp".to_string()),
+            ..Default::default()
+        },
+        buffers: vec![empty_buffer()],
+        nodes,
+        scenes,
+        scene: source.scene,
+        ..Default::default()
+    }
+}
+
+fn empty_buffer() -> json::Buffer {
+    json::Buffer {
+        byte_length: USize64(0),
+        name: None,
+        uri: None,
+        extensions: None,
+        extras: Default::default(),
+    }
+}
+
+fn align_to_four(bin: &mut Vec<u8>) {
+    bin.resize((bin.len() + 3) & !3, 0);
+}
+
+/// Appends float `values` to `bin` and adds an accessor for them.
+fn push_accessor(
+    root: &mut json::Root,
+    bin: &mut Vec<u8>,
+    buffer: Index<json::Buffer>,
+    values: &[f32],
+    type_: Type,
+    bounds: Option<(f32, f32)>,
+) -> Index<json::Accessor> {
+    align_to_four(bin);
+    let byte_offset = bin.len();
+    bin.extend(values.iter().flat_map(|v| v.to_le_bytes()));
+
+    let components = match type_ {
+        Type::Scalar => 1,
+        Type::Vec3 => 3,
+        Type::Vec4 => 4,
+        _ => unreachable!("animations only use scalars, vec3s and vec4s"),
+    };
+
+    let buffer_view = root.push(json::buffer::View {
+        buffer,
+        byte_length: USize64::from(values.len() * 4),
+        byte_offset: Some(USize64::from(byte_offset)),
+        byte_stride: None,
+        name: None,
+        target: None,
+        extensions: None,
+        extras: Default::default(),
+    });
+    root.push(json::Accessor {
+        buffer_view: Some(buffer_view),
+        byte_offset: None,
+        count: USize64::from(values.len() / components),
+        component_type: Valid(GenericComponentType(ComponentType::F32)),
+        extensions: None,
+        extras: Default::default(),
+        type_: Valid(type_),
+        min: bounds.map(|(min, _)| json::Value::from(vec![min])),
+        max: bounds.map(|(_, max)| json::Value::from(vec![max])),
+        name: None,
+        normalized: false,
+        sparse: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{core::Name, math::Quat};
+
+    use super::*;
+    use crate::VariableCurve;
+
+    const FOX: &[u8] = include_bytes!("../assets/Fox.glb");
+
+    fn root_path() -> EntityPath {
+        let gltf = gltf::Gltf::from_slice(FOX).unwrap();
+        let (_, (_, parts)) = node_paths(&gltf)
+            .into_iter()
+            .find(|(_, (_, parts))| parts.len() == 2)
+            .unwrap();
+        EntityPath { parts }
+    }
+
+    fn test_clip(path: EntityPath) -> AnimationClip {
+        let mut clip = AnimationClip::default();
+        clip.add_curve_to_path(
+            path,
+            VariableCurve {
+                keyframe_timestamps: vec![0.0, 0.5, 1.0],
+                keyframes: Keyframes::Rotation(vec![
+                    Quat::IDENTITY,
+                    Quat::from_rotation_y(1.0),
+                    Quat::IDENTITY,
+                ]),
+            },
+        );
+        clip
+    }
+
+    #[test]
+    fn export_targets_original_node() {
+        let path = root_path();
+        let bytes = export_animation_glb(FOX, &test_clip(path.clone()), &Default::default()).unwrap();
+        let exported = gltf::Gltf::from_slice(&bytes).unwrap();
+        let source = gltf::Gltf::from_slice(FOX).unwrap();
+
+        assert_eq!(exported.nodes().len(), source.nodes().len());
+        assert_eq!(exported.meshes().len(), 0);
+
+        let animation = exported.animations().next().unwrap();
+        let channel = animation.channels().next().unwrap();
+        let node = channel.target().node();
+        assert_eq!(Some(path.parts.last().unwrap().as_str()), node.name());
+
+        let reader = channel.reader(|_| exported.blob.as_deref());
+        let times: Vec<f32> = match reader.read_inputs().unwrap() {
+            gltf::accessor::Iter::Standard(times) => times.collect(),
+            gltf::accessor::Iter::Sparse(_) => panic!("exported sparse accessor"),
+        };
+        assert_eq!(times, vec![0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn export_copy_keeps_source() {
+        let options = GltfExportOptions {
+            animation_name: Some("Exported".to_string()),
+            copy_source: true,
+        };
+        let bytes = export_animation_glb(FOX, &test_clip(root_path()), &options).unwrap();
+        let exported = gltf::Gltf::from_slice(&bytes).unwrap();
+        let source = gltf::Gltf::from_slice(FOX).unwrap();
+
+        assert_eq!(exported.meshes().len(), source.meshes().len());
+        assert_eq!(exported.animations().len(), source.animations().len() + 1);
+        assert_eq!(exported.animations().last().unwrap().name(), Some("Exported"));
+    }
+
+    #[test]
+    fn export_unmatched_clip() {
+        let path = EntityPath { parts: vec![Name::new("not a node")] };
+        let result = export_animation_glb(FOX, &test_clip(path), &Default::default());
+        assert!(matches!(result, Err(GltfExportError::NoMatchingChannels)));
+    }
+
+    #[test]
+    fn export_ambiguous_path() {
+        let json = serde_json::json!({
+            "asset": { "version": "2.0" },
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "name": "root", "children": [1, 2] }, { "name": "twin" }, { "name": "twin" }],
+        });
+        let source = serde_json::to_vec(&json).unwrap();
+        let path = EntityPath { parts: vec![Name::new("root"), Name::new("twin")] };
+        let result = export_animation_glb(&source, &test_clip(path), &Default::default());
+        assert!(matches!(result, Err(GltfExportError::AmbiguousPath(_))));
+    }
+}
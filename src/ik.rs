@@ -0,0 +1,738 @@
+//! Inverse kinematics, correcting sampled animations so that feet and hands reach their targets.
+
+use std::f32::consts::PI;
+
+use bevy::core::Name;
+use bevy::ecs::prelude::*;
+use bevy::hierarchy::{Children, Parent};
+use bevy::math::{Affine3A, Quat, Vec3};
+use bevy::reflect::{FromReflect, Reflect};
+use bevy::transform::components::{GlobalTransform, Transform};
+use serde::{Deserialize, Serialize};
+
+use crate::{bevy_animation::find_bone, AimConstraint, EntityPath, FootLocking, HierarchyChanges};
+
+/// Bones shorter than this aren't bent.
+const MIN_LENGTH: f32 = 1e-4;
+
+/// A chain of two bones, such as a leg or an arm, bent so that its tip reaches a target.
+#[derive(Clone, Debug, Reflect, FromReflect)]
+pub struct TwoBoneIkChain {
+    /// Path to the first joint of the chain, such as a hip or a shoulder.
+    pub root: EntityPath,
+    /// Path to the middle joint, such as a knee or an elbow.
+    pub middle: EntityPath,
+    /// Path to the tip of the chain, such as an ankle or a wrist.
+    pub tip: EntityPath,
+    /// Position the tip reaches for, in world space.
+    pub target: Vec3,
+    /// Direction the middle joint bends towards, in world space. The animated bend is kept if
+    /// the pole is zero or along the chain.
+    pub pole: Vec3,
+    /// How much of the correction is applied, from 0 (the animated pose) to 1.
+    pub weight: f32,
+    #[reflect(ignore)]
+    bindings: JointBindings,
+}
+
+impl TwoBoneIkChain {
+    /// A chain through three joints, keeping the animated bend. It has no effect until it's given
+    /// a target and a weight.
+    pub fn new(root: EntityPath, middle: EntityPath, tip: EntityPath) -> Self {
+        TwoBoneIkChain {
+            root,
+            middle,
+            tip,
+            target: Vec3::ZERO,
+            pole: Vec3::ZERO,
+            weight: 0.0,
+            bindings: JointBindings::default(),
+        }
+    }
+}
+
+/// Two bone IK chains of the hierarchy below this entity, solved by [`solve_two_bone_ik`] after
+/// the animation is applied.
+///
+/// Paths start with the name of this entity, like the paths of an [`crate::AnimationClip`]
+/// played on it. Joints are resolved from their paths the first time a chain is solved, and again
+/// when the hierarchy or names below this entity change.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct TwoBoneIk {
+    /// Chains to solve, in order.
+    pub chains: Vec<TwoBoneIkChain>,
+}
+
+impl TwoBoneIk {
+    /// Resolves the joints of the chains again the next time they're solved, such as after their
+    /// paths changed.
+    pub fn rebind(&mut self) {
+        for chain in &mut self.chains {
+            chain.bindings.reset();
+        }
+    }
+}
+
+/// Entities of the joints of a chain, resolved from their paths once and kept until the
+/// hierarchy changes.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct JointBindings {
+    /// The entity of each joint, or `None` if one of them is missing. Unset until the joints are
+    /// resolved.
+    entities: Option<Option<Vec<Entity>>>,
+}
+
+impl JointBindings {
+    /// The entities of the joints at `paths` below `root`, resolving them if they aren't yet.
+    /// Missing joints are only reported the first time.
+    pub(crate) fn get_or_bind<'a>(
+        &mut self,
+        root: Entity,
+        paths: impl IntoIterator<Item = &'a EntityPath>,
+        children: &Query<&Children>,
+        names: &Query<&Name>,
+    ) -> Option<&[Entity]> {
+        self.entities
+            .get_or_insert_with(|| paths.into_iter().map(|path| find_bone(root, path, children, names)).collect())
+            .as_deref()
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.entities = None;
+    }
+}
+
+/// System that resets the joints bound by IK chains, [`AimConstraint`]s and [`FootLocking`]s when
+/// the hierarchy or names below them change, like [`crate::invalidate_animation_bindings`] does for players.
+///
+/// Joints are resolved again the next time the chains are solved.
+pub fn invalidate_joint_bindings(
+    mut hierarchy_changes: HierarchyChanges,
+    mut two_bone_iks: Query<(Entity, &mut TwoBoneIk)>,
+    mut chain_iks: Query<(Entity, &mut ChainIk)>,
+    mut aims: Query<(Entity, &mut AimConstraint)>,
+    mut lockings: Query<(Entity, &mut FootLocking)>,
+) {
+    let changed_roots = hierarchy_changes.changed_roots();
+    if changed_roots.is_empty() {
+        return;
+    }
+    for (_, mut ik) in two_bone_iks.iter_mut().filter(|(entity, _)| changed_roots.contains(entity)) {
+        ik.bypass_change_detection().rebind();
+    }
+    for (_, mut ik) in chain_iks.iter_mut().filter(|(entity, _)| changed_roots.contains(entity)) {
+        ik.bypass_change_detection().rebind();
+    }
+    for (_, mut aim) in aims.iter_mut().filter(|(entity, _)| changed_roots.contains(entity)) {
+        aim.bypass_change_detection().rebind();
+    }
+    for (_, mut locking) in lockings.iter_mut().filter(|(entity, _)| changed_roots.contains(entity)) {
+        locking.bypass_change_detection().rebind();
+    }
+}
+
+/// System that bends the chains of every [`TwoBoneIk`] towards their targets.
+///
+/// It runs after [`crate::apply_animation_poses`] and before transforms are propagated, so
+/// targets are reached in the frame's pose.
+pub fn solve_two_bone_ik(
+    mut iks: Query<(Entity, &mut TwoBoneIk)>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+    parents: Query<&Parent>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for (root, mut ik) in &mut iks {
+        // Only the cached joints are written
+        for chain in ik.bypass_change_detection().chains.iter_mut().filter(|chain| chain.weight > 0.0) {
+            let paths = [&chain.root, &chain.middle, &chain.tip];
+            let Some(&[first, middle, tip]) = chain.bindings.get_or_bind(root, paths, &children, &names) else {
+                continue;
+            };
+            bend_two_bones([first, middle, tip], chain.target, chain.pole, chain.weight, &parents, &mut transforms);
+        }
+    }
+}
+
+/// Bends the chain going through `joints` so that its tip reaches `target`, blended by `weight`.
+pub(crate) fn bend_two_bones(
+    joints: [Entity; 3],
+    target: Vec3,
+    pole: Vec3,
+    weight: f32,
+    parents: &Query<&Parent>,
+    transforms: &mut Query<&mut Transform>,
+) {
+    let mut pose = ChainPose::new(&joints, vec![None; 3], parents, transforms);
+    let &[first, middle, tip] = pose.positions().as_slice() else { return };
+    let (first_rotation, middle_rotation) = two_bone_rotations(first, middle, tip, target, pole);
+    pose.rotate(0, first_rotation);
+    pose.rotate(1, middle_rotation);
+    // Blend the solved pose with the animated one, like chains do
+    let weight = weight.min(1.0);
+    for (joint, (local, reference)) in joints.iter().zip(pose.locals.iter().zip(&pose.reference)) {
+        if let Ok(mut transform) = transforms.get_mut(*joint) {
+            transform.rotation = reference.slerp(local.rotation, weight);
+        }
+    }
+}
+
+/// Transform of `entity` from the local transforms of its ancestors, which are up to date
+/// before they're propagated.
+pub(crate) fn global_transform(
+    entity: Entity,
+    parents: &Query<&Parent>,
+    transforms: &Query<&mut Transform>,
+) -> GlobalTransform {
+    let local = transforms.get(entity).map_or(GlobalTransform::IDENTITY, |transform| (*transform).into());
+    match parents.get(entity) {
+        Ok(parent) => global_transform(parent.get(), parents, transforms) * local,
+        Err(_) => local,
+    }
+}
+
+/// Transform of `entity` relative to `ancestor`, from the local transforms in between, or `None`
+/// if `ancestor` isn't above `entity`.
+fn relative_transform(
+    entity: Entity,
+    ancestor: Entity,
+    parents: &Query<&Parent>,
+    transforms: &Query<&mut Transform>,
+) -> Option<Affine3A> {
+    let mut transform = Affine3A::IDENTITY;
+    let mut current = entity;
+    while current != ancestor {
+        let local = transforms.get(current).map_or(Affine3A::IDENTITY, |local| local.compute_affine());
+        transform = local * transform;
+        current = parents.get(current).ok()?.get();
+    }
+    Some(transform)
+}
+
+/// World space rotations of the first joint, and then of the middle joint, of the chain going
+/// through `first`, `middle` and `tip`, so that the tip reaches `target` or gets as close as it can.
+fn two_bone_rotations(first: Vec3, middle: Vec3, tip: Vec3, target: Vec3, pole: Vec3) -> (Quat, Quat) {
+    let (upper, lower) = (middle - first, tip - middle);
+    let (upper_length, lower_length) = (upper.length(), lower.length());
+    let Some(direction) = (target - first).try_normalize() else { return (Quat::IDENTITY, Quat::IDENTITY) };
+    if upper_length < MIN_LENGTH || lower_length < MIN_LENGTH {
+        return (Quat::IDENTITY, Quat::IDENTITY);
+    }
+
+    // Don't fully stretch or fold the chain, where the bend is undefined
+    let distance = (target - first).length().clamp(
+        (upper_length - lower_length).abs() + MIN_LENGTH,
+        upper_length + lower_length - MIN_LENGTH,
+    );
+    // Bend towards the pole, or in the animated plane
+    let bend = [pole, upper]
+        .into_iter()
+        .find_map(|vector| vector.reject_from_normalized(direction).try_normalize())
+        .unwrap_or_else(|| direction.any_orthonormal_vector());
+
+    // Law of cosines at the first joint
+    let cos = ((upper_length.powi(2) + distance.powi(2) - lower_length.powi(2)) / (2.0 * upper_length * distance))
+        .clamp(-1.0, 1.0);
+    let sin = (1.0 - cos * cos).sqrt();
+    let new_upper = upper_length * (cos * direction + sin * bend);
+    let new_lower = direction * distance - new_upper;
+
+    let first_rotation = Quat::from_rotation_arc(upper / upper_length, new_upper / upper_length);
+    let lower = first_rotation * lower;
+    let middle_rotation = Quat::from_rotation_arc(lower / lower_length, new_lower.normalize());
+    (first_rotation, middle_rotation)
+}
+
+/// Limits of the rotation of a joint relative to a reference rotation: its twist axis swings
+/// within a cone, and it twists around that axis within a range.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct JointLimit {
+    /// Axis the joint twists around in its local space, usually along the bone.
+    pub twist_axis: Vec3,
+    /// Largest angle between the twist axis and its reference direction, in radians.
+    pub max_swing: f32,
+    /// Smallest twist around the axis, in radians.
+    pub min_twist: f32,
+    /// Largest twist around the axis, in radians.
+    pub max_twist: f32,
+}
+
+impl Default for JointLimit {
+    fn default() -> Self {
+        JointLimit { twist_axis: Vec3::Y, max_swing: PI, min_twist: -PI, max_twist: PI }
+    }
+}
+
+impl JointLimit {
+    /// Splits `rotation` into a swing of the twist axis, followed by a twist around it, and returns
+    /// the swing and the twist angle.
+    pub fn swing_twist(&self, rotation: Quat) -> (Quat, f32) {
+        let axis = self.twist_axis.normalize();
+        let projection = rotation.xyz().dot(axis);
+        let mut twist_angle = 2.0 * projection.atan2(rotation.w);
+        if twist_angle > PI {
+            twist_angle -= 2.0 * PI;
+        } else if twist_angle < -PI {
+            twist_angle += 2.0 * PI;
+        }
+        let swing = rotation * Quat::from_axis_angle(axis, twist_angle).inverse();
+        (swing.normalize(), twist_angle)
+    }
+
+    /// Does `rotation` stay within the limit?
+    pub fn contains(&self, rotation: Quat) -> bool {
+        let (swing, twist) = self.swing_twist(rotation);
+        swing_angle(swing) <= self.max_swing + 1e-4 && (self.min_twist - 1e-4..=self.max_twist + 1e-4).contains(&twist)
+    }
+
+    /// The closest rotation to `rotation` within the limit.
+    pub fn clamp(&self, rotation: Quat) -> Quat {
+        let (swing, twist) = self.swing_twist(rotation);
+        let angle = swing_angle(swing);
+        let swing = if angle > self.max_swing {
+            Quat::IDENTITY.slerp(swing, self.max_swing / angle)
+        } else {
+            swing
+        };
+        let twist = twist.clamp(self.min_twist, self.max_twist.max(self.min_twist));
+        (swing * Quat::from_axis_angle(self.twist_axis.normalize(), twist)).normalize()
+    }
+
+    /// Does `rotation` stay within the limit, which is relative to `reference`?
+    pub fn contains_relative(&self, reference: Quat, rotation: Quat) -> bool {
+        self.contains(reference.inverse() * rotation)
+    }
+
+    /// The closest rotation to `rotation` within the limit, which is relative to `reference`.
+    pub fn clamp_relative(&self, reference: Quat, rotation: Quat) -> Quat {
+        reference * self.clamp(reference.inverse() * rotation)
+    }
+}
+
+fn swing_angle(swing: Quat) -> f32 {
+    2.0 * swing.w.abs().min(1.0).acos()
+}
+
+/// Algorithm used to solve an [`IkChain`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum IkChainSolver {
+    /// Forward And Backward Reaching Inverse Kinematics, which spreads the bend along the chain.
+    #[default]
+    Fabrik,
+    /// Cyclic Coordinate Descent, which bends the joints closest to the tip the most.
+    Ccd,
+}
+
+/// A joint of an [`IkChain`].
+#[derive(Clone, Debug, Reflect, FromReflect)]
+pub struct IkJoint {
+    /// Path to the joint.
+    pub path: EntityPath,
+    /// Limits of the joint, relative to its animated rotation.
+    pub limit: Option<JointLimit>,
+}
+
+/// A chain of any number of bones, such as a tail or a spine, bent so that its tip reaches a
+/// target entity.
+#[derive(Clone, Debug, Reflect, FromReflect)]
+pub struct IkChain {
+    /// Joints from the root of the chain to its tip. Each joint is below the previous one.
+    pub joints: Vec<IkJoint>,
+    /// Entity whose position the tip reaches for.
+    pub target: Entity,
+    /// Algorithm used to solve the chain.
+    pub solver: IkChainSolver,
+    /// Most iterations of the solver per frame.
+    pub iterations: u32,
+    /// Distance between the tip and the target at which the chain is solved.
+    pub tolerance: f32,
+    /// How much of the correction is applied, from 0 (the animated pose) to 1.
+    pub weight: f32,
+    #[reflect(ignore)]
+    bindings: JointBindings,
+}
+
+impl IkChain {
+    /// A chain through `joints`, without limits, solved with FABRIK.
+    pub fn new(joints: impl IntoIterator<Item = EntityPath>, target: Entity) -> Self {
+        IkChain {
+            joints: joints.into_iter().map(|path| IkJoint { path, limit: None }).collect(),
+            target,
+            solver: IkChainSolver::default(),
+            iterations: 10,
+            tolerance: 1e-3,
+            weight: 1.0,
+            bindings: JointBindings::default(),
+        }
+    }
+}
+
+/// Multi-bone IK chains of the hierarchy below this entity, solved by [`solve_chain_ik`] after the
+/// animation is applied.
+///
+/// Paths start with the name of this entity, like the paths of an [`crate::AnimationClip`]
+/// played on it. Joints are resolved like those of [`TwoBoneIk`].
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct ChainIk {
+    /// Chains to solve, in order.
+    pub chains: Vec<IkChain>,
+}
+
+impl ChainIk {
+    /// Resolves the joints of the chains again the next time they're solved, such as after their
+    /// paths changed.
+    pub fn rebind(&mut self) {
+        for chain in &mut self.chains {
+            chain.bindings.reset();
+        }
+    }
+}
+
+/// System that bends the chains of every [`ChainIk`] towards their target entities.
+///
+/// It runs after [`solve_two_bone_ik`] and before transforms are propagated.
+pub fn solve_chain_ik(
+    mut iks: Query<(Entity, &mut ChainIk)>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+    parents: Query<&Parent>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for (root, mut ik) in &mut iks {
+        // Only the cached joints are written
+        let chains = ik.bypass_change_detection().chains.iter_mut();
+        for chain in chains.filter(|chain| chain.weight > 0.0 && chain.joints.len() >= 2) {
+            if !transforms.contains(chain.target) {
+                continue;
+            }
+            let paths = chain.joints.iter().map(|joint| &joint.path);
+            let Some(joints) = chain.bindings.get_or_bind(root, paths, &children, &names) else { continue };
+
+            let target = global_transform(chain.target, &parents, &transforms).translation();
+            let limits = chain.joints.iter().map(|joint| joint.limit).collect();
+            let mut pose = ChainPose::new(joints, limits, &parents, &transforms);
+            match chain.solver {
+                IkChainSolver::Fabrik => pose.solve_fabrik(target, chain.iterations, chain.tolerance),
+                IkChainSolver::Ccd => pose.solve_ccd(target, chain.iterations, chain.tolerance),
+            }
+
+            let weight = chain.weight.min(1.0);
+            for (joint, (local, reference)) in joints.iter().zip(pose.locals.iter().zip(&pose.reference)) {
+                if let Ok(mut transform) = transforms.get_mut(*joint) {
+                    transform.rotation = reference.slerp(local.rotation, weight);
+                }
+            }
+        }
+    }
+}
+
+/// Local transforms of the joints of a chain, with the fixed transforms between them.
+pub(crate) struct ChainPose {
+    /// Transform from the previous joint (or from world space, for the first joint) to the parent of
+    /// each joint.
+    offsets: Vec<Affine3A>,
+    pub(crate) locals: Vec<Transform>,
+    /// Animated rotations, which the limits are relative to.
+    pub(crate) reference: Vec<Quat>,
+    limits: Vec<Option<JointLimit>>,
+}
+
+impl ChainPose {
+    pub(crate) fn new(
+        joints: &[Entity],
+        limits: Vec<Option<JointLimit>>,
+        parents: &Query<&Parent>,
+        transforms: &Query<&mut Transform>,
+    ) -> Self {
+        let mut previous = Affine3A::IDENTITY;
+        let mut offsets = Vec::with_capacity(joints.len());
+        let mut locals = Vec::with_capacity(joints.len());
+        for (index, joint) in joints.iter().enumerate() {
+            // Only walk up to the previous joint, whose world transform is known
+            let parent = parents.get(*joint).map_or(Affine3A::IDENTITY, |parent| {
+                let previous_joint = index.checked_sub(1).map(|previous_index| joints[previous_index]);
+                let offset = previous_joint
+                    .and_then(|previous_joint| relative_transform(parent.get(), previous_joint, parents, transforms));
+                match offset {
+                    Some(offset) => previous * offset,
+                    None => global_transform(parent.get(), parents, transforms).affine(),
+                }
+            });
+            let local = transforms.get(*joint).map_or(Transform::IDENTITY, |transform| *transform);
+            offsets.push(previous.inverse() * parent);
+            previous = parent * local.compute_affine();
+            locals.push(local);
+        }
+        let reference = locals.iter().map(|local| local.rotation).collect();
+        ChainPose { offsets, locals, reference, limits }
+    }
+
+    /// A chain of joints that are each the child of the previous one, the first one being in world space.
+    #[cfg(test)]
+    pub(crate) fn from_locals(locals: Vec<Transform>, limits: Vec<Option<JointLimit>>) -> Self {
+        ChainPose {
+            offsets: vec![Affine3A::IDENTITY; locals.len()],
+            reference: locals.iter().map(|local| local.rotation).collect(),
+            limits,
+            locals,
+        }
+    }
+
+    /// World transform of the parent of each joint.
+    pub(crate) fn parents(&self) -> Vec<Affine3A> {
+        let mut previous = Affine3A::IDENTITY;
+        self.offsets
+            .iter()
+            .zip(&self.locals)
+            .map(|(offset, local)| {
+                let parent = previous * *offset;
+                previous = parent * local.compute_affine();
+                parent
+            })
+            .collect()
+    }
+
+    /// World position of each joint.
+    pub(crate) fn positions(&self) -> Vec<Vec3> {
+        self.parents()
+            .iter()
+            .zip(&self.locals)
+            .map(|(parent, local)| parent.transform_point3(local.translation))
+            .collect()
+    }
+
+    /// Rotates a joint by `rotation` in world space, within its limit.
+    pub(crate) fn rotate(&mut self, index: usize, rotation: Quat) {
+        let (_, parent, _) = self.parents()[index].to_scale_rotation_translation();
+        let local = parent.inverse() * rotation * parent * self.locals[index].rotation;
+        let reference = self.reference[index];
+        self.locals[index].rotation = match self.limits[index] {
+            Some(limit) => limit.clamp_relative(reference, local),
+            None => local,
+        }
+        .normalize();
+    }
+
+    /// Rotates the joint at `index` so that the segment starting at it points from `from` to `to`.
+    fn rotate_towards(&mut self, index: usize, from: Vec3, to: Vec3) {
+        if let (Some(from), Some(to)) = (from.try_normalize(), to.try_normalize()) {
+            self.rotate(index, Quat::from_rotation_arc(from, to));
+        }
+    }
+
+    fn solve_ccd(&mut self, target: Vec3, iterations: u32, tolerance: f32) {
+        for _ in 0..iterations {
+            // Rotate each joint from the tip to the root so that the tip points at the target
+            for index in (0..self.locals.len() - 1).rev() {
+                let positions = self.positions();
+                let tip = positions[positions.len() - 1];
+                if tip.distance(target) <= tolerance {
+                    return;
+                }
+                self.rotate_towards(index, tip - positions[index], target - positions[index]);
+            }
+        }
+    }
+
+    fn solve_fabrik(&mut self, target: Vec3, iterations: u32, tolerance: f32) {
+        for _ in 0..iterations {
+            let mut positions = self.positions();
+            let last = positions.len() - 1;
+            if positions[last].distance(target) <= tolerance {
+                return;
+            }
+            let lengths: Vec<f32> = positions.windows(2).map(|segment| segment[0].distance(segment[1])).collect();
+
+            // Reach from the target back to the root, then from the root forward to the target
+            let root = positions[0];
+            positions[last] = target;
+            for index in (0..last).rev() {
+                let direction = (positions[index] - positions[index + 1]).normalize_or_zero();
+                positions[index] = positions[index + 1] + direction * lengths[index];
+            }
+            positions[0] = root;
+            for index in 0..last {
+                let direction = (positions[index + 1] - positions[index]).normalize_or_zero();
+                positions[index + 1] = positions[index] + direction * lengths[index];
+            }
+
+            // Rotate the joints to the new positions, within their limits
+            for index in 0..last {
+                let current = self.positions();
+                self.rotate_towards(
+                    index,
+                    current[index + 1] - current[index],
+                    positions[index + 1] - positions[index],
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::App;
+    use bevy::hierarchy::{BuildWorldChildren, DespawnRecursiveExt};
+    use bevy::transform::{TransformBundle, TransformPlugin};
+    use bevy::MinimalPlugins;
+
+    use super::*;
+
+    /// Positions of the middle joint and tip of a chain rotated by [`two_bone_rotations`].
+    fn solve(first: Vec3, middle: Vec3, tip: Vec3, target: Vec3, pole: Vec3) -> (Vec3, Vec3) {
+        let (first_rotation, middle_rotation) = two_bone_rotations(first, middle, tip, target, pole);
+        let new_middle = first + first_rotation * (middle - first);
+        (new_middle, new_middle + middle_rotation * first_rotation * (tip - middle))
+    }
+
+    #[test]
+    fn reaches_target() {
+        let (middle, tip) = solve(Vec3::ZERO, Vec3::Y, Vec3::Y * 2.0, Vec3::new(1.0, 1.0, 0.0), Vec3::Z);
+        assert!(tip.abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-3), "{tip}");
+        assert!((middle.length() - 1.0).abs() < 1e-4);
+        assert!((tip.distance(middle) - 1.0).abs() < 1e-4);
+        assert!(middle.z > 0.5, "{middle}");
+
+        // Out of reach, the chain is stretched towards the target
+        let (_, tip) = solve(Vec3::ZERO, Vec3::Y, Vec3::Y * 2.0, Vec3::X * 5.0, Vec3::Z);
+        assert!(tip.abs_diff_eq(Vec3::X * 2.0, 1e-3), "{tip}");
+    }
+
+    #[test]
+    fn solves_hierarchy() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_systems((invalidate_joint_bindings, solve_two_bone_ik).chain());
+        let path =
+            |parts: &[&str]| EntityPath { parts: parts.iter().map(|part| Name::new(part.to_string())).collect() };
+        let mut joint = |name: &'static str, children: &[Entity]| {
+            app.world
+                .spawn((TransformBundle::from(Transform::from_xyz(0.0, 1.0, 0.0)), Name::new(name)))
+                .push_children(children)
+                .id()
+        };
+        let foot = joint("foot", &[]);
+        let knee = joint("knee", &[foot]);
+        let hip = joint("hip", &[knee]);
+
+        let mut chain = TwoBoneIkChain::new(
+            path(&["root", "hip"]),
+            path(&["root", "hip", "knee"]),
+            path(&["root", "hip", "knee", "foot"]),
+        );
+        chain.target = Vec3::new(3.0, 2.0, 0.0);
+        chain.pole = Vec3::Z;
+        chain.weight = 1.0;
+        app.world
+            .spawn((
+                TransformBundle::from(Transform::from_xyz(2.0, 0.0, 0.0)),
+                Name::new("root"),
+                TwoBoneIk { chains: vec![chain] },
+            ))
+            .push_children(&[hip]);
+        app.update();
+
+        let foot_position = app.world.get::<GlobalTransform>(foot).unwrap().translation();
+        assert!(foot_position.abs_diff_eq(Vec3::new(3.0, 2.0, 0.0), 1e-3), "{foot_position}");
+        assert!(app.world.get::<GlobalTransform>(knee).unwrap().translation().z > 0.5);
+
+        // A longer foot replacing the bound one is bound instead
+        app.world.entity_mut(foot).despawn_recursive();
+        let foot = app.world.spawn((TransformBundle::from(Transform::from_xyz(0.0, 1.5, 0.0)), Name::new("foot"))).id();
+        app.world.entity_mut(knee).push_children(&[foot]);
+        app.update();
+        let foot_position = app.world.get::<GlobalTransform>(foot).unwrap().translation();
+        assert!(foot_position.abs_diff_eq(Vec3::new(3.0, 2.0, 0.0), 1e-3), "{foot_position}");
+
+        // Partial weights blend between the animated and the solved local rotations
+        let root = app.world.get::<Parent>(hip).unwrap().get();
+        let mut solve = |weight| {
+            for joint in [hip, knee] {
+                app.world.get_mut::<Transform>(joint).unwrap().rotation = Quat::IDENTITY;
+            }
+            app.world.get_mut::<TwoBoneIk>(root).unwrap().chains[0].weight = weight;
+            app.update();
+            [hip, knee].map(|joint| app.world.get::<Transform>(joint).unwrap().rotation)
+        };
+        let solved = solve(1.0);
+        for (rotation, solved) in solve(0.5).into_iter().zip(solved) {
+            assert!(rotation.abs_diff_eq(Quat::IDENTITY.slerp(solved, 0.5), 1e-4), "{rotation}");
+        }
+    }
+
+    /// A straight chain of `count` unit bones along Y, each the child of the previous one.
+    fn straight_chain(count: usize, limit: Option<JointLimit>) -> ChainPose {
+        let mut locals = vec![Transform::from_xyz(0.0, 1.0, 0.0); count];
+        locals[0] = Transform::IDENTITY;
+        ChainPose::from_locals(locals, vec![limit; count])
+    }
+
+    #[test]
+    fn chain_solvers_converge() {
+        let target = Vec3::new(2.0, 2.5, 1.0);
+        for solver in [IkChainSolver::Fabrik, IkChainSolver::Ccd] {
+            let mut pose = straight_chain(6, None);
+            match solver {
+                IkChainSolver::Fabrik => pose.solve_fabrik(target, 50, 1e-3),
+                IkChainSolver::Ccd => pose.solve_ccd(target, 50, 1e-3),
+            }
+            let positions = pose.positions();
+            assert!(positions[5].distance(target) <= 1e-3, "{solver:?}: {}", positions[5]);
+            // Bones keep their lengths
+            for segment in positions.windows(2) {
+                assert!((segment[0].distance(segment[1]) - 1.0).abs() < 1e-4, "{solver:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn chain_limits() {
+        let limit = JointLimit { max_swing: 0.3, min_twist: -0.1, max_twist: 0.1, ..Default::default() };
+        let mut pose = straight_chain(4, Some(limit));
+        pose.solve_fabrik(Vec3::new(3.0, 0.0, 0.0), 20, 1e-3);
+        for rotation in pose.locals.iter().map(|local| local.rotation) {
+            assert!(limit.contains(rotation), "{rotation}");
+        }
+        // The target is out of the limits' reach
+        assert!(pose.positions()[3].x < 2.0);
+
+        let clamped = limit.clamp(Quat::from_rotation_y(1.0) * Quat::from_rotation_x(1.0));
+        let (swing, twist) = limit.swing_twist(clamped);
+        assert!((swing_angle(swing) - 0.3).abs() < 1e-4);
+        assert!((twist - 0.1).abs() < 1e-4);
+    }
+
+    #[test]
+    fn chain_target_entity() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugin(TransformPlugin).add_system(solve_chain_ik);
+        let target = app.world.spawn(TransformBundle::from(Transform::from_xyz(1.0, 2.0, 0.0))).id();
+        let mut entities = vec![];
+        let mut paths = vec![];
+        let mut parent = app.world.spawn((TransformBundle::default(), Name::new("root"))).id();
+        let mut parts = vec![Name::new("root")];
+        for index in 0..4 {
+            let name = Name::new(format!("tail_{index}"));
+            let joint = app.world.spawn((TransformBundle::from(Transform::from_xyz(0.0, 1.0, 0.0)), name.clone())).id();
+            app.world.entity_mut(parent).push_children(&[joint]);
+            parts.push(name);
+            paths.push(EntityPath { parts: parts.clone() });
+            entities.push(joint);
+            parent = joint;
+        }
+        let root = app.world.get::<Parent>(entities[0]).unwrap().get();
+        // The third joint is between two joints of the chain without being part of it
+        paths.remove(2);
+        let mut chain = IkChain::new(paths, target);
+        chain.solver = IkChainSolver::Ccd;
+        chain.iterations = 50;
+        app.world.entity_mut(root).insert(ChainIk { chains: vec![chain] });
+        app.update();
+
+        let tip = app.world.get::<GlobalTransform>(entities[3]).unwrap().translation();
+        assert!(tip.abs_diff_eq(Vec3::new(1.0, 2.0, 0.0), 1e-2), "{tip}");
+    }
+}
@@ -1,14 +1,43 @@
+mod aim;
 mod bevy_animation;
 mod bevy_gltf;
+mod bvh;
+mod bvh_export;
+mod clip_editing;
+mod compression;
+mod foot_locking;
+mod gltf_export;
+mod ik;
+mod lod;
 mod motion_warp;
+mod pose;
+mod pose_cache;
+mod property_animation;
+mod retarget;
+mod skeleton;
 
 use bevy::{prelude::{PluginGroup, Plugin, CoreSet, App, AddAsset, IntoSystemConfig}, app::PluginGroupBuilder, transform::TransformSystem};
 
+pub use aim::*;
 pub use bevy_animation::*;
 pub use bevy_gltf::*;
+pub use bvh::*;
+pub use bvh_export::*;
+pub use clip_editing::*;
+pub use compression::*;
+pub use foot_locking::*;
+pub use gltf_export::*;
+pub use ik::*;
+pub use lod::*;
 pub use motion_warp::*;
+pub use pose::*;
+pub use pose_cache::*;
+pub use property_animation::*;
+pub use retarget::*;
+pub use skeleton::*;
 
 pub mod quat_splines;
+#[cfg(feature = "editor")]
 pub mod editor;
 
 pub struct MotionWarpPlugins;
@@ -18,7 +47,8 @@ impl PluginGroup for MotionWarpPlugins {
         let mut group = PluginGroupBuilder::start::<Self>();
         group = group
             .add(AnimationPlugin {})
-            .add(GltfPlugin);
+            .add(GltfPlugin)
+            .add(BvhPlugin);
         group
     }
 }
@@ -30,11 +60,67 @@ impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
         app.add_asset::<AnimationClip>()
             .add_asset::<MotionWarpClip>()
+            .add_asset::<Skeleton>()
             .register_asset_reflect::<AnimationClip>()
             .register_type::<AnimationPlayer>()
+            .register_type::<AnimationLod>()
+            .register_type::<TwoBoneIk>()
+            .register_type::<ChainIk>()
+            .register_type::<AimConstraint>()
+            .register_type::<FootLocking>()
+            .add_system(
+                invalidate_animation_pose_cache
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(animation_player),
+            )
+            .add_system(
+                invalidate_animation_bindings
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(animation_player),
+            )
             .add_system(
                 animation_player
                     .in_base_set(CoreSet::PostUpdate)
+                    .before(apply_animation_poses),
+            )
+            .add_system(
+                apply_animation_poses
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system(
+                apply_animation_properties
+                    .in_base_set(CoreSet::PostUpdate)
+                    .after(apply_animation_poses)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system(
+                invalidate_joint_bindings
+                    .in_base_set(CoreSet::PostUpdate)
+                    .before(lock_feet),
+            )
+            .add_system(
+                lock_feet
+                    .in_base_set(CoreSet::PostUpdate)
+                    .after(apply_animation_properties)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system(
+                solve_two_bone_ik
+                    .in_base_set(CoreSet::PostUpdate)
+                    .after(lock_feet)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system(
+                solve_chain_ik
+                    .in_base_set(CoreSet::PostUpdate)
+                    .after(solve_two_bone_ik)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system(
+                aim_constraints
+                    .in_base_set(CoreSet::PostUpdate)
+                    .after(solve_chain_ik)
                     .before(TransformSystem::TransformPropagate),
             );
     }
@@ -0,0 +1,136 @@
+//! Level of detail for [`crate::AnimationPlayer`]s, to animate large crowds.
+
+use bevy::asset::Handle;
+use bevy::ecs::prelude::*;
+use bevy::hierarchy::{Children, HierarchyQueryExt};
+use bevy::math::Vec3;
+use bevy::reflect::{FromReflect, Reflect};
+use bevy::render::{mesh::Mesh, view::ComputedVisibility};
+use bevy::transform::components::GlobalTransform;
+
+/// How an [`crate::AnimationPlayer`] is evaluated from some distance to the camera.
+#[derive(Clone, Debug, Reflect, FromReflect)]
+pub struct AnimationLodLevel {
+    /// Distance from the closest active camera from which this level is used.
+    pub min_distance: f32,
+    /// The player is sampled once every `update_interval` frames.
+    pub update_interval: u32,
+    /// Only bones this deep below the player are sampled, if set. Other bones keep their
+    /// last sampled transform.
+    pub max_bone_depth: Option<usize>,
+}
+
+/// Lowers how often an [`crate::AnimationPlayer`] on the same entity is evaluated, depending on its
+/// distance to the camera and its visibility.
+///
+/// Throttled players keep advancing their elapsed time, so they're in sync with full rate
+/// players whenever they're sampled.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct AnimationLod {
+    /// Levels of detail, in any order. The level with the largest `min_distance` under the
+    /// distance to the camera is used, and players closer than every level are fully evaluated.
+    pub levels: Vec<AnimationLodLevel>,
+    /// Skip sampling while no mesh below the player is visible.
+    pub pause_when_hidden: bool,
+    #[reflect(ignore)]
+    frame: u32,
+    /// Meshes below the player, resolved when needed and reset on hierarchy changes.
+    #[reflect(ignore)]
+    pub(crate) visibility_targets: Option<Vec<Entity>>,
+}
+
+impl Default for AnimationLod {
+    fn default() -> Self {
+        AnimationLod {
+            levels: vec![
+                AnimationLodLevel { min_distance: 20.0, update_interval: 2, max_bone_depth: None },
+                AnimationLodLevel { min_distance: 50.0, update_interval: 4, max_bone_depth: Some(4) },
+            ],
+            pause_when_hidden: true,
+            frame: 0,
+            visibility_targets: None,
+        }
+    }
+}
+
+/// What parts of an animation to sample this frame.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Throttle {
+    pub(crate) sample: bool,
+    pub(crate) max_bone_depth: Option<usize>,
+}
+
+impl Throttle {
+    pub(crate) const FULL: Throttle = Throttle { sample: true, max_bone_depth: None };
+}
+
+impl AnimationLod {
+    /// The level of detail used at `distance` from the camera.
+    pub fn level(&self, distance: f32) -> Option<&AnimationLodLevel> {
+        self.levels
+            .iter()
+            .filter(|level| distance >= level.min_distance)
+            .max_by(|a, b| a.min_distance.total_cmp(&b.min_distance))
+    }
+
+    /// Advances by a frame and decides what to sample for the player on `root`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn throttle(
+        &mut self,
+        root: Entity,
+        camera_positions: &[Vec3],
+        global_transforms: &Query<&GlobalTransform>,
+        children: &Query<&Children>,
+        visibilities: &Query<&ComputedVisibility>,
+        meshes: &Query<(), With<Handle<Mesh>>>,
+    ) -> Throttle {
+        self.frame = self.frame.wrapping_add(1);
+
+        if self.pause_when_hidden {
+            let targets = self.visibility_targets.get_or_insert_with(|| {
+                children.iter_descendants(root).filter(|entity| meshes.contains(*entity)).collect()
+            });
+            let visible = if targets.is_empty() {
+                visibilities.get(root).map_or(true, ComputedVisibility::is_visible)
+            } else {
+                targets
+                    .iter()
+                    .any(|target| visibilities.get(*target).is_ok_and(ComputedVisibility::is_visible))
+            };
+            if !visible {
+                return Throttle { sample: false, max_bone_depth: None };
+            }
+        }
+
+        let distance = match global_transforms.get(root) {
+            Ok(transform) if !camera_positions.is_empty() => camera_positions
+                .iter()
+                .map(|camera| camera.distance(transform.translation()))
+                .fold(f32::INFINITY, f32::min),
+            _ => 0.0,
+        };
+        let Some(level) = self.level(distance) else { return Throttle::FULL };
+
+        // Offset by entity so throttled players don't all sample on the same frame
+        let interval = level.update_interval.max(1);
+        Throttle {
+            sample: self.frame.wrapping_add(root.index()) % interval == 0,
+            max_bone_depth: level.max_bone_depth,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lod_levels() {
+        let lod = AnimationLod::default();
+
+        assert!(lod.level(10.0).is_none());
+        assert_eq!(lod.level(20.0).unwrap().update_interval, 2);
+        assert_eq!(lod.level(100.0).unwrap().update_interval, 4);
+    }
+}
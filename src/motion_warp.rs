@@ -2,7 +2,10 @@ use std::cmp::Ordering;
 
 use bevy::{prelude::{Vec2, Quat}, reflect::{TypeUuid}, utils::HashMap, math::cubic_splines::CubicCurve};
 
-use crate::{EntityPath, quat_splines::{DeCasteljauQuatCurve, bisect}};
+use crate::{
+    AnimationClip, EntityPath, JointLimit, Keyframes, Skeleton, VariableCurve,
+    quat_splines::{DeCasteljauQuatCurve, bisect},
+};
 
 const MAX_ERROR: f32 = 1e-5;
 
@@ -11,60 +14,128 @@ pub mod builder {
     use std::collections::VecDeque;
 
     use bevy::{prelude::{Quat, Resource, CardinalSpline, CubicGenerator}, reflect::{FromReflect, Reflect}};
+    use serde::{Deserialize, Serialize};
+    use thiserror::Error;
 
-    use crate::{AnimationClip, quat_splines::CardinalQuatCurve};
+    use crate::quat_splines::{CardinalQuatCurve, SplineBoundary};
 
     use super::*;
 
-    #[derive(Reflect, FromReflect, Default, Clone)]
+    #[derive(Reflect, FromReflect, Default, Clone, Serialize, Deserialize)]
     pub struct MotionWarpCurveFrame {
         pub rotation: Quat,
         pub fix_a: bool,
     }
 
-    #[derive(Reflect, FromReflect, Default, Clone, )]
+    #[derive(Reflect, FromReflect, Default, Clone, Serialize, Deserialize)]
     pub struct MotionWarpClipFrame {
         pub time: f32,
         pub warp_time: Option<f32>,
         pub map: HashMap<EntityPath, MotionWarpCurveFrame>
     }
 
-    #[derive(Reflect, FromReflect, Resource, Clone)]
+    /// How the warp curves built by a [`MotionWarpClipBuilder`] behave at the start and end
+    /// of the clip.
+    #[derive(Reflect, FromReflect, Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum WarpBoundary {
+        /// The clip loops, so curves wrap around by its duration and its start and end
+        /// depend on each other.
+        #[default]
+        Cyclic,
+        /// The clip plays once, and rotation curves start and end at rest. The time warp keeps
+        /// the speed of its first and last controls, like with `Natural`, so that the animation
+        /// doesn't stop at its ends.
+        Clamped,
+        /// The clip plays once, and curves start and end at the speed of their first and last
+        /// controls.
+        Natural,
+    }
+
+    /// An error that makes a [`MotionWarpClipBuilder`] impossible to build.
+    #[derive(Error, Debug, PartialEq)]
+    pub enum WarpDescriptionError {
+        #[error("warp window starts at {start_time} and ends at {end_time}")]
+        EmptyWindow { start_time: f32, end_time: f32 },
+        #[error("tension {0} isn't between -1 and 1")]
+        InvalidTension(f32),
+        #[error("several keyframes at time {0}")]
+        DuplicateKeyframe(f32),
+        #[error("cyclic warp keyframes span {span}s, more than the clip duration {duration}s")]
+        KeyframesExceedDuration { span: f32, duration: f32 },
+    }
+
+    /// Everything needed to build a [`MotionWarpClip`] for an [`AnimationClip`].
+    ///
+    /// Saved by the editor as a RON warp description.
+    #[derive(Reflect, FromReflect, Resource, Clone, Serialize, Deserialize)]
     pub struct MotionWarpClipBuilder {
         pub clips: Vec<MotionWarpClipFrame>,
         pub start_time: f32,
         pub end_time: f32,
         pub blend_margin: f32,
         pub tension: f32,
+        #[serde(default)]
+        pub boundary: WarpBoundary,
     }
 
     impl MotionWarpClipBuilder {
 
+        /// Serializes this builder as a RON warp description.
+        pub fn to_ron(&self) -> Result<String, ron::Error> {
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+        }
+
+        /// Deserializes a builder from a RON warp description.
+        pub fn from_ron(description: &str) -> Result<Self, ron::error::SpannedError> {
+            ron::from_str(description)
+        }
+
+        /// Checks that this builder can build a warp for `clip`, for descriptions that weren't
+        /// made by the editor.
+        pub fn validate(&self, clip: &AnimationClip) -> Result<(), WarpDescriptionError> {
+            if self.start_time.partial_cmp(&self.end_time) != Some(Ordering::Less) {
+                let (start_time, end_time) = (self.start_time, self.end_time);
+                return Err(WarpDescriptionError::EmptyWindow { start_time, end_time });
+            }
+            if !(-1.0..=1.0).contains(&self.tension) {
+                return Err(WarpDescriptionError::InvalidTension(self.tension));
+            }
+            let mut times: Vec<f32> = self.clips.iter().map(|frame| frame.time).collect();
+            times.sort_by(f32::total_cmp);
+            if let Some(window) = times.windows(2).find(|window| window[0].partial_cmp(&window[1]) != Some(Ordering::Less)) {
+                return Err(WarpDescriptionError::DuplicateKeyframe(window[1]));
+            }
+            // Cyclic curves wrap their keyframes around by the duration, which must keep them sorted
+            let span = times.last().zip(times.first()).map_or(0.0, |(last, first)| last - first);
+            if self.boundary == WarpBoundary::Cyclic && span.partial_cmp(&clip.duration()) != Some(Ordering::Less) {
+                return Err(WarpDescriptionError::KeyframesExceedDuration { span, duration: clip.duration() });
+            }
+            Ok(())
+        }
+
+        /// Builds the warp of `clip`.
+        ///
+        /// Panics if the builder isn't valid for `clip`, see [`MotionWarpClipBuilder::validate`].
         // Efficient? No. Good enough for now? Yes.
         // TODO: debug the shit out of this
-        // TODO: add way more asserts
         pub fn build(&mut self, clip: &AnimationClip) -> MotionWarpClip {
-            assert!(self.start_time < self.end_time);
+            if let Err(err) = self.validate(clip) {
+                panic!("invalid motion warp: {err}");
+            }
 
             self.clips.sort_by(|a, b| a.time.total_cmp(&b.time));
 
             let duration = clip.duration();
             
             let g = {
-                let duration_splat = Vec2::new(duration, duration);
-                let mut times: VecDeque<_> = self
+                let times: Vec<_> = self
                     .clips
                     .iter()
                     .filter_map(|clip_frame| 
                         clip_frame.warp_time.map(|warp_time| Vec2::new(clip_frame.time, warp_time))
                     ).collect();
-                if times.is_empty() {
-                    times.push_back(Vec2::new(0.0, 0.0));
-                }
-                times.push_back(*times.front().unwrap() + duration_splat);
-                times.push_front(*times.back().unwrap() - duration_splat);
                 
-                CardinalSpline::new(self.tension, times).to_curve()
+                CardinalSpline::new(self.tension, g_controls(times, self.boundary, duration)).to_curve()
             };
 
             let (curves, paths) = {
@@ -101,22 +172,40 @@ pub mod builder {
                                 })
                                 .unzip();
 
-                            let mut a_start_control = *a_params.back().unwrap();
-                            let mut a_end_control = *a_params.front().unwrap();
-                            let mut b_start_control = *b_params.back().unwrap();
-                            let mut b_end_control = *b_params.front().unwrap();
-                            a_start_control.1 -= duration;
-                            a_end_control.1 += duration;
-                            b_start_control.1 -= duration;
-                            b_end_control.1 += duration;
-                            a_params.push_front(a_start_control);
-                            a_params.push_back(a_end_control);
-                            b_params.push_front(b_start_control);
-                            b_params.push_back(b_end_control);
+                            let spline_boundary = match self.boundary {
+                                WarpBoundary::Cyclic => {
+                                    // Wrap the controls around, so the curves loop with the clip
+                                    for params in [&mut a_params, &mut b_params] {
+                                        let (last, last_time) = *params.back().unwrap();
+                                        let (first, first_time) = *params.front().unwrap();
+                                        params.push_front((last, last_time - duration));
+                                        params.push_back((first, first_time + duration));
+                                    }
+                                    SplineBoundary::Natural
+                                }
+                                WarpBoundary::Clamped | WarpBoundary::Natural => {
+                                    // A single control holds for the whole clip
+                                    for params in [&mut a_params, &mut b_params] {
+                                        if params.len() == 1 {
+                                            let (control, time) = params[0];
+                                            params.push_back((control, time + 1.0));
+                                        }
+                                    }
+                                    match self.boundary {
+                                        WarpBoundary::Clamped => SplineBoundary::Clamped,
+                                        _ => SplineBoundary::Natural,
+                                    }
+                                }
+                            };
 
                             let curve = MotionWarpCurve {
-                                a: CardinalQuatCurve::new(self.tension, a_params).to_curve(),
-                                b: CardinalQuatCurve::new(self.tension, b_params).to_curve(),
+                                a: CardinalQuatCurve::new(self.tension, a_params)
+                                    .with_boundary(spline_boundary)
+                                    .to_curve(),
+                                b: CardinalQuatCurve::new(self.tension, b_params)
+                                    .with_boundary(spline_boundary)
+                                    .to_curve(),
+                                limit: None,
                             };
 
                             curves.push(curve);
@@ -139,17 +228,57 @@ pub mod builder {
     }
 }
 
+/// Control points of the time warp `g` through `points`, sorted by time, with the extra points
+/// needed by [`bevy::prelude::CardinalSpline`] at both ends.
+fn g_controls(mut points: Vec<Vec2>, boundary: builder::WarpBoundary, duration: f32) -> Vec<Vec2> {
+    if points.is_empty() {
+        points.push(Vec2::ZERO);
+    }
+    let first = points[0];
+    if boundary == builder::WarpBoundary::Cyclic {
+        // The first point is repeated before it, and wrapped around by the duration after the last one
+        return [first].into_iter().chain(points).chain([first + Vec2::splat(duration)]).collect();
+    }
+
+    // A single point keeps the speed of the animation
+    if points.len() == 1 {
+        points.push(first + Vec2::ONE);
+    }
+    let (second, second_last, last) = (points[1], points[points.len() - 2], points[points.len() - 1]);
+    // Extrapolated points keep the speed at both ends. Reflected points would stop the
+    // animation there, even for clamped rotation curves.
+    [2.0 * first - second].into_iter().chain(points).chain([2.0 * last - second_last]).collect()
+}
+
 #[derive(Clone, Debug)]
 pub struct MotionWarpCurve {
     a: DeCasteljauQuatCurve,
-    b: DeCasteljauQuatCurve
+    b: DeCasteljauQuatCurve,
+    /// Rest rotation of the joint, and the limit relative to it that warped rotations are kept within
+    limit: Option<(Quat, JointLimit)>,
 }
 
 impl MotionWarpCurve {
 
     #[inline]
     pub fn theta_prime(&self, t: f32, theta: Quat) -> Quat {
-        self.a.position(t) * theta + self.b.position(t)
+        let theta_prime = self.a.position(t) * theta + self.b.position(t);
+        match &self.limit {
+            Some((rest_rotation, limit)) => limit.clamp_relative(*rest_rotation, theta_prime.normalize()),
+            None => theta_prime,
+        }
+    }
+
+    /// This curve for a joint whose rotations are `parent * theta * child`, with `theta` the
+    /// rotations of the joint of this curve.
+    ///
+    /// Limits are dropped, since they belong to the skeleton of this curve.
+    pub(crate) fn retargeted(&self, parent: Quat, child: Quat) -> Self {
+        MotionWarpCurve {
+            a: self.a.map_controls(|a| parent * a * parent.inverse()),
+            b: self.b.map_controls(|b| parent * b * child),
+            limit: None,
+        }
     }
 }
 
@@ -166,6 +295,19 @@ pub struct MotionWarpClip {
 
 impl MotionWarpClip {
 
+    /// This warp with the rotations of the joints limited in `skeleton` kept within their limits.
+    ///
+    /// Warped rotations are clamped before they're blended with the animation, so the animation
+    /// itself isn't changed outside of the warp.
+    pub fn with_joint_limits(mut self, skeleton: &Skeleton) -> Self {
+        for (path, id) in &self.paths {
+            self.curves[*id].limit = skeleton.index_of(path).and_then(|index| {
+                Some((skeleton.rest_transforms()[index].rotation, skeleton.limits()[index]?))
+            });
+        }
+        self
+    }
+
     /// Maps from "warped time" to "unwarped time"
     #[inline]
     pub fn g(&self, t_prime: f32) -> f32 {
@@ -200,6 +342,54 @@ impl MotionWarpClip {
         0.75 * t_squared / (t_squared - t + 1.0)
     }
 
+    /// Bakes this warp into a copy of `clip`.
+    ///
+    /// Rotation curves of warped joints are resampled at `frame_rate` frames per second
+    /// inside the warp window (keeping their original keyframes), and the warp is applied
+    /// to every keyframe in the same way as [`crate::animation_player`].
+    pub fn bake(&self, clip: &AnimationClip, frame_rate: f32) -> AnimationClip {
+        assert!(frame_rate > 0.0);
+
+        let mut baked = AnimationClip::default();
+        for (path, bone_id) in clip.paths() {
+            let curves = clip.get_curves(*bone_id).unwrap();
+            let warped = self.paths.contains_key(path);
+
+            for curve in curves {
+                let (true, Keyframes::Rotation(_) | Keyframes::CompressedRotation(_)) = (warped, &curve.keyframes) else {
+                    baked.add_curve_to_path(path.clone(), curve.clone());
+                    continue;
+                };
+
+                let frames = ((self.end_time - self.start_time) * frame_rate).ceil() as usize;
+                let mut keyframe_timestamps: Vec<f32> = curve
+                    .keyframe_timestamps
+                    .iter()
+                    .copied()
+                    .chain((0..=frames).map(|i| (self.start_time + i as f32 / frame_rate).min(self.end_time)))
+                    .collect();
+                keyframe_timestamps.sort_by(f32::total_cmp);
+                keyframe_timestamps.dedup_by(|a, b| (*a - *b).abs() < MAX_ERROR);
+
+                let keyframes = keyframe_timestamps
+                    .iter()
+                    .map(|&t| {
+                        let mut joint = clip.sample_joint_clamped(*bone_id, t).unwrap();
+                        self.warp_joint(path, t, &mut joint);
+                        joint.rotation.unwrap()
+                    })
+                    .collect();
+
+                baked.add_curve_to_path(
+                    path.clone(),
+                    VariableCurve { keyframe_timestamps, keyframes: Keyframes::Rotation(keyframes) }
+                );
+            }
+        }
+        baked.copy_property_curves(clip, EntityPath::clone);
+        baked
+    }
+
     // TODO: test me
     #[inline]
     pub fn omega(&self, t: f32) -> f32 {
@@ -216,4 +406,109 @@ impl MotionWarpClip {
             1.0
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::core::Name;
+    use bevy::transform::components::Transform;
+
+    use super::builder::*;
+    use super::*;
+
+    fn warp(boundary: WarpBoundary, end_rotation: Quat) -> MotionWarpClip {
+        let path = EntityPath { parts: vec![Name::new("root")] };
+        let mut clip = AnimationClip::default();
+        clip.add_curve_to_path(
+            path.clone(),
+            VariableCurve { keyframe_timestamps: vec![0.0, 1.0], keyframes: Keyframes::Rotation(vec![Quat::IDENTITY; 2]) },
+        );
+        let frame = |time, rotation| MotionWarpClipFrame {
+            time,
+            warp_time: Some(time),
+            map: [(path.clone(), MotionWarpCurveFrame { rotation, fix_a: false })].into_iter().collect(),
+        };
+        MotionWarpClipBuilder {
+            clips: vec![frame(0.3, Quat::from_rotation_y(0.5)), frame(0.7, end_rotation)],
+            start_time: 0.0,
+            end_time: 1.0,
+            blend_margin: 0.1,
+            tension: 0.5,
+            boundary,
+        }
+        .build(&clip)
+    }
+
+    #[test]
+    fn validates_descriptions() {
+        let mut clip = AnimationClip::default();
+        clip.add_curve_to_path(
+            EntityPath { parts: vec![Name::new("root")] },
+            VariableCurve { keyframe_timestamps: vec![0.0, 1.0], keyframes: Keyframes::Rotation(vec![Quat::IDENTITY; 2]) },
+        );
+        let frame = |time| MotionWarpClipFrame { time, warp_time: Some(time), map: HashMap::new() };
+        let builder = MotionWarpClipBuilder {
+            clips: vec![frame(0.2), frame(0.8)],
+            start_time: 0.0,
+            end_time: 1.0,
+            blend_margin: 0.1,
+            tension: 0.5,
+            boundary: WarpBoundary::Cyclic,
+        };
+        assert_eq!(builder.validate(&clip), Ok(()));
+
+        let empty = MotionWarpClipBuilder { start_time: 1.0, ..builder.clone() };
+        assert_eq!(empty.validate(&clip), Err(WarpDescriptionError::EmptyWindow { start_time: 1.0, end_time: 1.0 }));
+        let duplicate = MotionWarpClipBuilder { clips: vec![frame(0.8), frame(0.2), frame(0.8)], ..builder.clone() };
+        assert_eq!(duplicate.validate(&clip), Err(WarpDescriptionError::DuplicateKeyframe(0.8)));
+        let wide = MotionWarpClipBuilder { clips: vec![frame(0.0), frame(1.0)], ..builder.clone() };
+        assert!(matches!(wide.validate(&clip), Err(WarpDescriptionError::KeyframesExceedDuration { .. })));
+        let clamped = MotionWarpClipBuilder { boundary: WarpBoundary::Clamped, ..wide };
+        assert_eq!(clamped.validate(&clip), Ok(()));
+    }
+
+    #[test]
+    fn non_cyclic_boundaries() {
+        let theta_prime = |warp: &MotionWarpClip, t| warp.curves[0].theta_prime(t, Quat::IDENTITY).normalize();
+        for boundary in [WarpBoundary::Clamped, WarpBoundary::Natural] {
+            let a = warp(boundary, Quat::from_rotation_y(-0.5));
+            let b = warp(boundary, Quat::from_rotation_x(1.0));
+            // The start doesn't depend on the end of the clip
+            assert!(theta_prime(&a, 0.1).abs_diff_eq(theta_prime(&b, 0.1), 1e-5), "{boundary:?}");
+            assert!(theta_prime(&a, 0.3).abs_diff_eq(Quat::from_rotation_y(0.5), 1e-4), "{boundary:?}");
+        }
+
+        let a = warp(WarpBoundary::Cyclic, Quat::from_rotation_y(-0.5));
+        let b = warp(WarpBoundary::Cyclic, Quat::from_rotation_x(1.0));
+        assert!(!theta_prime(&a, 0.1).abs_diff_eq(theta_prime(&b, 0.1), 1e-3));
+    }
+
+    #[test]
+    fn g_controls() {
+        let points = vec![Vec2::new(0.2, 0.3), Vec2::new(0.6, 0.5)];
+        let cyclic = super::g_controls(points.clone(), WarpBoundary::Cyclic, 1.0);
+        assert_eq!(cyclic, [points[0], points[0], points[1], Vec2::new(1.2, 1.3)]);
+        let extrapolated = [Vec2::new(-0.2, 0.1), points[0], points[1], Vec2::new(1.0, 0.7)];
+        for boundary in [WarpBoundary::Clamped, WarpBoundary::Natural] {
+            let controls = super::g_controls(points.clone(), boundary, 1.0);
+            assert!(controls.iter().zip(extrapolated).all(|(a, b)| a.abs_diff_eq(b, 1e-5)), "{controls:?}");
+        }
+        assert_eq!(super::g_controls(vec![Vec2::ONE], WarpBoundary::Natural, 1.0).len(), 4);
+    }
+
+    #[test]
+    fn joint_limits() {
+        let limit = JointLimit { min_twist: -0.1, max_twist: 0.1, ..Default::default() };
+        let root = EntityPath { parts: vec![Name::new("root")] };
+        let mut skeleton = Skeleton::new(vec![root], vec![Transform::IDENTITY]);
+        skeleton.set_limit(0, Some(limit));
+        let warp = warp(WarpBoundary::Clamped, Quat::from_rotation_y(-0.5)).with_joint_limits(&skeleton);
+        for t in [0.3, 0.5, 0.7] {
+            let rotation = warp.theta_blend(&warp.curves[0], t, Quat::IDENTITY);
+            assert!(limit.contains(rotation), "{t}: {rotation}");
+        }
+        // Outside the warp, the limited warp blends back to the animation
+        let rotation = warp.theta_blend(&warp.curves[0], 0.0, Quat::from_rotation_y(1.0));
+        assert!(rotation.abs_diff_eq(Quat::from_rotation_y(1.0), 1e-4));
+    }
+}
@@ -0,0 +1,690 @@
+//! Poses sampled from [`AnimationClip`]s, independent of the ECS.
+
+use bevy::core::Name;
+use bevy::math::{Quat, Vec3, Vec4};
+use bevy::transform::components::Transform;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::{AnimationClip, EntityPath, Keyframes, MotionWarpClip, Skeleton, VariableCurve};
+
+/// The local transform of a joint sampled from an [`AnimationClip`].
+///
+/// Properties that no curve animates at the sampled time are `None`, and are left untouched
+/// when the pose is applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct JointPose {
+    pub translation: Option<Vec3>,
+    pub rotation: Option<Quat>,
+    pub scale: Option<Vec3>,
+}
+
+impl JointPose {
+    /// Blends `transform` towards this pose by `weight`.
+    pub fn apply(&self, transform: &mut Transform, weight: f32) {
+        if let Some(translation) = self.translation {
+            transform.translation = transform.translation.lerp(translation, weight);
+        }
+        if let Some(rotation) = self.rotation {
+            transform.rotation = transform.rotation.slerp(rotation, weight);
+        }
+        if let Some(scale) = self.scale {
+            transform.scale = transform.scale.lerp(scale, weight);
+        }
+    }
+}
+
+/// The [`JointPose`] of every joint animated by an [`AnimationClip`], at some time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pose {
+    pub joints: HashMap<EntityPath, JointPose>,
+}
+
+impl Pose {
+    /// The pose of the joint at `path`, if it's animated.
+    pub fn get(&self, path: &EntityPath) -> Option<&JointPose> {
+        self.joints.get(path)
+    }
+
+    /// The weighted blend of `poses`.
+    ///
+    /// Each property of a joint is blended between the poses that set it, with their weights
+    /// renormalized, so joints and properties missing from some poses aren't pulled towards
+    /// the rest pose. Rotations are averaged in the hemisphere of the first one.
+    pub fn blend(poses: &[(&Pose, f32)]) -> Pose {
+        let mut sums: HashMap<&EntityPath, [(Vec4, f32); 3]> = HashMap::new();
+        for (pose, weight) in poses {
+            for (path, joint) in &pose.joints {
+                let [translation, rotation, scale] = sums.entry(path).or_default();
+                if let Some(t) = joint.translation {
+                    translation.0 += t.extend(0.0) * *weight;
+                    translation.1 += weight;
+                }
+                if let Some(r) = joint.rotation {
+                    let r = Vec4::from(r);
+                    let sign = if rotation.1 > 0.0 && rotation.0.dot(r) < 0.0 { -1.0 } else { 1.0 };
+                    rotation.0 += r * sign * *weight;
+                    rotation.1 += weight;
+                }
+                if let Some(s) = joint.scale {
+                    scale.0 += s.extend(0.0) * *weight;
+                    scale.1 += weight;
+                }
+            }
+        }
+
+        let joints = sums
+            .into_iter()
+            .map(|(path, [translation, rotation, scale])| {
+                let joint = JointPose {
+                    translation: (translation.1 > 0.0).then(|| (translation.0 / translation.1).truncate()),
+                    rotation: (rotation.1 > 0.0).then(|| Quat::from_vec4(rotation.0).normalize()),
+                    scale: (scale.1 > 0.0).then(|| (scale.0 / scale.1).truncate()),
+                };
+                (path.clone(), joint)
+            })
+            .collect();
+        Pose { joints }
+    }
+
+    /// The difference between this pose and `reference`, to be applied with [`Pose::add`].
+    ///
+    /// Only properties set in both poses are kept. Scale components that are zero in `reference`
+    /// are kept as they are.
+    pub fn difference(&self, reference: &Pose) -> Pose {
+        let joints = self
+            .joints
+            .iter()
+            .filter_map(|(path, joint)| {
+                let base = reference.joints.get(path)?;
+                let difference = JointPose {
+                    translation: joint.translation.zip(base.translation).map(|(t, b)| t - b),
+                    rotation: joint.rotation.zip(base.rotation).map(|(r, b)| b.inverse() * r),
+                    // Hidden joints are often scaled to zero, keep their scale rather than dividing by it
+                    scale: joint
+                        .scale
+                        .zip(base.scale)
+                        .map(|(s, b)| Vec3::select(b.abs().cmpgt(Vec3::splat(f32::EPSILON)), s / b, s)),
+                };
+                Some((path.clone(), difference))
+            })
+            .collect();
+        Pose { joints }
+    }
+
+    /// This pose with a difference from [`Pose::difference`] added on top, scaled by `weight`.
+    ///
+    /// Properties of `additive` that this pose doesn't set are ignored.
+    pub fn add(&self, additive: &Pose, weight: f32) -> Pose {
+        let mut pose = self.clone();
+        for (path, joint) in &mut pose.joints {
+            let Some(difference) = additive.joints.get(path) else { continue };
+            if let (Some(t), Some(d)) = (&mut joint.translation, difference.translation) {
+                *t += d * weight;
+            }
+            if let (Some(r), Some(d)) = (&mut joint.rotation, difference.rotation) {
+                *r = (*r * Quat::IDENTITY.slerp(d, weight)).normalize();
+            }
+            if let (Some(s), Some(d)) = (&mut joint.scale, difference.scale) {
+                *s *= Vec3::ONE.lerp(d, weight);
+            }
+        }
+        pose
+    }
+
+    /// Only the joints of this pose whose path is accepted by `mask`.
+    pub fn masked(&self, mut mask: impl FnMut(&EntityPath) -> bool) -> Pose {
+        let joints = self
+            .joints
+            .iter()
+            .filter(|(path, _)| mask(path))
+            .map(|(path, joint)| (path.clone(), *joint))
+            .collect();
+        Pose { joints }
+    }
+
+    /// This pose reflected by `mirror`, with left and right joints swapped.
+    pub fn mirrored(&self, mirror: &PoseMirror) -> Pose {
+        let joints = self
+            .joints
+            .iter()
+            .map(|(path, joint)| (mirror.mirror_path(path), mirror.mirror_joint(path, joint)))
+            .collect();
+        Pose { joints }
+    }
+}
+
+/// How to mirror poses and clips from one side of a skeleton to the other.
+///
+/// Joints are reflected across a plane of the space of the joints without parents, and
+/// swapped with the joint on the other side by name. Without a skeleton, the local axes of
+/// each joint are assumed to be the reflection of the axes of the joint on the other side,
+/// and the axes of the joints in the middle to be symmetric across the plane, so that the
+/// same reflection applies in the local space of every joint. Rigs whose joints have the same
+/// orientation on both sides, such as the fox, are mirrored with [`PoseMirror::with_skeleton`],
+/// which corrects the reflection of each joint with its rest pose.
+#[derive(Clone, Debug)]
+pub struct PoseMirror {
+    /// Normal of the reflection plane, in the space of the joints without parents.
+    pub normal: Vec3,
+    /// Words of joint names swapped between sides, such as `("Left", "Right")` or `("L", "R")`.
+    ///
+    /// Words are delimited by the ends of the name, by characters other than letters, such as
+    /// `_` or digits, and by changes from lower to upper case, so `b_LeftLeg_L` becomes
+    /// `b_RightLeg_R`.
+    pub name_pairs: Vec<(String, String)>,
+    /// Rotation from the reflected model space rest rotation of each joint to the rest rotation
+    /// of the joint on the other side, and the same rotation for its parent.
+    rest_corrections: HashMap<EntityPath, (Quat, Quat)>,
+}
+
+impl Default for PoseMirror {
+    fn default() -> Self {
+        PoseMirror {
+            normal: Vec3::X,
+            name_pairs: [("Left", "Right"), ("left", "right"), ("L", "R"), ("l", "r")]
+                .map(|(a, b)| (a.to_string(), b.to_string()))
+                .to_vec(),
+            rest_corrections: HashMap::new(),
+        }
+    }
+}
+
+impl PoseMirror {
+    /// This mirror, reflecting the joints of `skeleton` in model space so that its rest pose
+    /// mirrors to itself, whatever the orientation of the joints.
+    ///
+    /// The skeleton must be symmetric in its rest pose. Joints of the skeleton whose mirrored
+    /// name isn't in it are matched with a joint that only differs by a trailing `_<index>`.
+    pub fn with_skeleton(mut self, skeleton: &Skeleton) -> Self {
+        let stripped =
+            |path: &EntityPath| path.parts.iter().map(|name| strip_index(name.as_str()).to_string()).collect();
+        let indices: HashMap<Vec<String>, usize> =
+            skeleton.paths().iter().enumerate().map(|(index, path)| (stripped(path), index)).collect();
+        let model = skeleton.model_rest_transforms();
+        let corrections: Vec<Quat> = skeleton
+            .paths()
+            .iter()
+            .zip(&model)
+            .map(|(path, transform)| {
+                let mirrored = self.mirror_path(path);
+                let other = skeleton.index_of(&mirrored).or_else(|| indices.get(&stripped(&mirrored)).copied());
+                other.map_or(Quat::IDENTITY, |other| {
+                    (self.mirror_rotation(transform.rotation).inverse() * model[other].rotation).normalize()
+                })
+            })
+            .collect();
+        self.rest_corrections = skeleton
+            .paths()
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let parent = skeleton.parents()[index].map_or(Quat::IDENTITY, |parent| corrections[parent]);
+                (path.clone(), (parent, corrections[index]))
+            })
+            .collect();
+        self
+    }
+
+    /// The name of the joint on the other side, or `name` if it's in the middle.
+    ///
+    /// Every word of `name_pairs` is swapped at once, longer words first.
+    pub fn mirror_name(&self, name: &str) -> String {
+        let mut words: Vec<(&str, &str)> = self
+            .name_pairs
+            .iter()
+            .flat_map(|(a, b)| [(a.as_str(), b.as_str()), (b.as_str(), a.as_str())])
+            .filter(|(word, _)| !word.is_empty())
+            .collect();
+        words.sort_by_key(|(word, _)| std::cmp::Reverse(word.len()));
+
+        let mut mirrored = String::with_capacity(name.len());
+        let mut index = 0;
+        while let Some(character) = name[index..].chars().next() {
+            let swapped = words
+                .iter()
+                .find(|(word, _)| name[index..].starts_with(word) && is_word(name, index, index + word.len()));
+            match swapped {
+                Some((word, other)) => {
+                    mirrored.push_str(other);
+                    index += word.len();
+                }
+                None => {
+                    mirrored.push(character);
+                    index += character.len_utf8();
+                }
+            }
+        }
+        mirrored
+    }
+
+    /// The path of the joint on the other side.
+    pub fn mirror_path(&self, path: &EntityPath) -> EntityPath {
+        EntityPath {
+            parts: path.parts.iter().map(|part| Name::new(self.mirror_name(part.as_str()))).collect(),
+        }
+    }
+
+    /// Reflects a translation across the plane.
+    pub fn mirror_translation(&self, translation: Vec3) -> Vec3 {
+        let normal = self.normal.normalize();
+        translation - 2.0 * translation.dot(normal) * normal
+    }
+
+    /// Reflects a rotation across the plane.
+    pub fn mirror_rotation(&self, rotation: Quat) -> Quat {
+        // The axis of a rotation is a pseudovector, so it flips along with the reflection
+        let axis = -self.mirror_translation(rotation.xyz());
+        Quat::from_xyzw(axis.x, axis.y, axis.z, rotation.w)
+    }
+
+    /// Reflects the local transform of the joint at `path` into the local transform of the
+    /// joint on the other side.
+    pub fn mirror_joint(&self, path: &EntityPath, joint: &JointPose) -> JointPose {
+        JointPose {
+            translation: joint.translation.map(|t| self.mirror_local_translation(path, t)),
+            rotation: joint.rotation.map(|r| self.mirror_local_rotation(path, r)),
+            scale: joint.scale,
+        }
+    }
+
+    fn mirror_local_translation(&self, path: &EntityPath, translation: Vec3) -> Vec3 {
+        let (parent, _) = self.rest_corrections.get(path).copied().unwrap_or_default();
+        parent.inverse() * self.mirror_translation(translation)
+    }
+
+    fn mirror_local_rotation(&self, path: &EntityPath, rotation: Quat) -> Quat {
+        match self.rest_corrections.get(path) {
+            Some((parent, joint)) => (parent.inverse() * self.mirror_rotation(rotation) * *joint).normalize(),
+            None => self.mirror_rotation(rotation),
+        }
+    }
+}
+
+/// Is `name[start..end]` a whole word, delimited by the ends of `name`, by characters other
+/// than letters, or by changes from lower to upper case?
+fn is_word(name: &str, start: usize, end: usize) -> bool {
+    let word = &name[start..end];
+    let (Some(first), Some(last)) = (word.chars().next(), word.chars().next_back()) else { return false };
+    let starts = name[..start]
+        .chars()
+        .next_back()
+        .map_or(true, |before| !before.is_alphabetic() || (before.is_lowercase() && first.is_uppercase()));
+    let ends = name[end..]
+        .chars()
+        .next()
+        .map_or(true, |after| !after.is_alphabetic() || (last.is_lowercase() && after.is_uppercase()));
+    starts && ends
+}
+
+/// `name` without a trailing `_<index>`, which some exporters add to every joint name.
+fn strip_index(name: &str) -> &str {
+    match name.rsplit_once('_') {
+        Some((base, index)) if !index.is_empty() && index.bytes().all(|byte| byte.is_ascii_digit()) => base,
+        _ => name,
+    }
+}
+
+impl AnimationClip {
+    /// A copy of this clip for the other side of `mirror`'s plane, with every path mapped to
+    /// the joint on the other side and every keyframe reflected by [`PoseMirror::mirror_joint`].
+    ///
+    /// Mirrored names that aren't in this clip are matched with a joint of the clip that only
+    /// differs by a trailing `_<index>`, such as `b_RightHand_08` for `b_RightHand_011`, if it's
+    /// the only such joint.
+    pub fn mirrored(&self, mirror: &PoseMirror) -> AnimationClip {
+        let names: HashSet<&str> = self.paths().keys().flat_map(|path| &path.parts).map(Name::as_str).collect();
+        // Names without their index, or `None` if several joints share it, like `Finger_1` and `Finger_2`
+        let mut stripped_names: HashMap<&str, Option<&str>> = HashMap::new();
+        for name in &names {
+            stripped_names.entry(strip_index(name)).and_modify(|unique| *unique = None).or_insert(Some(name));
+        }
+        let mirror_name = |name: &Name| {
+            let mirrored = mirror.mirror_name(name.as_str());
+            if names.contains(mirrored.as_str()) {
+                return Name::new(mirrored);
+            }
+            match stripped_names.get(strip_index(&mirrored)) {
+                Some(Some(name)) => Name::new(name.to_string()),
+                _ => Name::new(mirrored),
+            }
+        };
+
+        let mut paths: Vec<_> = self.paths().iter().collect();
+        paths.sort_unstable_by_key(|(_, bone_id)| **bone_id);
+
+        let mut mirrored = AnimationClip::default();
+        for (path, bone_id) in paths {
+            let mirrored_path = EntityPath { parts: path.parts.iter().map(mirror_name).collect() };
+            for curve in &self.curves()[*bone_id] {
+                let keyframes = match curve.keyframes.decompressed() {
+                    Keyframes::Rotation(rotations) => Keyframes::Rotation(
+                        rotations.into_iter().map(|r| mirror.mirror_local_rotation(path, r)).collect(),
+                    ),
+                    Keyframes::Translation(translations) => Keyframes::Translation(
+                        translations.into_iter().map(|t| mirror.mirror_local_translation(path, t)).collect(),
+                    ),
+                    keyframes => keyframes,
+                };
+                let keyframe_timestamps = curve.keyframe_timestamps.clone();
+                mirrored.add_curve_to_path(mirrored_path.clone(), VariableCurve { keyframe_timestamps, keyframes });
+            }
+        }
+        mirrored.copy_property_curves(self, |path| EntityPath { parts: path.parts.iter().map(mirror_name).collect() });
+        mirrored.duration = self.duration();
+        mirrored
+    }
+
+    /// Samples every joint of this clip at `time`, in the same way as [`crate::animation_player`].
+    ///
+    /// `time` isn't wrapped, so repeating animations should wrap it by [`AnimationClip::duration`].
+    pub fn sample_pose(&self, time: f32) -> Pose {
+        Pose {
+            joints: self
+                .paths()
+                .iter()
+                .map(|(path, bone_id)| (path.clone(), self.sample_joint(*bone_id, time).unwrap()))
+                .collect(),
+        }
+    }
+
+    /// Samples every joint of this clip at `time` with `warp` applied.
+    pub fn sample_pose_warped(&self, time: f32, warp: &MotionWarpClip) -> Pose {
+        let mut pose = self.sample_pose(time);
+        for (path, joint) in &mut pose.joints {
+            warp.warp_joint(path, time, joint);
+        }
+        pose
+    }
+}
+
+impl MotionWarpClip {
+    /// Applies this warp to the rotation of the joint at `path`, if it's warped at `time`.
+    pub fn warp_joint(&self, path: &EntityPath, time: f32, joint: &mut JointPose) {
+        if !(self.start_time <= time && time <= self.end_time) {
+            return;
+        }
+        let (Some(theta), Some(warp_curve)) = (
+            joint.rotation,
+            self.paths.get(path).and_then(|id| self.curves.get(*id)),
+        ) else {
+            return;
+        };
+        joint.rotation = Some(self.theta_blend(warp_curve, time, theta));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> EntityPath {
+        EntityPath { parts: vec![Name::new(name.to_string())] }
+    }
+
+    fn test_clip() -> AnimationClip {
+        let mut clip = AnimationClip::default();
+        clip.add_curve_to_path(
+            path("a"),
+            VariableCurve {
+                keyframe_timestamps: vec![0.0, 1.0],
+                keyframes: Keyframes::Rotation(vec![Quat::IDENTITY, Quat::from_rotation_y(1.0)]),
+            },
+        );
+        clip.add_curve_to_path(
+            path("a"),
+            VariableCurve {
+                keyframe_timestamps: vec![0.5, 1.0],
+                keyframes: Keyframes::Translation(vec![Vec3::ZERO, Vec3::X]),
+            },
+        );
+        clip.add_curve_to_path(
+            path("b"),
+            VariableCurve {
+                keyframe_timestamps: vec![0.0],
+                keyframes: Keyframes::Scale(vec![Vec3::splat(2.0)]),
+            },
+        );
+        clip
+    }
+
+    #[test]
+    fn sample_pose() {
+        let pose = test_clip().sample_pose(0.75);
+
+        let a = pose.get(&path("a")).unwrap();
+        assert!(a.rotation.unwrap().abs_diff_eq(Quat::from_rotation_y(0.75), 1e-5));
+        assert!(a.translation.unwrap().abs_diff_eq(Vec3::new(0.5, 0.0, 0.0), 1e-5));
+        assert_eq!(a.scale, None);
+        assert_eq!(pose.get(&path("b")).unwrap().scale, Some(Vec3::splat(2.0)));
+    }
+
+    #[test]
+    fn sample_before_curve_start() {
+        let clip = test_clip();
+        let a = clip.sample_pose(0.25).joints[&path("a")];
+
+        assert!(a.rotation.is_some());
+        assert_eq!(a.translation, None);
+        assert_eq!(clip.get_joint_rotation_at(&path("a"), 0.25), a.rotation.unwrap());
+    }
+
+    #[test]
+    fn apply_joint_pose() {
+        let mut transform = Transform::from_scale(Vec3::splat(3.0));
+        let joint = JointPose { translation: Some(Vec3::X), ..Default::default() };
+        joint.apply(&mut transform, 0.5);
+
+        assert_eq!(transform.translation, Vec3::new(0.5, 0.0, 0.0));
+        assert_eq!(transform.scale, Vec3::splat(3.0));
+    }
+
+    fn joint_pose(translation: Vec3, rotation: Quat) -> Pose {
+        let joint = JointPose { translation: Some(translation), rotation: Some(rotation), scale: None };
+        Pose { joints: [(path("a"), joint)].into_iter().collect() }
+    }
+
+    #[test]
+    fn blend_poses() {
+        let a = joint_pose(Vec3::ZERO, Quat::from_rotation_y(0.2));
+        // The same rotation in the other hemisphere
+        let b = joint_pose(Vec3::X, -Quat::from_rotation_y(0.6));
+        let blended = Pose::blend(&[(&a, 1.0), (&b, 1.0)]);
+
+        let joint = blended.joints[&path("a")];
+        assert!(joint.translation.unwrap().abs_diff_eq(Vec3::new(0.5, 0.0, 0.0), 1e-5));
+        assert!(joint.rotation.unwrap().abs_diff_eq(Quat::from_rotation_y(0.4), 1e-5));
+        assert_eq!(joint.scale, None);
+    }
+
+    #[test]
+    fn blend_missing_joints() {
+        let a = joint_pose(Vec3::X, Quat::IDENTITY);
+        let blended = Pose::blend(&[(&a, 0.25), (&Pose::default(), 0.75)]);
+
+        assert_eq!(blended.joints[&path("a")].translation, Some(Vec3::X));
+    }
+
+    #[test]
+    fn difference_then_add() {
+        let reference = joint_pose(Vec3::Y, Quat::from_rotation_x(0.5));
+        let target = joint_pose(Vec3::new(1.0, 1.0, 0.0), Quat::from_rotation_x(0.5) * Quat::from_rotation_z(0.3));
+        let difference = target.difference(&reference);
+
+        let full = reference.add(&difference, 1.0).joints[&path("a")];
+        assert!(full.translation.unwrap().abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-5));
+        assert!(full.rotation.unwrap().abs_diff_eq(target.joints[&path("a")].rotation.unwrap(), 1e-5));
+
+        let half = reference.add(&difference, 0.5).joints[&path("a")];
+        assert!(half.rotation.unwrap().abs_diff_eq(Quat::from_rotation_x(0.5) * Quat::from_rotation_z(0.15), 1e-5));
+
+        // A reference joint scaled to zero doesn't make the difference infinite
+        let scaled = |scale| {
+            let joint = JointPose { scale: Some(scale), ..Default::default() };
+            Pose { joints: [(path("a"), joint)].into_iter().collect() }
+        };
+        let difference = scaled(Vec3::new(2.0, 2.0, 1.0)).difference(&scaled(Vec3::new(0.0, 1.0, 1.0)));
+        assert_eq!(difference.joints[&path("a")].scale, Some(Vec3::new(2.0, 2.0, 1.0)));
+    }
+
+    #[test]
+    fn mask_pose() {
+        let pose = test_clip().sample_pose(0.75);
+        let masked = pose.masked(|path| path.parts[0].as_str() == "b");
+
+        assert_eq!(masked.joints.len(), 1);
+        assert!(masked.get(&path("b")).is_some());
+    }
+
+    #[test]
+    fn mirror_pose() {
+        let mirror = PoseMirror::default();
+        let pose = Pose {
+            joints: [(
+                path("LeftArm"),
+                JointPose {
+                    translation: Some(Vec3::new(1.0, 2.0, 3.0)),
+                    rotation: Some(Quat::from_rotation_y(0.5)),
+                    scale: None,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+        let mirrored = pose.mirrored(&mirror);
+
+        let joint = mirrored.joints[&path("RightArm")];
+        assert_eq!(joint.translation, Some(Vec3::new(-1.0, 2.0, 3.0)));
+        assert!(joint.rotation.unwrap().abs_diff_eq(Quat::from_rotation_y(-0.5), 1e-6));
+        assert_eq!(mirror.mirror_name("Spine"), "Spine");
+        assert_eq!(pose.mirrored(&mirror).mirrored(&mirror), pose);
+    }
+
+    #[test]
+    fn mirror_names() {
+        let mirror = PoseMirror::default();
+        for (name, mirrored) in [
+            ("b_LeftLeg_L", "b_RightLeg_R"),
+            ("b_RightLeg_L", "b_LeftLeg_R"),
+            ("Hand_L_end", "Hand_R_end"),
+            ("mixamorig:LeftHandIndex1", "mixamorig:RightHandIndex1"),
+            ("upperarm_l", "upperarm_r"),
+            ("left_hand", "right_hand"),
+            ("Lower_Lip", "Lower_Lip"),
+            ("Cleft", "Cleft"),
+        ] {
+            assert_eq!(mirror.mirror_name(name), mirrored);
+            assert_eq!(mirror.mirror_name(mirrored), name);
+        }
+    }
+
+    #[test]
+    fn mirror_with_skeleton() {
+        let mirror = PoseMirror::default();
+        let path =
+            |parts: &[&str]| EntityPath { parts: parts.iter().map(|part| Name::new(part.to_string())).collect() };
+        // The joints of both sides have the same orientation up to `twist`, and the hip isn't
+        // symmetric, so joints can't be reflected in their local space
+        let hip =
+            Transform::from_xyz(0.0, 2.0, 0.0).with_rotation(Quat::from_rotation_z(0.5) * Quat::from_rotation_y(1.2));
+        let twist = Quat::from_rotation_x(0.7);
+        let left_leg = Transform::from_xyz(0.5, -0.2, 0.3).with_rotation(Quat::from_rotation_z(-0.4));
+        let left_position = hip.transform_point(left_leg.translation);
+        let right_position = hip.rotation.inverse() * (mirror.mirror_translation(left_position) - hip.translation);
+        let right_leg = Transform::from_translation(right_position)
+            .with_rotation(hip.rotation.inverse() * mirror.mirror_rotation(hip.rotation * left_leg.rotation) * twist);
+        let left_foot = Transform::from_xyz(0.0, -1.0, 0.2);
+        let right_foot = Transform::from_translation(twist.inverse() * mirror.mirror_translation(left_foot.translation))
+            .with_rotation(twist.inverse());
+        let skeleton = Skeleton::new(
+            vec![
+                path(&["hip"]),
+                path(&["hip", "LeftLeg"]),
+                path(&["hip", "LeftLeg", "LeftFoot"]),
+                path(&["hip", "RightLeg"]),
+                path(&["hip", "RightLeg", "RightFoot"]),
+            ],
+            vec![hip, left_leg, left_foot, right_leg, right_foot],
+        );
+        let mirror = mirror.with_skeleton(&skeleton);
+
+        let positions = |pose: &Pose| {
+            let mut model: Vec<Transform> = Vec::new();
+            for (index, path) in skeleton.paths().iter().enumerate() {
+                let mut local = skeleton.rest_transforms()[index];
+                if let Some(joint) = pose.get(path) {
+                    joint.apply(&mut local, 1.0);
+                }
+                model.push(skeleton.parents()[index].map_or(local, |parent| model[parent].mul_transform(local)));
+            }
+            model.iter().map(|transform| transform.translation).collect::<Vec<Vec3>>()
+        };
+        let rest = skeleton.rest_pose();
+        let rest_positions = positions(&rest);
+        assert!(positions(&rest.mirrored(&mirror)).iter().zip(&rest_positions).all(|(a, b)| a.abs_diff_eq(*b, 1e-4)));
+
+        let joint = |rotation| JointPose { rotation: Some(rotation), ..Default::default() };
+        let pose = Pose {
+            joints: [
+                (path(&["hip"]), joint(hip.rotation * Quat::from_rotation_x(0.3))),
+                (path(&["hip", "LeftLeg"]), joint(left_leg.rotation * Quat::from_rotation_y(0.8))),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let (original, mirrored) = (positions(&pose), positions(&pose.mirrored(&mirror)));
+        // The feet are where the other foot was, on the other side
+        for (left, right) in [(2, 4), (4, 2), (1, 3)] {
+            let reflected = mirror.mirror_translation(original[left]);
+            assert!(mirrored[right].abs_diff_eq(reflected, 1e-4), "{} != {reflected}", mirrored[right]);
+        }
+        let twice = pose.mirrored(&mirror).mirrored(&mirror);
+        for (path, joint) in &pose.joints {
+            assert!(twice.joints[path].rotation.unwrap().angle_between(joint.rotation.unwrap()) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn mirror_clip() {
+        let mirror = PoseMirror::default();
+        let mut clip = AnimationClip::default();
+        for (name, angle) in [("b_LeftHand_011", 0.5), ("b_RightHand_08", -0.2), ("b_Spine_02", 0.3)] {
+            clip.add_curve_to_path(
+                path(name),
+                VariableCurve {
+                    keyframe_timestamps: vec![0.0, 1.0],
+                    keyframes: Keyframes::Rotation(vec![Quat::IDENTITY, Quat::from_rotation_y(angle)]),
+                },
+            );
+        }
+        clip.add_curve_to_path(
+            path("b_Spine_02"),
+            VariableCurve {
+                keyframe_timestamps: vec![0.0, 2.0],
+                keyframes: Keyframes::Translation(vec![Vec3::ZERO, Vec3::new(1.0, 0.0, 1.0)]),
+            },
+        );
+        let mirrored = clip.mirrored(&mirror);
+
+        // Sides are swapped, including joints with different indices
+        let pose = mirrored.sample_pose(0.5);
+        assert_eq!(pose.joints.len(), 3);
+        let rotation = |name| pose.get(&path(name)).unwrap().rotation.unwrap();
+        assert!(rotation("b_RightHand_08").abs_diff_eq(Quat::from_rotation_y(-0.25), 1e-5));
+        assert!(rotation("b_LeftHand_011").abs_diff_eq(Quat::from_rotation_y(0.1), 1e-5));
+        assert!(rotation("b_Spine_02").abs_diff_eq(Quat::from_rotation_y(-0.15), 1e-5));
+        let translation = pose.get(&path("b_Spine_02")).unwrap().translation.unwrap();
+        assert!(translation.abs_diff_eq(Vec3::new(-0.25, 0.0, 0.25), 1e-5));
+        assert_eq!(mirrored.duration(), clip.duration());
+
+        // Indices don't pick one of several joints with the same name
+        for name in ["RightFinger_1", "RightFinger_2", "LeftFinger_5", "LeftFinger_6"] {
+            clip.add_curve_to_path(
+                path(name),
+                VariableCurve { keyframe_timestamps: vec![0.0], keyframes: Keyframes::Rotation(vec![Quat::IDENTITY]) },
+            );
+        }
+        let mirrored = clip.mirrored(&mirror);
+        assert!(mirrored.paths().contains_key(&path("RightFinger_5")));
+        assert!(mirrored.paths().contains_key(&path("LeftFinger_1")));
+    }
+}
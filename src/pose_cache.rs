@@ -0,0 +1,291 @@
+//! Sharing sampled poses between [`crate::AnimationPlayer`]s playing the same clip.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use bevy::asset::{AssetEvent, HandleId};
+use bevy::ecs::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::{AnimationClip, JointPose, MotionWarpClip};
+
+/// Key of a shared pose: clip, warp and quantized time.
+type PoseKey = (HandleId, Option<HandleId>, i64);
+
+/// A shared pose, with the last frame it was used in.
+struct CachedPose {
+    pose: Arc<Vec<JointPose>>,
+    last_used: AtomicU64,
+}
+
+/// When inserted as a resource, [`crate::AnimationPlayer`]s sample each clip (and warp) once
+/// per quantized time, and share the resulting pose.
+///
+/// Players interpolate between the poses sampled at the quantized times just before and just
+/// after their elapsed time, so crowds playing the same clip at nearly the same time cost about
+/// as much as a single player. Poses are dropped when they weren't used in the previous frame,
+/// or when their clip or warp asset changes.
+#[derive(Resource)]
+pub struct AnimationPoseCache {
+    quantization: f32,
+    frame: u64,
+    poses: RwLock<HashMap<PoseKey, CachedPose>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl Default for AnimationPoseCache {
+    fn default() -> Self {
+        AnimationPoseCache::new(1.0 / 60.0)
+    }
+}
+
+impl AnimationPoseCache {
+    /// A cache sharing poses sampled every `quantization` seconds.
+    pub fn new(quantization: f32) -> Self {
+        assert!(quantization > 0.0);
+        AnimationPoseCache {
+            quantization,
+            frame: 0,
+            poses: Default::default(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of poses taken from the cache and number sampled, since the cache was created.
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+
+    /// Number of poses in the cache.
+    pub fn len(&self) -> usize {
+        self.poses.read().unwrap().len()
+    }
+
+    /// Is the cache empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every cached pose.
+    pub fn clear(&mut self) {
+        self.poses.get_mut().unwrap().clear();
+    }
+
+    /// The poses of every bone of `clip` (indexed by bone ID) around `elapsed`, with `warp` applied.
+    ///
+    /// Repeating clips are interpolated towards their first pose after the last quantized time.
+    pub(crate) fn sample(
+        &self,
+        clip_id: HandleId,
+        clip: &AnimationClip,
+        warp: Option<(HandleId, &MotionWarpClip)>,
+        elapsed: f32,
+        repeat: bool,
+    ) -> CachedPoses {
+        let steps = elapsed / self.quantization;
+        let step = steps.floor();
+        let (mut after, mut fraction) = (step as i64 + 1, steps - step);
+        // Wrap to the first step at the loop seam, which is closer than a whole step if the
+        // duration isn't a multiple of the quantization
+        let last_time = step * self.quantization;
+        if repeat && (step + 1.0) * self.quantization >= clip.duration() && clip.duration() > last_time {
+            after = 0;
+            fraction = (elapsed - last_time) / (clip.duration() - last_time);
+        }
+        CachedPoses {
+            before: self.sample_step(clip_id, clip, warp, step as i64),
+            after: self.sample_step(clip_id, clip, warp, after),
+            fraction,
+        }
+    }
+
+    fn sample_step(
+        &self,
+        clip_id: HandleId,
+        clip: &AnimationClip,
+        warp: Option<(HandleId, &MotionWarpClip)>,
+        step: i64,
+    ) -> Arc<Vec<JointPose>> {
+        let key = (clip_id, warp.map(|(id, _)| id), step);
+        if let Some(cached) = self.poses.read().unwrap().get(&key) {
+            cached.last_used.store(self.frame, Ordering::Relaxed);
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return cached.pose.clone();
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let time = step as f32 * self.quantization;
+        let mut pose = vec![JointPose::default(); clip.curves().len()];
+        for (path, bone_id) in clip.paths() {
+            let mut joint = clip.sample_joint(*bone_id, time).unwrap();
+            if let Some((_, warp)) = warp {
+                warp.warp_joint(path, time, &mut joint);
+            }
+            pose[*bone_id] = joint;
+        }
+        let pose = Arc::new(pose);
+        let cached = CachedPose { pose: pose.clone(), last_used: AtomicU64::new(self.frame) };
+        self.poses.write().unwrap().insert(key, cached);
+        pose
+    }
+
+    /// Drops the poses that weren't used since the last call, which are the poses of the
+    /// previous frame.
+    fn evict_unused(&mut self) {
+        let frame = self.frame;
+        self.poses.get_mut().unwrap().retain(|_, cached| *cached.last_used.get_mut() == frame);
+        self.frame += 1;
+    }
+
+    fn remove(&mut self, id: HandleId) {
+        self.poses
+            .get_mut()
+            .unwrap()
+            .retain(|(clip, warp, _), _| *clip != id && *warp != Some(id));
+    }
+}
+
+/// The cached poses at the quantized times around a time.
+pub(crate) struct CachedPoses {
+    before: Arc<Vec<JointPose>>,
+    after: Arc<Vec<JointPose>>,
+    /// Where the time is between the two quantized times, from 0 to 1.
+    fraction: f32,
+}
+
+impl CachedPoses {
+    /// The pose of the bone `bone_id`, interpolated between the two cached poses.
+    ///
+    /// Properties set in only one of them, because their curve starts or ends in between, are
+    /// taken from that one.
+    pub(crate) fn joint(&self, bone_id: usize) -> JointPose {
+        let (before, after) = (self.before[bone_id], self.after[bone_id]);
+        let fraction = self.fraction;
+        JointPose {
+            translation: interpolate(before.translation, after.translation, |a, b| a.lerp(b, fraction)),
+            rotation: interpolate(before.rotation, after.rotation, |a, b| a.slerp(b, fraction)),
+            scale: interpolate(before.scale, after.scale, |a, b| a.lerp(b, fraction)),
+        }
+    }
+}
+
+fn interpolate<T>(before: Option<T>, after: Option<T>, interpolate: impl FnOnce(T, T) -> T) -> Option<T> {
+    match (before, after) {
+        (Some(before), Some(after)) => Some(interpolate(before, after)),
+        (before, after) => before.or(after),
+    }
+}
+
+/// System that drops the poses of the [`AnimationPoseCache`] that weren't used in the previous
+/// frame, and the poses of modified or removed assets.
+pub fn invalidate_animation_pose_cache(
+    cache: Option<ResMut<AnimationPoseCache>>,
+    mut clip_events: EventReader<AssetEvent<AnimationClip>>,
+    mut warp_events: EventReader<AssetEvent<MotionWarpClip>>,
+) {
+    let modified = clip_events
+        .iter()
+        .filter_map(modified_asset)
+        .chain(warp_events.iter().filter_map(modified_asset));
+    let Some(mut cache) = cache else {
+        return;
+    };
+    for id in modified.collect::<Vec<_>>() {
+        cache.remove(id);
+    }
+    cache.evict_unused();
+}
+
+fn modified_asset<T: bevy::asset::Asset>(event: &AssetEvent<T>) -> Option<HandleId> {
+    match event {
+        AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => Some(handle.id()),
+        AssetEvent::Created { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::app::App;
+    use bevy::asset::{AssetPlugin, Assets, Handle};
+    use bevy::core::Name;
+    use bevy::math::Vec3;
+    use bevy::time::{Time, TimeUpdateStrategy};
+    use bevy::transform::components::Transform;
+    use bevy::MinimalPlugins;
+
+    use super::*;
+    use crate::{AnimationPlayer, AnimationPlugin, EntityPath, Keyframes, VariableCurve};
+
+    fn test_clip() -> AnimationClip {
+        let mut clip = AnimationClip::default();
+        clip.add_curve_to_path(
+            EntityPath { parts: vec![Name::new("root")] },
+            VariableCurve {
+                keyframe_timestamps: vec![0.0, 1.0],
+                keyframes: Keyframes::Translation(vec![Vec3::ZERO, Vec3::X]),
+            },
+        );
+        clip
+    }
+
+    #[test]
+    fn shares_quantized_poses() {
+        let mut cache = AnimationPoseCache::new(0.1);
+        let clip = test_clip();
+        let id = Handle::<AnimationClip>::weak(HandleId::random::<AnimationClip>()).id();
+
+        let a = cache.sample(id, &clip, None, 0.51, false);
+        let b = cache.sample(id, &clip, None, 0.55, false);
+        assert!(Arc::ptr_eq(&a.before, &b.before) && Arc::ptr_eq(&a.after, &b.after));
+        assert_eq!(cache.stats(), (2, 2));
+        // Sampled at the exact time, not at the quantized one
+        assert!(a.joint(0).translation.unwrap().abs_diff_eq(Vec3::new(0.51, 0.0, 0.0), 1e-5));
+        assert!(b.joint(0).translation.unwrap().abs_diff_eq(Vec3::new(0.55, 0.0, 0.0), 1e-5));
+
+        cache.sample(id, &clip, None, 0.7, false);
+        assert_eq!(cache.len(), 4);
+        cache.remove(id);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn wraps_repeating_clips() {
+        let cache = AnimationPoseCache::new(0.3);
+        let clip = test_clip();
+        let id = Handle::<AnimationClip>::weak(HandleId::random::<AnimationClip>()).id();
+
+        // Between the last quantized time, 0.9, and the start of the next loop at 1.0
+        let repeating = cache.sample(id, &clip, None, 0.95, true);
+        assert!(repeating.joint(0).translation.unwrap().abs_diff_eq(Vec3::new(0.45, 0.0, 0.0), 1e-5));
+        // Just before the seam, the pose is nearly the first one, as when sampling the clip directly
+        let seam = cache.sample(id, &clip, None, 0.999, true);
+        assert!(seam.joint(0).translation.unwrap().abs_diff_eq(Vec3::new(0.009, 0.0, 0.0), 1e-4));
+    }
+
+    #[test]
+    fn drops_unused_poses() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(AnimationPlugin::default())
+            .insert_resource(AnimationPoseCache::default());
+        let clip = app.world.resource_mut::<Assets<AnimationClip>>().add(test_clip());
+        let mut player = AnimationPlayer::default();
+        player.play(clip);
+        app.world.spawn((Transform::default(), Name::new("root"), player));
+
+        // Long after the end of the clip, which doesn't repeat
+        let startup = app.world.resource::<Time>().startup();
+        for frame in 1..=300 {
+            let instant = startup + Duration::from_secs_f32(frame as f32 / 60.0);
+            app.insert_resource(TimeUpdateStrategy::ManualInstant(instant));
+            app.update();
+            // The two poses around the time of this frame, and of the previous one
+            assert!(app.world.resource::<AnimationPoseCache>().len() <= 4);
+        }
+    }
+}
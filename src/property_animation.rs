@@ -0,0 +1,248 @@
+//! Animation of the fields of any reflected component or asset, like the intensity of a light or
+//! the colour of a material, by type name and reflection path.
+
+use bevy::math::{Quat, Vec2, Vec3, Vec4};
+use bevy::reflect::{FromReflect, Reflect};
+use bevy::render::color::Color;
+use bevy::utils::HashMap;
+
+use crate::{bevy_animation::keyframe_step, AnimationClip, EntityPath};
+
+/// A value of an animated field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PropertyValue {
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Quat(Quat),
+    /// Colours are interpolated in linear RGBA.
+    Color(Color),
+}
+
+impl PropertyValue {
+    /// Interpolates from this value to `other` by `t`.
+    ///
+    /// Returns `other` if the values aren't of the same type.
+    pub fn lerp(self, other: PropertyValue, t: f32) -> PropertyValue {
+        match (self, other) {
+            (PropertyValue::Float(a), PropertyValue::Float(b)) => PropertyValue::Float(a + (b - a) * t),
+            (PropertyValue::Vec2(a), PropertyValue::Vec2(b)) => PropertyValue::Vec2(a.lerp(b, t)),
+            (PropertyValue::Vec3(a), PropertyValue::Vec3(b)) => PropertyValue::Vec3(a.lerp(b, t)),
+            (PropertyValue::Vec4(a), PropertyValue::Vec4(b)) => PropertyValue::Vec4(a.lerp(b, t)),
+            (PropertyValue::Quat(a), PropertyValue::Quat(b)) => {
+                PropertyValue::Quat(a.normalize().slerp(b.normalize(), t))
+            }
+            (PropertyValue::Color(a), PropertyValue::Color(b)) => {
+                let a = Vec4::from(a.as_linear_rgba_f32());
+                let b = Vec4::from(b.as_linear_rgba_f32());
+                let [red, green, blue, alpha] = a.lerp(b, t).to_array();
+                PropertyValue::Color(Color::rgba_linear(red, green, blue, alpha))
+            }
+            (_, other) => other,
+        }
+    }
+
+    /// The value of a reflected field, if it's of one of the supported types.
+    pub fn from_field(field: &dyn Reflect) -> Option<PropertyValue> {
+        let any = field.as_any();
+        any.downcast_ref::<f32>()
+            .map(|value| PropertyValue::Float(*value))
+            .or_else(|| any.downcast_ref::<Vec2>().map(|value| PropertyValue::Vec2(*value)))
+            .or_else(|| any.downcast_ref::<Vec3>().map(|value| PropertyValue::Vec3(*value)))
+            .or_else(|| any.downcast_ref::<Vec4>().map(|value| PropertyValue::Vec4(*value)))
+            .or_else(|| any.downcast_ref::<Quat>().map(|value| PropertyValue::Quat(*value)))
+            .or_else(|| any.downcast_ref::<Color>().map(|value| PropertyValue::Color(*value)))
+    }
+
+    /// Blends a reflected field towards this value by `weight`.
+    ///
+    /// Returns `false` if the field isn't of the type of this value.
+    pub fn blend_into(self, field: &mut dyn Reflect, weight: f32) -> bool {
+        let value = match PropertyValue::from_field(field) {
+            Some(current) if weight < 1.0 => current.lerp(self, weight),
+            _ => self,
+        };
+        let any = field.as_any_mut();
+        match value {
+            PropertyValue::Float(value) => any.downcast_mut::<f32>().map(|field| *field = value),
+            PropertyValue::Vec2(value) => any.downcast_mut::<Vec2>().map(|field| *field = value),
+            PropertyValue::Vec3(value) => any.downcast_mut::<Vec3>().map(|field| *field = value),
+            PropertyValue::Vec4(value) => any.downcast_mut::<Vec4>().map(|field| *field = value),
+            PropertyValue::Quat(value) => any.downcast_mut::<Quat>().map(|field| *field = value),
+            PropertyValue::Color(value) => any.downcast_mut::<Color>().map(|field| *field = value),
+        }
+        .is_some()
+    }
+}
+
+/// List of keyframes for a field animated by a [`PropertyCurve`], of the type of the field.
+#[derive(Reflect, FromReflect, Clone, Debug)]
+pub enum PropertyKeyframes {
+    Float(Vec<f32>),
+    Vec2(Vec<Vec2>),
+    Vec3(Vec<Vec3>),
+    Vec4(Vec<Vec4>),
+    Quat(Vec<Quat>),
+    Color(Vec<Color>),
+}
+
+impl PropertyKeyframes {
+    /// Number of keyframes.
+    pub fn len(&self) -> usize {
+        match self {
+            PropertyKeyframes::Float(keyframes) => keyframes.len(),
+            PropertyKeyframes::Vec2(keyframes) => keyframes.len(),
+            PropertyKeyframes::Vec3(keyframes) => keyframes.len(),
+            PropertyKeyframes::Vec4(keyframes) => keyframes.len(),
+            PropertyKeyframes::Quat(keyframes) => keyframes.len(),
+            PropertyKeyframes::Color(keyframes) => keyframes.len(),
+        }
+    }
+
+    /// Are there no keyframes?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The keyframe at `index`, if there's one.
+    pub fn get(&self, index: usize) -> Option<PropertyValue> {
+        match self {
+            PropertyKeyframes::Float(keyframes) => keyframes.get(index).copied().map(PropertyValue::Float),
+            PropertyKeyframes::Vec2(keyframes) => keyframes.get(index).copied().map(PropertyValue::Vec2),
+            PropertyKeyframes::Vec3(keyframes) => keyframes.get(index).copied().map(PropertyValue::Vec3),
+            PropertyKeyframes::Vec4(keyframes) => keyframes.get(index).copied().map(PropertyValue::Vec4),
+            PropertyKeyframes::Quat(keyframes) => keyframes.get(index).copied().map(PropertyValue::Quat),
+            PropertyKeyframes::Color(keyframes) => keyframes.get(index).copied().map(PropertyValue::Color),
+        }
+    }
+}
+
+/// Describes how a field of a reflected component or asset should be animated.
+///
+/// The type is found by its name in the [`bevy::ecs::reflect::AppTypeRegistry`]. Components must
+/// be registered with `#[reflect(Component)]`, and assets with `register_asset_reflect`.
+/// `keyframe_timestamps` and `keyframes` should have the same length.
+#[derive(Reflect, FromReflect, Clone, Debug)]
+pub struct PropertyCurve {
+    /// Full type name of the component, like `bevy_pbr::light::PointLight`, as given by
+    /// [`std::any::type_name`]. It can also name an asset, like `bevy_pbr::pbr_material::StandardMaterial`,
+    /// which is then animated through the `Handle` of the target entity. Assets are shared, so this
+    /// changes them for every entity using them.
+    pub component: String,
+    /// Reflection path to the field in the component or asset, like `intensity` or `color`.
+    pub field: String,
+    /// Timestamp for each of the keyframes.
+    pub keyframe_timestamps: Vec<f32>,
+    /// List of the keyframes.
+    pub keyframes: PropertyKeyframes,
+}
+
+impl PropertyCurve {
+    /// The value of the field at a time, clamped to the first and last keyframes.
+    pub fn value_at(&self, time: f32) -> Option<PropertyValue> {
+        let timestamps = &self.keyframe_timestamps;
+        let last = timestamps.len().checked_sub(1)?;
+        let time = time.clamp(timestamps[0], timestamps[last]);
+        if time >= timestamps[last] {
+            return self.keyframes.get(last);
+        }
+        self.sample_with_cursor(time, &mut 0)
+    }
+
+    /// Samples the field at an elapsed time, starting the keyframe search from `cursor`.
+    ///
+    /// Like transform curves, curves that aren't started yet or are finished leave their field
+    /// unset, unless they have a single keyframe.
+    pub(crate) fn sample_with_cursor(&self, elapsed: f32, cursor: &mut usize) -> Option<PropertyValue> {
+        if self.keyframe_timestamps.len() == 1 {
+            return self.keyframes.get(0);
+        }
+        let step_start = keyframe_step(&self.keyframe_timestamps, elapsed, cursor)?;
+        let ts_start = self.keyframe_timestamps[step_start];
+        let ts_end = self.keyframe_timestamps[step_start + 1];
+        let lerp = (elapsed - ts_start) / (ts_end - ts_start);
+        Some(self.keyframes.get(step_start)?.lerp(self.keyframes.get(step_start + 1)?, lerp))
+    }
+}
+
+impl AnimationClip {
+    /// [`PropertyCurve`]s for each animated entity. Indexed by the property ID.
+    #[inline]
+    pub fn property_curves(&self) -> &Vec<Vec<PropertyCurve>> {
+        &self.property_curves
+    }
+
+    /// The [`EntityPath`] of each entity with animated properties, mapped to its property ID.
+    #[inline]
+    pub fn property_paths(&self) -> &HashMap<EntityPath, usize> {
+        &self.property_paths
+    }
+
+    /// Gets the property curves by the [`EntityPath`] of their entity.
+    ///
+    /// Returns `None` if no property of the entity is animated.
+    #[inline]
+    pub fn get_property_curves_by_path(&self, path: &EntityPath) -> Option<&'_ Vec<PropertyCurve>> {
+        self.property_paths.get(path).and_then(|id| self.property_curves.get(*id))
+    }
+
+    /// Add a [`PropertyCurve`] to an [`EntityPath`].
+    pub fn add_property_curve_to_path(&mut self, path: EntityPath, curve: PropertyCurve) {
+        self.duration = self.duration.max(*curve.keyframe_timestamps.last().unwrap_or(&0.0));
+        if let Some(id) = self.property_paths.get(&path) {
+            self.property_curves[*id].push(curve);
+        } else {
+            let id = self.property_curves.len();
+            self.property_curves.push(vec![curve]);
+            self.property_paths.insert(path, id);
+        }
+    }
+
+    /// Adds the property curves of `other` to this clip, at the paths given by `map_path`, for
+    /// clips derived from `other` that only change its transform curves.
+    pub(crate) fn copy_property_curves(&mut self, other: &AnimationClip, map_path: impl Fn(&EntityPath) -> EntityPath) {
+        let mut paths: Vec<_> = other.property_paths.iter().collect();
+        paths.sort_unstable_by_key(|(_, id)| **id);
+        for (path, id) in paths {
+            for curve in &other.property_curves[*id] {
+                self.add_property_curve_to_path(map_path(path), curve.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(keyframes: PropertyKeyframes) -> PropertyCurve {
+        PropertyCurve {
+            component: "Glow".to_string(),
+            field: "intensity".to_string(),
+            keyframe_timestamps: vec![0.0, 1.0],
+            keyframes,
+        }
+    }
+
+    #[test]
+    fn samples_properties() {
+        let float = curve(PropertyKeyframes::Float(vec![1.0, 3.0]));
+        assert_eq!(float.value_at(0.25), Some(PropertyValue::Float(1.5)));
+        assert_eq!(float.value_at(2.0), Some(PropertyValue::Float(3.0)));
+        // Finished curves leave their field unset when played
+        assert_eq!(float.sample_with_cursor(1.0, &mut 0), None);
+
+        let color = curve(PropertyKeyframes::Color(vec![
+            Color::rgba_linear(0.0, 0.0, 1.0, 1.0),
+            Color::rgba_linear(1.0, 0.0, 0.0, 1.0),
+        ]));
+        let Some(PropertyValue::Color(sampled)) = color.value_at(0.5) else { panic!() };
+        assert_eq!(sampled.as_linear_rgba_f32(), [0.5, 0.0, 0.5, 1.0]);
+
+        let mut field = 2.0_f32;
+        assert!(PropertyValue::Float(4.0).blend_into(&mut field, 0.5));
+        assert_eq!(field, 3.0);
+        assert!(!PropertyValue::Vec3(Vec3::ONE).blend_into(&mut field, 1.0));
+    }
+}
@@ -2,10 +2,21 @@ use bevy::prelude::{Quat, Vec3};
 use itertools::Itertools;
 use std::cmp::Ordering;
 
+/// How a [`CardinalQuatCurve`] starts at its first control and ends at its last one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplineBoundary {
+    /// The curve starts and ends at rest.
+    Clamped,
+    /// The curve starts towards the second control and ends coming from the second to last one.
+    #[default]
+    Natural,
+}
+
 #[derive(Clone, Debug)]
 pub struct CardinalQuatCurve {
     tension: f32,
-    controls: Vec<(Quat, f32)>
+    controls: Vec<(Quat, f32)>,
+    boundary: SplineBoundary,
 }
 
 impl CardinalQuatCurve {
@@ -17,7 +28,7 @@ impl CardinalQuatCurve {
     {
         let mut controls = control_points.into_iter().collect::<Vec<_>>();
 
-        assert!(-1.0 <= tension && tension <= 1.0);
+        assert!((-1.0..=1.0).contains(&tension));
         assert!(controls.len() >= 2);
 
         for window in controls.windows(2) {
@@ -26,7 +37,7 @@ impl CardinalQuatCurve {
         }
 
         // Canonicalize points
-        let mut a = controls[0].0.clone();
+        let mut a = controls[0].0;
         for (b, _) in controls.iter_mut().skip(1) {
             if a.dot(*b) < 0.0 {
                 *b = -*b;
@@ -34,7 +45,13 @@ impl CardinalQuatCurve {
             }
         }
 
-        CardinalQuatCurve { controls, tension }
+        CardinalQuatCurve { controls, tension, boundary: SplineBoundary::default() }
+    }
+
+    /// Sets how the curve behaves at its first and last controls.
+    pub fn with_boundary(mut self, boundary: SplineBoundary) -> Self {
+        self.boundary = boundary;
+        self
     }
 
     pub fn to_curve(&self) -> DeCasteljauQuatCurve {
@@ -43,7 +60,7 @@ impl CardinalQuatCurve {
         let control_points = self
             .controls
             .windows(3)
-            .map(|window| {
+            .flat_map(|window| {
                 let &[(q0, t0), (q1, t1), (q2, t2)] = window else { panic!("window iterator is incorrect") };
                 let h1 = t1 - t0;
                 let h2 = t2 - t1;
@@ -59,10 +76,17 @@ impl CardinalQuatCurve {
                     * (1.0-self.tension);
 
                 [exp_map(-omega * h1 / 3.0) * q1, exp_map(omega * h2 / 3.0) * q1]
-            }).flatten();
-
-        let first_control = [end_quaternion(self.controls[0].0, self.controls[1].0)].into_iter();
-        let last_control = [end_quaternion(self.controls[len-1].0, self.controls[len-2].0)].into_iter();
+            });
+
+        let (first_control, last_control) = match self.boundary {
+            SplineBoundary::Natural => (
+                end_quaternion(self.controls[0].0, self.controls[1].0),
+                end_quaternion(self.controls[len-1].0, self.controls[len-2].0),
+            ),
+            SplineBoundary::Clamped => (self.controls[0].0, self.controls[len-1].0),
+        };
+        let first_control = [first_control].into_iter();
+        let last_control = [last_control].into_iter();
 
         let control_points = first_control.chain(control_points).chain(last_control).tuples::<(Quat, Quat)>();
         
@@ -161,15 +185,17 @@ pub struct DeCasteljauQuatCurve {
 
 impl DeCasteljauQuatCurve {
 
+    /// The rotation at `t`, which is clamped to the times of the first and last controls.
     #[inline]
     pub fn position(&self, t: f32) -> Quat {
+        let t = t.clamp(self.times[0], *self.times.last().unwrap());
         let (segment, t) = self.segment(t);
-        return segment.position(t);
+        segment.position(t)
     }
 
     #[inline]
     pub fn segment(&self, t: f32) -> (&DeCasteljauQuatSegment, f32) {
-        let index = self.times.partition_point(|probe| *probe <= t).clamp(0, self.segments.len());
+        let index = self.times.partition_point(|probe| *probe <= t).clamp(1, self.segments.len());
         let segment = &self.segments[index-1];
         let t0 = self.times[index-1];
         let t1 = self.times[index];
@@ -177,6 +203,19 @@ impl DeCasteljauQuatCurve {
         (segment, t)
     }
 
+    /// This curve with `f` applied to each of its controls.
+    ///
+    /// Segments interpolate with slerps, so when `f` multiplies by unit quaternions, positions of
+    /// the new curve are `f` applied to the positions of this one.
+    pub fn map_controls(&self, f: impl Fn(Quat) -> Quat) -> Self {
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| DeCasteljauQuatSegment { coeff: segment.coeff.map(&f) })
+            .collect();
+        DeCasteljauQuatCurve { segments, times: self.times.clone() }
+    }
+
     #[inline]
     pub fn iter_positions(&self, subdivisions: usize) -> impl Iterator<Item = Quat> + '_ 
     {
@@ -206,7 +245,7 @@ mod tests {
             (Quat::from_axis_angle(Vec3::Z, PI/2.0), PI)
             ];
 
-        let curve = CardinalQuatCurve::new(0.0, quats.clone()).to_curve();
+        let curve = CardinalQuatCurve::new(0.0, quats).to_curve();
             
         for (q, t) in quats {
             let diff = curve.position(t) * q.inverse();
@@ -289,4 +328,26 @@ mod tests {
 
         assert!(error.abs() < ERROR_BOUND)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_boundaries() {
+        let quats = [
+            (Quat::IDENTITY, 0.0),
+            (Quat::from_axis_angle(Vec3::X, PI/2.0), 1.0),
+            (Quat::from_axis_angle(Vec3::Y, PI/2.0), 2.0),
+        ];
+        let natural = CardinalQuatCurve::new(0.0, quats).to_curve();
+        let clamped = CardinalQuatCurve::new(0.0, quats).with_boundary(SplineBoundary::Clamped).to_curve();
+
+        // Clamped curves start and end at rest
+        for (t, q) in [(0.01, quats[0].0), (1.99, quats[2].0)] {
+            assert!(clamped.position(t).angle_between(q) < 0.1 * natural.position(t).angle_between(q));
+        }
+
+        // Both hold their ends
+        for curve in [natural, clamped] {
+            assert!(curve.position(-1.0).abs_diff_eq(quats[0].0, ERROR_BOUND));
+            assert!(curve.position(3.0).abs_diff_eq(quats[2].0, ERROR_BOUND));
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! Retargeting of animations between skeletons with different proportions and joint names.
+
+use bevy::math::{Quat, Vec3};
+use bevy::reflect::{FromReflect, Reflect};
+use bevy::transform::components::Transform;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{AnimationClip, EntityPath, Keyframes, MotionWarpClip, Skeleton, VariableCurve};
+
+/// An error that occurred when retargeting an animation.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RetargetError {
+    #[error("no joint named {0:?} in the source skeleton")]
+    MissingSourceJoint(String),
+    #[error("no joint in the target skeleton for {0:?}")]
+    MissingTargetJoint(String),
+    #[error("{1:?} isn't below {0:?} in the {2} skeleton")]
+    InvalidLeg(String, String, &'static str),
+}
+
+/// How the joints of a source skeleton map to the joints of a target skeleton, to play animations
+/// authored on one with the other.
+///
+/// Rotations are retargeted relative to the rest poses: each target joint turns away from its rest
+/// pose by the same rotation, in model space, as its source joint. Both skeletons should have a
+/// similar rest pose, like a T-pose, and face the same way.
+#[derive(Reflect, FromReflect, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Retargeting {
+    /// Name of the target joint for each source joint. Source joints that aren't in the map are
+    /// retargeted to the target joint with the same name, if there's one.
+    #[serde(default)]
+    pub joint_names: HashMap<String, String>,
+    /// Source joint that moves the whole character, like the hips. Only its translation is
+    /// retargeted; if `None`, the translation of the source joints without parents is.
+    #[serde(default)]
+    pub root: Option<String>,
+    /// Top and bottom source joints of a leg, like the hip and the ankle. Translation of the root is
+    /// scaled by the length of the leg in the target skeleton over its length in the source
+    /// skeleton; if `None`, it isn't scaled.
+    #[serde(default)]
+    pub leg: Option<(String, String)>,
+}
+
+/// How the animation of a source joint is retargeted to a target joint.
+#[derive(Clone, Copy, Debug)]
+struct JointMapping {
+    /// Index of the target joint
+    target: usize,
+    /// Rotation from the rest space of the parent of the source joint to the rest space of the
+    /// parent of the target joint
+    parent: Quat,
+    /// Rotation from the rest rotation of the source joint to the rest rotation of the target joint
+    child: Quat,
+    source_rest_translation: Vec3,
+    target_rest_translation: Vec3,
+    /// Is the translation of this joint retargeted?
+    root: bool,
+}
+
+impl JointMapping {
+    fn rotation(&self, rotation: Quat) -> Quat {
+        (self.parent * rotation * self.child).normalize()
+    }
+
+    fn translation(&self, translation: Vec3, scale: f32) -> Vec3 {
+        self.target_rest_translation + self.parent * (translation - self.source_rest_translation) * scale
+    }
+}
+
+impl Retargeting {
+    /// Serializes this retargeting as RON.
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    /// Deserializes a retargeting from RON.
+    pub fn from_ron(description: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(description)
+    }
+
+    /// Name of the target joint for the source joint named `source`.
+    pub fn target_name<'a>(&'a self, source: &'a str) -> &'a str {
+        self.joint_names.get(source).map_or(source, String::as_str)
+    }
+
+    /// Length of the leg in `target` over its length in `source`, or 1 without a leg.
+    pub fn leg_scale(&self, source: &Skeleton, target: &Skeleton) -> Result<f32, RetargetError> {
+        self.skeleton_leg_scale(&SkeletonPair::new(source, target))
+    }
+
+    fn skeleton_leg_scale(&self, skeletons: &SkeletonPair) -> Result<f32, RetargetError> {
+        let Some((top, bottom)) = &self.leg else {
+            return Ok(1.0);
+        };
+        let source_length = leg_length(
+            skeletons.source,
+            skeletons.source_joint(top)?,
+            skeletons.source_joint(bottom)?,
+        )
+        .ok_or_else(|| RetargetError::InvalidLeg(top.clone(), bottom.clone(), "source"))?;
+        let target_length = leg_length(
+            skeletons.target,
+            skeletons.target_joint(self, top)?,
+            skeletons.target_joint(self, bottom)?,
+        )
+        .ok_or_else(|| RetargetError::InvalidLeg(top.clone(), bottom.clone(), "target"))?;
+        Ok(target_length / source_length)
+    }
+
+    /// `clip`, authored on `source`, retargeted to `target`.
+    ///
+    /// Joints of `clip` that aren't in `source`, or don't map to a joint of `target`, are dropped, as
+    /// are translations of joints other than the root and all scales, so target joints keep their
+    /// proportions. Keyframes of the retargeted clip aren't compressed.
+    pub fn retarget_clip(
+        &self,
+        clip: &AnimationClip,
+        source: &Skeleton,
+        target: &Skeleton,
+    ) -> Result<AnimationClip, RetargetError> {
+        let skeletons = SkeletonPair::new(source, target);
+        let mappings = self.joint_mappings(&skeletons)?;
+        let scale = self.skeleton_leg_scale(&skeletons)?;
+
+        let mut retargeted = AnimationClip::default();
+        for (path, id) in clip.paths() {
+            let Some(mapping) = mappings.get(path) else {
+                continue;
+            };
+            for curve in &clip.curves()[*id] {
+                let keyframes = match curve.keyframes.decompressed() {
+                    Keyframes::Rotation(rotations) => {
+                        Keyframes::Rotation(rotations.into_iter().map(|rotation| mapping.rotation(rotation)).collect())
+                    }
+                    Keyframes::Translation(translations) if mapping.root => Keyframes::Translation(
+                        translations.into_iter().map(|translation| mapping.translation(translation, scale)).collect(),
+                    ),
+                    _ => continue,
+                };
+                retargeted.add_curve_to_path(
+                    target.paths()[mapping.target].clone(),
+                    VariableCurve { keyframe_timestamps: curve.keyframe_timestamps.clone(), keyframes },
+                );
+            }
+        }
+        retargeted.copy_property_curves(clip, |path| match mappings.get(path) {
+            Some(mapping) => target.paths()[mapping.target].clone(),
+            None => path.clone(),
+        });
+        retargeted.duration = clip.duration();
+        Ok(retargeted)
+    }
+
+    /// `warp`, authored on `source`, retargeted to `target`, to warp clips retargeted with
+    /// [`Retargeting::retarget_clip`].
+    ///
+    /// Joint limits are dropped, since they belong to `source`; use
+    /// [`MotionWarpClip::with_joint_limits`] to limit the joints of `target`.
+    pub fn retarget_warp(
+        &self,
+        warp: &MotionWarpClip,
+        source: &Skeleton,
+        target: &Skeleton,
+    ) -> Result<MotionWarpClip, RetargetError> {
+        let mappings = self.joint_mappings(&SkeletonPair::new(source, target))?;
+
+        let mut retargeted = warp.clone();
+        retargeted.curves.clear();
+        retargeted.paths.clear();
+        for (path, id) in &warp.paths {
+            let Some(mapping) = mappings.get(path) else {
+                continue;
+            };
+            retargeted.paths.insert(target.paths()[mapping.target].clone(), retargeted.curves.len());
+            retargeted.curves.push(warp.curves[*id].retargeted(mapping.parent, mapping.child));
+        }
+        Ok(retargeted)
+    }
+
+    /// How each joint of the source skeleton that maps to a joint of the target skeleton is
+    /// retargeted, by source path.
+    fn joint_mappings(&self, skeletons: &SkeletonPair) -> Result<HashMap<EntityPath, JointMapping>, RetargetError> {
+        let SkeletonPair { source, target, .. } = *skeletons;
+        let root = match &self.root {
+            Some(root) => {
+                let index = skeletons.source_joint(root)?;
+                skeletons.target_joint(self, root)?;
+                Some(index)
+            }
+            None => None,
+        };
+
+        let source_model = source.model_rest_transforms();
+        let target_model = target.model_rest_transforms();
+        let model_rotation = |model: &[Transform], parent: Option<usize>| {
+            parent.map_or(Quat::IDENTITY, |parent| model[parent].rotation)
+        };
+
+        let mut mappings = HashMap::default();
+        for index in 0..source.len() {
+            let Some(name) = source.name(index) else { continue };
+            let Some(&target_index) = skeletons.target_joints.get(self.target_name(name.as_str())) else {
+                continue;
+            };
+            let source_parent = model_rotation(&source_model, source.parents()[index]);
+            let target_parent = model_rotation(&target_model, target.parents()[target_index]);
+            let mapping = JointMapping {
+                target: target_index,
+                parent: target_parent.inverse() * source_parent,
+                child: source_model[index].rotation.inverse() * target_model[target_index].rotation,
+                source_rest_translation: source.rest_transforms()[index].translation,
+                target_rest_translation: target.rest_transforms()[target_index].translation,
+                root: root.map_or(source.parents()[index].is_none(), |root| root == index),
+            };
+            mappings.insert(source.paths()[index].clone(), mapping);
+        }
+        Ok(mappings)
+    }
+}
+
+/// The skeletons an animation is retargeted between, with their joints by name.
+struct SkeletonPair<'a> {
+    source: &'a Skeleton,
+    target: &'a Skeleton,
+    source_joints: HashMap<&'a str, usize>,
+    target_joints: HashMap<&'a str, usize>,
+}
+
+impl<'a> SkeletonPair<'a> {
+    fn new(source: &'a Skeleton, target: &'a Skeleton) -> Self {
+        SkeletonPair { source, target, source_joints: joints_by_name(source), target_joints: joints_by_name(target) }
+    }
+
+    fn source_joint(&self, name: &str) -> Result<usize, RetargetError> {
+        self.source_joints.get(name).copied().ok_or_else(|| RetargetError::MissingSourceJoint(name.to_string()))
+    }
+
+    /// The target joint that the source joint named `source` maps to.
+    fn target_joint(&self, retargeting: &Retargeting, source: &str) -> Result<usize, RetargetError> {
+        let name = retargeting.target_name(source);
+        self.target_joints.get(name).copied().ok_or_else(|| RetargetError::MissingTargetJoint(source.to_string()))
+    }
+}
+
+/// Index of each joint of `skeleton` by name. The first of the joints with the same name is kept.
+fn joints_by_name(skeleton: &Skeleton) -> HashMap<&str, usize> {
+    let mut joints = HashMap::default();
+    for index in (0..skeleton.len()).rev() {
+        if let Some(name) = skeleton.name(index) {
+            joints.insert(name.as_str(), index);
+        }
+    }
+    joints
+}
+
+/// Sum of the lengths of the bones from `top` down to `bottom` in the rest pose, or `None` if
+/// `bottom` isn't below `top`.
+fn leg_length(skeleton: &Skeleton, top: usize, bottom: usize) -> Option<f32> {
+    let model = skeleton.model_rest_transforms();
+    let mut length = 0.0;
+    let mut joint = bottom;
+    while joint != top {
+        let parent = skeleton.parents()[joint]?;
+        length += model[joint].translation.distance(model[parent].translation);
+        joint = parent;
+    }
+    (top != bottom).then_some(length)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::core::Name;
+
+    use super::*;
+    use crate::builder::{MotionWarpClipBuilder, MotionWarpClipFrame, MotionWarpCurveFrame, WarpBoundary};
+
+    fn path(parts: &[&str]) -> EntityPath {
+        EntityPath { parts: parts.iter().map(|part| Name::new(part.to_string())).collect() }
+    }
+
+    /// A hip, knee and ankle, with the knee turned by `knee` in the rest pose.
+    fn leg(names: [&str; 3], length: f32, knee: Quat) -> Skeleton {
+        Skeleton::new(
+            vec![path(&names[..1]), path(&names[..2]), path(&names)],
+            vec![
+                Transform::from_xyz(0.0, length, 0.0),
+                Transform::from_xyz(0.0, -length / 2.0, 0.0).with_rotation(knee),
+                Transform::from_translation(knee.inverse() * Vec3::new(0.0, -length / 2.0, 0.0)),
+            ],
+        )
+    }
+
+    fn retargeting() -> Retargeting {
+        Retargeting {
+            joint_names: [("hip", "Hips"), ("knee", "Knee")]
+                .into_iter()
+                .map(|(source, target)| (source.to_string(), target.to_string()))
+                .collect(),
+            root: Some("hip".to_string()),
+            leg: Some(("hip".to_string(), "ankle".to_string())),
+        }
+    }
+
+    #[test]
+    fn retargets_clip() {
+        let source = leg(["hip", "knee", "ankle"], 2.0, Quat::IDENTITY);
+        let target = leg(["Hips", "Knee", "ankle"], 1.0, Quat::from_rotation_z(0.5));
+        let retargeting = retargeting();
+        assert_eq!(retargeting.leg_scale(&source, &target), Ok(0.5));
+
+        let mut clip = AnimationClip::default();
+        clip.add_curve_to_path(
+            path(&["hip"]),
+            VariableCurve {
+                keyframe_timestamps: vec![0.0, 1.0],
+                keyframes: Keyframes::Translation(vec![Vec3::Y * 2.0, Vec3::new(1.0, 2.0, 0.0)]),
+            },
+        );
+        clip.add_curve_to_path(
+            path(&["hip", "knee"]),
+            VariableCurve {
+                keyframe_timestamps: vec![0.0, 1.0],
+                keyframes: Keyframes::Rotation(vec![Quat::IDENTITY, Quat::from_rotation_x(1.0)]),
+            },
+        );
+        clip.add_curve_to_path(
+            path(&["hip", "knee", "ankle"]),
+            VariableCurve { keyframe_timestamps: vec![0.0], keyframes: Keyframes::Scale(vec![Vec3::ONE]) },
+        );
+        let retargeted = retargeting.retarget_clip(&clip, &source, &target).unwrap();
+
+        assert_eq!(retargeted.paths().len(), 2);
+        assert_eq!(retargeted.duration(), 1.0);
+        let hips = &retargeted.get_curves_by_path(&path(&["Hips"])).unwrap()[0];
+        assert!(hips.translation_at(1.0).unwrap().abs_diff_eq(Vec3::new(0.5, 1.0, 0.0), 1e-6));
+        let knee = &retargeted.get_curves_by_path(&path(&["Hips", "Knee"])).unwrap()[0];
+        // The target knee is at rest when the source knee is, and turns the same way in model space
+        assert!(knee.rotation_at(0.0).unwrap().abs_diff_eq(Quat::from_rotation_z(0.5), 1e-6));
+        let turn = knee.rotation_at(1.0).unwrap() * Quat::from_rotation_z(0.5).inverse();
+        assert!(turn.abs_diff_eq(Quat::from_rotation_x(1.0), 1e-6), "{turn}");
+
+        assert_eq!(
+            Retargeting { root: Some("pelvis".to_string()), ..retargeting.clone() }.retarget_clip(&clip, &source, &target).unwrap_err(),
+            RetargetError::MissingSourceJoint("pelvis".to_string()),
+        );
+        assert_eq!(
+            Retargeting { leg: Some(("knee".to_string(), "hip".to_string())), ..retargeting }.leg_scale(&source, &target),
+            Err(RetargetError::InvalidLeg("knee".to_string(), "hip".to_string(), "source")),
+        );
+    }
+
+    #[test]
+    fn retargets_warp() {
+        let source = leg(["hip", "knee", "ankle"], 2.0, Quat::from_rotation_y(0.3));
+        let target = leg(["Hips", "Knee", "ankle"], 1.0, Quat::from_rotation_z(0.5));
+        let retargeting = retargeting();
+        let knee = path(&["hip", "knee"]);
+
+        let mut clip = AnimationClip::default();
+        clip.add_curve_to_path(
+            knee.clone(),
+            VariableCurve {
+                keyframe_timestamps: vec![0.0, 1.0],
+                keyframes: Keyframes::Rotation(vec![Quat::from_rotation_y(0.3), Quat::from_rotation_x(1.0)]),
+            },
+        );
+        let frame = |time, rotation| MotionWarpClipFrame {
+            time,
+            warp_time: Some(time),
+            map: [(knee.clone(), MotionWarpCurveFrame { rotation, fix_a: false })].into_iter().collect(),
+        };
+        let warp = MotionWarpClipBuilder {
+            clips: vec![frame(0.3, Quat::from_rotation_y(0.8)), frame(0.7, Quat::from_rotation_x(0.5))],
+            start_time: 0.0,
+            end_time: 1.0,
+            blend_margin: 0.1,
+            tension: 0.5,
+            boundary: WarpBoundary::Clamped,
+        }
+        .build(&clip);
+
+        let retargeted_clip = retargeting.retarget_clip(&clip, &source, &target).unwrap();
+        let retargeted_warp = retargeting.retarget_warp(&warp, &source, &target).unwrap();
+        let curve = &retargeted_warp.curves[retargeted_warp.paths[&path(&["Hips", "Knee"])]];
+        let mapping = retargeting.joint_mappings(&SkeletonPair::new(&source, &target)).unwrap()[&knee];
+        for t in [0.2, 0.3, 0.5, 0.7] {
+            // Warping the retargeted clip is retargeting the warped clip
+            let theta = retargeted_clip.get_joint_rotation_at(&path(&["Hips", "Knee"]), t);
+            let warped = retargeted_warp.theta_blend(curve, t, theta);
+            let expected = mapping.rotation(warp.theta_blend(&warp.curves[0], t, clip.get_joint_rotation_at(&knee, t)));
+            assert!(warped.abs_diff_eq(expected, 1e-4) || warped.abs_diff_eq(-expected, 1e-4), "{t}: {warped} {expected}");
+        }
+    }
+}
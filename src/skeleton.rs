@@ -0,0 +1,239 @@
+//! Skeletons, with the rest pose of their joints and the limits of their rotations.
+
+use bevy::core::Name;
+use bevy::math::{Mat4, Quat};
+use bevy::reflect::TypeUuid;
+use bevy::transform::components::Transform;
+use bevy::utils::HashMap;
+
+use crate::{EntityPath, JointLimit, JointPose, Pose};
+
+/// The joints of a skinned model, with their hierarchy, rest pose and rotation limits.
+///
+/// Loaded by [`crate::GltfLoader`] for each skin, labeled `Skeleton<index>`, so that joints can be
+/// worked with without walking the entity hierarchy. Joints are in the order of the skin, which is
+/// the order of [`bevy::render::mesh::skinning::SkinnedMesh::joints`].
+///
+/// Joint limits are enforced on warped rotations by [`crate::MotionWarpClip::with_joint_limits`].
+/// Saved by the editor as RON, with [`Skeleton::limits_to_ron`].
+#[derive(Clone, Debug, Default, TypeUuid)]
+#[uuid = "3c1e0f9d-5b7a-4f62-8d2e-91a4c6b0e7f3"]
+pub struct Skeleton {
+    paths: Vec<EntityPath>,
+    parents: Vec<Option<usize>>,
+    rest_transforms: Vec<Transform>,
+    inverse_bind_matrices: Vec<Mat4>,
+    limits: Vec<Option<JointLimit>>,
+    indices: HashMap<EntityPath, usize>,
+}
+
+impl Skeleton {
+    /// A skeleton of the joints at `paths`, with their local transforms in the rest pose.
+    ///
+    /// The parent of each joint is the closest joint above it. Inverse bind matrices are computed
+    /// from the rest pose, relative to the space of the joints without parents.
+    pub fn new(paths: Vec<EntityPath>, rest_transforms: Vec<Transform>) -> Self {
+        assert_eq!(paths.len(), rest_transforms.len());
+        let indices: HashMap<EntityPath, usize> =
+            paths.iter().enumerate().map(|(index, path)| (path.clone(), index)).collect();
+        let parents = paths
+            .iter()
+            .map(|path| {
+                (1..path.parts.len())
+                    .rev()
+                    .find_map(|length| indices.get(&EntityPath { parts: path.parts[..length].to_vec() }).copied())
+            })
+            .collect();
+        let limits = vec![None; paths.len()];
+        let mut skeleton =
+            Skeleton { paths, parents, rest_transforms, inverse_bind_matrices: Vec::new(), limits, indices };
+        skeleton.inverse_bind_matrices = skeleton
+            .model_rest_transforms()
+            .iter()
+            .map(|transform| transform.compute_matrix().inverse())
+            .collect();
+        skeleton
+    }
+
+    /// This skeleton with the inverse bind matrices of its skin.
+    pub fn with_inverse_bind_matrices(mut self, inverse_bind_matrices: Vec<Mat4>) -> Self {
+        assert_eq!(inverse_bind_matrices.len(), self.len());
+        self.inverse_bind_matrices = inverse_bind_matrices;
+        self
+    }
+
+    /// Number of joints.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Does this skeleton have no joints?
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Path of each joint, like the paths of the [`crate::AnimationClip`]s animating it.
+    pub fn paths(&self) -> &[EntityPath] {
+        &self.paths
+    }
+
+    /// Name of the joint at `index`, or `None` if its path is empty.
+    pub fn name(&self, index: usize) -> Option<&Name> {
+        self.paths[index].parts.last()
+    }
+
+    /// Index of the parent of each joint, if it has one in this skeleton.
+    pub fn parents(&self) -> &[Option<usize>] {
+        &self.parents
+    }
+
+    /// Local transform of each joint in the rest pose.
+    pub fn rest_transforms(&self) -> &[Transform] {
+        &self.rest_transforms
+    }
+
+    /// Inverse bind matrix of each joint.
+    pub fn inverse_bind_matrices(&self) -> &[Mat4] {
+        &self.inverse_bind_matrices
+    }
+
+    /// Index of the joint at `path`.
+    pub fn index_of(&self, path: &EntityPath) -> Option<usize> {
+        self.indices.get(path).copied()
+    }
+
+    /// Transform of each joint in the rest pose, relative to the joints without parents.
+    pub fn model_rest_transforms(&self) -> Vec<Transform> {
+        let mut transforms: Vec<Option<Transform>> = vec![None; self.len()];
+        for index in 0..self.len() {
+            self.model_rest_transform(index, &mut transforms);
+        }
+        transforms.into_iter().map(Option::unwrap).collect()
+    }
+
+    fn model_rest_transform(&self, index: usize, transforms: &mut [Option<Transform>]) -> Transform {
+        if let Some(transform) = transforms[index] {
+            return transform;
+        }
+        let local = self.rest_transforms[index];
+        let transform = match self.parents[index] {
+            Some(parent) => self.model_rest_transform(parent, transforms).mul_transform(local),
+            None => local,
+        };
+        transforms[index] = Some(transform);
+        transform
+    }
+
+    /// Limit of the local rotation of each joint relative to its rest rotation, if it's limited.
+    pub fn limits(&self) -> &[Option<JointLimit>] {
+        &self.limits
+    }
+
+    /// Limits the local rotation of the joint at `index` relative to its rest rotation, or removes
+    /// its limit.
+    pub fn set_limit(&mut self, index: usize, limit: Option<JointLimit>) {
+        self.limits[index] = limit;
+    }
+
+    /// Does the local `rotation` of the joint at `index` stay within its limit?
+    pub fn allows(&self, index: usize, rotation: Quat) -> bool {
+        self.limits[index].map_or(true, |limit| limit.contains_relative(self.rest_transforms[index].rotation, rotation))
+    }
+
+    /// The closest local rotation to `rotation` within the limit of the joint at `index`.
+    pub fn clamp(&self, index: usize, rotation: Quat) -> Quat {
+        match self.limits[index] {
+            Some(limit) => limit.clamp_relative(self.rest_transforms[index].rotation, rotation),
+            None => rotation,
+        }
+    }
+
+    /// Serializes the limits of the limited joints as RON, by path.
+    pub fn limits_to_ron(&self) -> Result<String, ron::Error> {
+        let limits: Vec<(&EntityPath, JointLimit)> =
+            self.paths.iter().zip(&self.limits).filter_map(|(path, limit)| Some((path, (*limit)?))).collect();
+        ron::ser::to_string_pretty(&limits, ron::ser::PrettyConfig::default())
+    }
+
+    /// Sets the limits of the joints from RON saved by [`Skeleton::limits_to_ron`]. Other joints
+    /// keep their limits, and paths that aren't in this skeleton are ignored.
+    pub fn load_limits_ron(&mut self, limits: &str) -> Result<(), ron::error::SpannedError> {
+        let limits: Vec<(EntityPath, JointLimit)> = ron::from_str(limits)?;
+        for (path, limit) in limits {
+            if let Some(index) = self.index_of(&path) {
+                self.limits[index] = Some(limit);
+            }
+        }
+        Ok(())
+    }
+
+    /// The rest pose of every joint.
+    pub fn rest_pose(&self) -> Pose {
+        let joints = self
+            .paths
+            .iter()
+            .zip(&self.rest_transforms)
+            .map(|(path, transform)| {
+                let joint = JointPose {
+                    translation: Some(transform.translation),
+                    rotation: Some(transform.rotation),
+                    scale: Some(transform.scale),
+                };
+                (path.clone(), joint)
+            })
+            .collect();
+        Pose { joints }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec3;
+
+    use super::*;
+
+    fn path(parts: &[&str]) -> EntityPath {
+        EntityPath { parts: parts.iter().map(|part| Name::new(part.to_string())).collect() }
+    }
+
+    #[test]
+    fn skeleton_hierarchy() {
+        // The knee comes first, and the hip isn't a joint
+        let skeleton = Skeleton::new(
+            vec![path(&["root", "hip", "knee"]), path(&["root"]), path(&["root", "hip", "knee", "foot"])],
+            vec![Transform::from_xyz(0.0, 1.0, 0.0), Transform::from_xyz(0.0, 2.0, 0.0), Transform::from_xyz(0.0, 1.0, 0.0)],
+        );
+        assert_eq!(skeleton.parents(), [Some(1), None, Some(0)]);
+        assert_eq!(skeleton.index_of(&path(&["root", "hip", "knee", "foot"])), Some(2));
+        assert_eq!(skeleton.name(0).map(Name::as_str), Some("knee"));
+        assert_eq!(skeleton.model_rest_transforms()[2].translation, Vec3::new(0.0, 4.0, 0.0));
+        let foot = skeleton.inverse_bind_matrices()[2].transform_point3(Vec3::new(0.0, 4.0, 0.0));
+        assert!(foot.abs_diff_eq(Vec3::ZERO, 1e-6));
+        assert_eq!(skeleton.rest_pose().get(&path(&["root"])).unwrap().translation, Some(Vec3::Y * 2.0));
+    }
+
+    #[test]
+    fn joint_limits() {
+        let mut skeleton = Skeleton::new(
+            vec![path(&["hip"]), path(&["hip", "knee"])],
+            vec![Transform::IDENTITY, Transform::from_rotation(Quat::from_rotation_x(1.0))],
+        );
+        let limit = JointLimit { max_swing: 0.5, min_twist: -0.2, max_twist: 0.2, ..Default::default() };
+        skeleton.set_limit(1, Some(limit));
+        assert!(skeleton.allows(1, Quat::from_rotation_x(1.4)));
+        assert!(!skeleton.allows(1, Quat::from_rotation_x(1.6)));
+        assert!(skeleton.allows(0, Quat::from_rotation_x(1.6)));
+        let clamped = skeleton.clamp(1, Quat::from_rotation_x(2.0));
+        assert!(clamped.abs_diff_eq(Quat::from_rotation_x(1.5), 1e-4), "{clamped}");
+
+        let mut loaded = Skeleton::new(skeleton.paths().to_vec(), skeleton.rest_transforms().to_vec());
+        loaded.load_limits_ron(&skeleton.limits_to_ron().unwrap()).unwrap();
+        assert_eq!(loaded.limits(), [None, Some(limit)]);
+    }
+
+    #[test]
+    fn empty_path_has_no_name() {
+        let skeleton = Skeleton::new(vec![EntityPath::default()], vec![Transform::IDENTITY]);
+        assert_eq!(skeleton.name(0), None);
+    }
+}